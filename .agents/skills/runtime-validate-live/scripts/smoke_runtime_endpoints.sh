@@ -39,6 +39,16 @@ if (typeof meta.scanTime !== 'string' || meta.scanTime.length === 0) {
 console.log(`meta: ready=${meta.ready} sqsEnabled=${meta.sqsEnabled} scanTime=${meta.scanTime}`);
 NODE
 
+readyz_json="$(curl -sS "${BASE_URL}/readyz")"
+READYZ_JSON="${readyz_json}" node <<'NODE'
+const readyz = JSON.parse(process.env.READYZ_JSON || '{}');
+if (readyz.ready !== true) {
+  const reasons = (readyz.reasons || []).map((reason) => reason.code).join(', ');
+  throw new Error(`readyz not ready: ${reasons || 'unknown'}`);
+}
+console.log('readyz: ready');
+NODE
+
 traffic_url="${BASE_URL}/v1/traffic/adsbx?lat=${TRAFFIC_LAT}&lon=${TRAFFIC_LON}&radiusNm=${TRAFFIC_RADIUS_NM}&limit=120"
 traffic_json="$(curl -fsS "${traffic_url}")"
 TRAFFIC_JSON="${traffic_json}" node <<'NODE'
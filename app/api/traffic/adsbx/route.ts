@@ -1,5 +1,8 @@
 import { NextRequest, NextResponse } from 'next/server';
 
+import { forwardedForHeaders, runtimeApiKeyHeaders } from '@/lib/runtime-api-key';
+import { traceContextHeaders } from '@/lib/trace-context';
+
 export const runtime = 'nodejs';
 export const dynamic = 'force-dynamic';
 
@@ -35,7 +38,7 @@ function upstreamTrafficUrl(request: NextRequest): string {
   return upstreamUrl.toString();
 }
 
-async function fetchWithTimeout(url: string): Promise<Response> {
+async function fetchWithTimeout(url: string, request: NextRequest): Promise<Response> {
   const controller = new AbortController();
   const timeoutId = setTimeout(() => controller.abort(), REQUEST_TIMEOUT_MS);
   try {
@@ -44,7 +47,10 @@ async function fetchWithTimeout(url: string): Promise<Response> {
       signal: controller.signal,
       headers: {
         accept: 'application/json',
-        'user-agent': 'approach-viz/1.0'
+        'user-agent': 'approach-viz/1.0',
+        ...traceContextHeaders(request),
+        ...forwardedForHeaders(request),
+        ...runtimeApiKeyHeaders()
       }
     });
   } finally {
@@ -63,7 +69,7 @@ export async function GET(request: NextRequest) {
   }
 
   try {
-    const upstreamResponse = await fetchWithTimeout(upstreamTrafficUrl(request));
+    const upstreamResponse = await fetchWithTimeout(upstreamTrafficUrl(request), request);
     const body = await upstreamResponse.text();
     const contentType = upstreamResponse.headers.get('content-type') || 'application/json';
     return new NextResponse(body, {
@@ -1,5 +1,8 @@
 import { NextRequest, NextResponse } from 'next/server';
 
+import { forwardedForHeaders, runtimeApiKeyHeaders } from '@/lib/runtime-api-key';
+import { traceContextHeaders } from '@/lib/trace-context';
+
 export const runtime = 'nodejs';
 export const dynamic = 'force-dynamic';
 
@@ -20,7 +23,7 @@ function clamp(value: number, min: number, max: number): number {
   return Math.min(max, Math.max(min, value));
 }
 
-async function fetchWithTimeout(url: string): Promise<Response> {
+async function fetchWithTimeout(url: string, request: NextRequest): Promise<Response> {
   const controller = new AbortController();
   const timeoutId = setTimeout(() => controller.abort(), REQUEST_TIMEOUT_MS);
   try {
@@ -28,8 +31,11 @@ async function fetchWithTimeout(url: string): Promise<Response> {
       cache: 'no-store',
       signal: controller.signal,
       headers: {
-        accept: '*/*',
-        'user-agent': 'approach-viz/1.0'
+        accept: request.headers.get('accept') || '*/*',
+        'user-agent': 'approach-viz/1.0',
+        ...traceContextHeaders(request),
+        ...forwardedForHeaders(request),
+        ...runtimeApiKeyHeaders()
       }
     });
   } finally {
@@ -76,9 +82,12 @@ export async function GET(request: NextRequest) {
   );
 
   try {
-    let upstreamResponse = await fetchWithTimeout(upstreamEchoTopUrl(lat, lon, maxRangeNm));
+    let upstreamResponse = await fetchWithTimeout(upstreamEchoTopUrl(lat, lon, maxRangeNm), request);
     if (upstreamResponse.status === 404) {
-      upstreamResponse = await fetchWithTimeout(upstreamLegacyEchoTopUrl(lat, lon, maxRangeNm));
+      upstreamResponse = await fetchWithTimeout(
+        upstreamLegacyEchoTopUrl(lat, lon, maxRangeNm),
+        request
+      );
     }
 
     if (!upstreamResponse.ok) {
@@ -97,17 +106,19 @@ export async function GET(request: NextRequest) {
       );
     }
 
-    const payload = await upstreamResponse.json();
+    const payload = await upstreamResponse.arrayBuffer();
     const headers = new Headers();
     headers.set('Cache-Control', 'no-store');
-    headers.set('Content-Type', 'application/json');
+    headers.set('Content-Type', upstreamResponse.headers.get('content-type') ?? 'application/json');
+    const vary = upstreamResponse.headers.get('vary');
+    if (vary) headers.set('Vary', vary);
 
     const scanTime = upstreamResponse.headers.get('x-av-scan-time');
     if (scanTime) headers.set('X-AV-SCAN-TIME', scanTime);
     const generatedAt = upstreamResponse.headers.get('x-av-generated-at');
     if (generatedAt) headers.set('X-AV-GENERATED-AT', generatedAt);
 
-    return new NextResponse(JSON.stringify(payload), {
+    return new NextResponse(payload, {
       status: 200,
       headers
     });
@@ -1,5 +1,8 @@
 import { NextRequest, NextResponse } from 'next/server';
 
+import { forwardedForHeaders, runtimeApiKeyHeaders } from '@/lib/runtime-api-key';
+import { traceContextHeaders } from '@/lib/trace-context';
+
 export const runtime = 'nodejs';
 export const dynamic = 'force-dynamic';
 
@@ -21,7 +24,7 @@ function clamp(value: number, min: number, max: number): number {
   return Math.min(max, Math.max(min, value));
 }
 
-async function fetchWithTimeout(url: string): Promise<Response> {
+async function fetchWithTimeout(url: string, request: NextRequest): Promise<Response> {
   const controller = new AbortController();
   const timeoutId = setTimeout(() => controller.abort(), REQUEST_TIMEOUT_MS);
   try {
@@ -29,8 +32,11 @@ async function fetchWithTimeout(url: string): Promise<Response> {
       cache: 'no-store',
       signal: controller.signal,
       headers: {
-        accept: '*/*',
-        'user-agent': 'approach-viz/1.0'
+        accept: request.headers.get('accept') || '*/*',
+        'user-agent': 'approach-viz/1.0',
+        ...traceContextHeaders(request),
+        ...forwardedForHeaders(request),
+        ...runtimeApiKeyHeaders()
       }
     });
   } finally {
@@ -92,10 +98,11 @@ export async function GET(request: NextRequest) {
   const upstreamUrl = upstreamVolumeUrl(lat, lon, minDbz, maxRangeNm);
 
   try {
-    let upstreamResponse = await fetchWithTimeout(upstreamUrl);
+    let upstreamResponse = await fetchWithTimeout(upstreamUrl, request);
     if (upstreamResponse.status === 404) {
       upstreamResponse = await fetchWithTimeout(
-        upstreamLegacyVolumeUrl(lat, lon, minDbz, maxRangeNm)
+        upstreamLegacyVolumeUrl(lat, lon, minDbz, maxRangeNm),
+        request
       );
     }
     if (!upstreamResponse.ok) {
@@ -42,6 +42,19 @@ interface LiveTrafficAircraft {
   groundSpeedKt: number | null;
   trackDeg: number | null;
   lastSeenSeconds: number | null;
+  squawk?: string | null;
+  baroRateFpm?: number | null;
+  geomRateFpm?: number | null;
+  category?: string | null;
+  selectedAltitudeFeet?: number | null;
+  navAltitudeFeet?: number | null;
+  selectedHeadingDeg?: number | null;
+  magHeadingDeg?: number | null;
+  trueHeadingDeg?: number | null;
+  iasKt?: number | null;
+  tasKt?: number | null;
+  nic?: number | null;
+  nacP?: number | null;
 }
 
 interface LiveTrafficHistoryPoint {
@@ -9,6 +9,7 @@ import {
   MRMS_BINARY_MAGIC,
   MRMS_BINARY_V2_VERSION,
   MRMS_BINARY_V3_VERSION,
+  MRMS_BINARY_V4_VERSION,
   MRMS_BINARY_V2_RECORD_BYTES,
   MRMS_BINARY_BASE_URL,
   MRMS_LEVEL_TAGS
@@ -51,7 +52,7 @@ function decodeBinaryPayload(bytes: ArrayBuffer): NexradVolumePayload {
   }
 
   const version = view.getUint16(4, true);
-  if (version !== MRMS_BINARY_V2_VERSION && version !== MRMS_BINARY_V3_VERSION) {
+  if (version < MRMS_BINARY_V2_VERSION || version > MRMS_BINARY_V4_VERSION) {
     throw new Error(`Unsupported MRMS payload version (${version}).`);
   }
 
@@ -63,6 +64,11 @@ function decodeBinaryPayload(bytes: ArrayBuffer): NexradVolumePayload {
   const scanTimeMs = readInt64LittleEndian(view, 28);
   const footprintXNm = view.getUint16(36, true) / 1000;
   const footprintYNm = view.getUint16(38, true) / 1000;
+  // Level-present bitmask (bit i = level i) from v4 headers; v2/v3 imply
+  // every level is present.
+  const hasLevelMask = version >= MRMS_BINARY_V4_VERSION && headerBytes >= 72;
+  const levelMaskLow = hasLevelMask ? view.getUint32(64, true) : 0xffffffff;
+  const levelMaskHigh = hasLevelMask ? view.getUint32(68, true) : 0xffffffff;
   const defaultRecordBytes = MRMS_BINARY_V2_RECORD_BYTES;
   const recordBytes = recordBytesFromHeader > 0 ? recordBytesFromHeader : defaultRecordBytes;
   if (recordBytes < MRMS_BINARY_V2_RECORD_BYTES) {
@@ -126,7 +132,8 @@ function decodeBinaryPayload(bytes: ArrayBuffer): NexradVolumePayload {
       elevationAngleDeg: Number.isFinite(elevation) ? elevation : index,
       sourceKey: `mrms-binary://${scanTime}/${levelTag}`,
       scanTime,
-      voxelCount: voxelCountForLayer
+      voxelCount: voxelCountForLayer,
+      present: (((index < 32 ? levelMaskLow : levelMaskHigh) >>> (index % 32)) & 1) === 1
     };
   });
 
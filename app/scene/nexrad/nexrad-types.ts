@@ -13,6 +13,7 @@ export const MIN_VOXEL_HEIGHT_NM = 0.04;
 export const MRMS_BINARY_MAGIC = 'AVMR';
 export const MRMS_BINARY_V2_VERSION = 2;
 export const MRMS_BINARY_V3_VERSION = 3;
+export const MRMS_BINARY_V4_VERSION = 4;
 export const MRMS_BINARY_V2_RECORD_BYTES = 20;
 export const MRMS_BINARY_BASE_URL = process.env.NEXT_PUBLIC_MRMS_BINARY_BASE_URL?.trim() ?? '';
 export const MRMS_LEVEL_TAGS = [
@@ -97,6 +98,8 @@ export interface NexradLayerSummary {
   sourceKey: string;
   scanTime: string;
   voxelCount: number;
+  /** False when the level was missing from a partial scan and not yet patched in. */
+  present?: boolean;
 }
 
 export interface NexradVolumePayload {
@@ -0,0 +1,18 @@
+/**
+ * `x-api-key` header for runtime service requests when `RUNTIME_API_KEY` is
+ * set, i.e. when the runtime is configured with `RUNTIME_API_KEYS`.
+ */
+export function runtimeApiKeyHeaders(): Record<string, string> {
+  const key = process.env.RUNTIME_API_KEY?.trim();
+  return key ? { 'x-api-key': key } : {};
+}
+
+/**
+ * `x-forwarded-for` carrying the browser's IP, so the runtime rate-limits
+ * each client behind this proxy separately instead of sharing one budget.
+ * Only the last incoming entry, appended by our own host, is trusted.
+ */
+export function forwardedForHeaders(request: Request): Record<string, string> {
+  const clientIp = request.headers.get('x-forwarded-for')?.split(',').pop()?.trim();
+  return clientIp ? { 'x-forwarded-for': clientIp } : {};
+}
@@ -0,0 +1,16 @@
+/** W3C trace-context headers forwarded from browser requests to the runtime service. */
+const TRACE_CONTEXT_HEADERS = ['traceparent', 'tracestate'] as const;
+
+/**
+ * Copies incoming `traceparent` / `tracestate` so the runtime continues the
+ * caller's trace. When dd-trace is active it injects its own proxy span into
+ * outgoing fetches, which takes precedence over these forwarded values.
+ */
+export function traceContextHeaders(request: Request): Record<string, string> {
+  const headers: Record<string, string> = {};
+  for (const name of TRACE_CONTEXT_HEADERS) {
+    const value = request.headers.get(name);
+    if (value) headers[name] = value;
+  }
+  return headers;
+}
@@ -13,7 +13,10 @@ const DEFAULT_MRMS_MAX_RANGE_NM = 120;
 const WIRE_MAGIC = 'AVMR';
 const WIRE_V2_VERSION = 2;
 const WIRE_V3_VERSION = 3;
+const WIRE_V4_VERSION = 4;
+const WIRE_MEDIA_TYPE_PREFIX = 'application/vnd.approach-viz.mrms.v';
 const WIRE_HEADER_BYTES = 64;
+const WIRE_V4_HEADER_BYTES = 72;
 const WIRE_RECORD_BYTES = 20;
 
 function envNumber(name: string, fallback: number): number {
@@ -30,7 +33,11 @@ function runtimeBaseUrl(): string {
   return (process.env.RUNTIME_INTEGRATION_BASE_URL || DEFAULT_RUNTIME_BASE_URL).replace(/\/$/, '');
 }
 
-async function fetchWithTimeout(url: string, timeoutMs = 12000): Promise<Response> {
+async function fetchWithTimeout(
+  url: string,
+  timeoutMs = 12000,
+  accept?: string
+): Promise<Response> {
   const controller = new AbortController();
   const timer = setTimeout(() => controller.abort(), timeoutMs);
   try {
@@ -38,7 +45,9 @@ async function fetchWithTimeout(url: string, timeoutMs = 12000): Promise<Respons
       signal: controller.signal,
       cache: 'no-store',
       headers: {
-        'user-agent': 'approach-viz-runtime-integration-test/1.0'
+        'user-agent': 'approach-viz-runtime-integration-test/1.0',
+        ...(accept ? { accept } : {}),
+        ...(process.env.RUNTIME_API_KEY ? { 'x-api-key': process.env.RUNTIME_API_KEY } : {})
       }
     });
   } finally {
@@ -126,12 +135,15 @@ test('runtime MRMS meta and wire payload are structurally valid', async () => {
   volumeUrl.searchParams.set('minDbz', minDbz.toString());
   volumeUrl.searchParams.set('maxRangeNm', maxRangeNm.toString());
 
-  const volumeResponse = await fetchWithTimeout(volumeUrl.toString());
+  const volumeResponse = await fetchWithTimeout(
+    volumeUrl.toString(),
+    undefined,
+    `${WIRE_MEDIA_TYPE_PREFIX}${WIRE_V3_VERSION}`
+  );
   assert.equal(volumeResponse.status, 200, `Volume endpoint returned ${volumeResponse.status}`);
   const contentType = (volumeResponse.headers.get('content-type') || '').toLowerCase();
   assert.ok(
-    contentType.includes('application/vnd.approach-viz.mrms.v2') ||
-      contentType.includes('application/vnd.approach-viz.mrms.v3'),
+    contentType.includes(`${WIRE_MEDIA_TYPE_PREFIX}${WIRE_V3_VERSION}`),
     `Unexpected MRMS content-type: ${contentType || 'none'}`
   );
   assert.ok(
@@ -153,10 +165,7 @@ test('runtime MRMS meta and wire payload are structurally valid', async () => {
   const layerCount = view.getUint16(16, true);
   const recordBytes = view.getUint16(18, true);
 
-  assert.ok(
-    wireVersion === WIRE_V2_VERSION || wireVersion === WIRE_V3_VERSION,
-    `Unexpected MRMS wire version: ${wireVersion}`
-  );
+  assert.equal(wireVersion, WIRE_V3_VERSION, `Unexpected MRMS wire version: ${wireVersion}`);
   assert.equal(headerBytes, WIRE_HEADER_BYTES, 'Unexpected MRMS wire header length');
   assert.equal(recordBytes, WIRE_RECORD_BYTES, 'Unexpected MRMS wire record size');
   assert.ok(layerCount > 0, 'MRMS payload should include at least one layer');
@@ -170,6 +179,43 @@ test('runtime MRMS meta and wire payload are structurally valid', async () => {
     `MRMS payload length mismatch (expected ${expectedBytes}, got ${payload.byteLength})`
   );
 
+  const pinnedV2Response = await fetchWithTimeout(
+    volumeUrl.toString(),
+    undefined,
+    `${WIRE_MEDIA_TYPE_PREFIX}${WIRE_V2_VERSION}`
+  );
+  assert.equal(pinnedV2Response.status, 200, `Pinned v2 returned ${pinnedV2Response.status}`);
+  const pinnedV2Payload = new Uint8Array(await pinnedV2Response.arrayBuffer());
+  const pinnedV2View = new DataView(
+    pinnedV2Payload.buffer,
+    pinnedV2Payload.byteOffset,
+    pinnedV2Payload.byteLength
+  );
+  assert.equal(pinnedV2View.getUint16(4, true), WIRE_V2_VERSION, 'Accept should pin wire v2');
+  assert.equal(pinnedV2View.getUint16(6, true), WIRE_HEADER_BYTES, 'Wire v2 header length changed');
+
+  const latestResponse = await fetchWithTimeout(volumeUrl.toString());
+  assert.equal(latestResponse.status, 200, `Latest wire returned ${latestResponse.status}`);
+  const latestPayload = new Uint8Array(await latestResponse.arrayBuffer());
+  const latestView = new DataView(
+    latestPayload.buffer,
+    latestPayload.byteOffset,
+    latestPayload.byteLength
+  );
+  assert.equal(latestView.getUint16(4, true), WIRE_V4_VERSION, 'Default wire should be v4');
+  assert.equal(latestView.getUint16(6, true), WIRE_V4_HEADER_BYTES, 'Unexpected v4 header length');
+
+  const unknownVersionResponse = await fetchWithTimeout(
+    volumeUrl.toString(),
+    undefined,
+    `${WIRE_MEDIA_TYPE_PREFIX}99`
+  );
+  assert.equal(
+    unknownVersionResponse.status,
+    406,
+    `Unknown wire version should return 406, got ${unknownVersionResponse.status}`
+  );
+
   const echoTopUrl = new URL(`${baseUrl}/v1/weather/echo-tops`);
   echoTopUrl.searchParams.set('lat', lat.toString());
   echoTopUrl.searchParams.set('lon', lon.toString());
@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, MatchedPath, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::config::Config;
+use crate::constants::{API_KEY_HEADER, API_KEY_QUERY_PARAM, RATE_LIMIT_IDLE_BUCKET_TTL};
+use crate::utils::tokens_match;
+
+/// Requests per minute for each budget; `0` disables that limit.
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimits {
+    pub default_per_minute: u32,
+    pub volume_per_minute: u32,
+    pub traffic_per_minute: u32,
+    /// Open `/v1/traffic/stream` sockets per client; `0` is unlimited.
+    pub traffic_streams_per_client: u32,
+}
+
+/// Streams are charged per upstream fetch rather than per handshake.
+const TRAFFIC_STREAM_ROUTE: &str = "/v1/traffic/stream";
+
+/// Separate token buckets so cheap metadata polling cannot starve the heavy
+/// volume and traffic endpoints, and vice versa.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Budget {
+    Default,
+    Volume,
+    Traffic,
+}
+
+impl Budget {
+    /// `None` for probes, metrics and admin routes, which skip API keys and
+    /// rate limits (admin routes have their own token).
+    fn for_route(route: &str) -> Option<Self> {
+        match route {
+            "/v1/weather/volume" | "/v1/volume" | "/v1/weather/isosurface" => Some(Budget::Volume),
+            "/v1/traffic/adsbx" | "/v1/traffic/stream" => Some(Budget::Traffic),
+            route if route.starts_with("/v1/admin/") => None,
+            route if route.starts_with("/v1/") => Some(Budget::Default),
+            _ => None,
+        }
+    }
+
+    fn per_minute(self, limits: &RateLimits) -> u32 {
+        match self {
+            Budget::Default => limits.default_per_minute,
+            Budget::Volume => limits.volume_per_minute,
+            Budget::Traffic => limits.traffic_per_minute,
+        }
+    }
+}
+
+/// Who a bucket belongs to: the API key when one was presented, else the
+/// client IP. A key arriving through a trusted proxy with a forwarded client
+/// IP is split per IP, so one shared proxy key is not one shared budget.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum ClientId {
+    Key(String),
+    ForwardedKey(String, IpAddr),
+    Ip(IpAddr),
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    /// Holds up to one minute of budget and refills continuously.
+    fn try_take(&mut self, per_minute: u32, now: Instant) -> Result<(), Duration> {
+        let capacity = f64::from(per_minute);
+        let per_second = capacity / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_second).min(capacity);
+        self.updated_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / per_second))
+        }
+    }
+}
+
+pub struct AccessControl {
+    api_keys: Vec<String>,
+    limits: RateLimits,
+    trust_forwarded_for: bool,
+    forwarded_for_hops: usize,
+    buckets: Mutex<RateLimitBuckets>,
+    open_streams: Mutex<HashMap<ClientId, u32>>,
+}
+
+#[derive(Default)]
+struct RateLimitBuckets {
+    entries: HashMap<(Budget, ClientId), TokenBucket>,
+    last_pruned_at: Option<Instant>,
+}
+
+impl AccessControl {
+    pub fn new(cfg: &Config) -> Arc<Self> {
+        Arc::new(Self {
+            api_keys: cfg.api_keys.clone(),
+            limits: cfg.rate_limits,
+            trust_forwarded_for: cfg.trust_forwarded_for,
+            forwarded_for_hops: cfg.forwarded_for_hops,
+            buckets: Mutex::new(RateLimitBuckets::default()),
+            open_streams: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn check_rate(&self, budget: Budget, client: ClientId, now: Instant) -> Result<(), Duration> {
+        let per_minute = budget.per_minute(&self.limits);
+        if per_minute == 0 {
+            return Ok(());
+        }
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        // Idle buckets have refilled completely, so dropping them loses nothing.
+        if buckets.last_pruned_at.is_none_or(|pruned| {
+            now.saturating_duration_since(pruned) >= RATE_LIMIT_IDLE_BUCKET_TTL
+        }) {
+            buckets.entries.retain(|_, bucket| {
+                now.saturating_duration_since(bucket.updated_at) < RATE_LIMIT_IDLE_BUCKET_TTL
+            });
+            buckets.last_pruned_at = Some(now);
+        }
+        buckets
+            .entries
+            .entry((budget, client))
+            .or_insert_with(|| TokenBucket {
+                tokens: f64::from(per_minute),
+                updated_at: now,
+            })
+            .try_take(per_minute, now)
+    }
+
+    /// With `trust_forwarded_for`, the `X-Forwarded-For` entry appended by
+    /// the outermost trusted proxy, `forwarded_for_hops` places from the right.
+    /// Entries further left are client-supplied and ignored.
+    fn forwarded_ip(&self, headers: &HeaderMap) -> Option<IpAddr> {
+        if !self.trust_forwarded_for {
+            return None;
+        }
+        headers
+            .get("x-forwarded-for")?
+            .to_str()
+            .ok()?
+            .rsplit(',')
+            .nth(self.forwarded_for_hops.saturating_sub(1))?
+            .trim()
+            .parse::<IpAddr>()
+            .ok()
+    }
+
+    fn client_ip(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<IpAddr> {
+        self.forwarded_ip(headers).or(peer.map(|peer| peer.ip()))
+    }
+}
+
+/// One open traffic stream, counted against its client until dropped. The
+/// stream charges the traffic budget through it for each upstream fetch.
+pub struct TrafficStreamClient {
+    access: Arc<AccessControl>,
+    client: ClientId,
+}
+
+impl TrafficStreamClient {
+    /// `None` when the client already has its limit of streams open.
+    fn open(access: &Arc<AccessControl>, client: ClientId) -> Option<Self> {
+        let limit = access.limits.traffic_streams_per_client;
+        let mut open_streams = access
+            .open_streams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let count = open_streams.entry(client.clone()).or_default();
+        if limit > 0 && *count >= limit {
+            return None;
+        }
+        *count += 1;
+        Some(Self {
+            access: access.clone(),
+            client,
+        })
+    }
+
+    /// Takes one traffic request from the client's budget, or returns how
+    /// long until one is available.
+    pub fn charge_upstream_fetch(&self) -> Result<(), Duration> {
+        self.access
+            .check_rate(Budget::Traffic, self.client.clone(), Instant::now())
+    }
+}
+
+impl Drop for TrafficStreamClient {
+    fn drop(&mut self) {
+        let mut open_streams = self
+            .access
+            .open_streams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(count) = open_streams.get_mut(&self.client) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                open_streams.remove(&self.client);
+            }
+        }
+    }
+}
+
+/// Enforces API keys (when any are configured) and per-client rate limits on
+/// `/v1/*` data routes.
+pub async fn enforce_access(
+    State(access): State<Arc<AccessControl>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+    else {
+        return next.run(request).await;
+    };
+    let Some(budget) = Budget::for_route(&route) else {
+        return next.run(request).await;
+    };
+
+    let presented_key = presented_api_key(&request);
+    if !access.api_keys.is_empty()
+        && !presented_key
+            .as_deref()
+            .is_some_and(|key| access.api_keys.iter().any(|valid| tokens_match(key, valid)))
+    {
+        return error_response(StatusCode::UNAUTHORIZED, "Missing or invalid API key.");
+    }
+
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0);
+    let client = match presented_key.filter(|_| !access.api_keys.is_empty()) {
+        Some(key) => match access.forwarded_ip(request.headers()) {
+            Some(ip) => ClientId::ForwardedKey(key, ip),
+            None => ClientId::Key(key),
+        },
+        None => match access.client_ip(request.headers(), peer) {
+            Some(ip) => ClientId::Ip(ip),
+            None => return next.run(request).await,
+        },
+    };
+
+    if route == TRAFFIC_STREAM_ROUTE {
+        let Some(stream_client) = TrafficStreamClient::open(&access, client) else {
+            return error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many open traffic streams.",
+            );
+        };
+        request.extensions_mut().insert(Arc::new(stream_client));
+        return next.run(request).await;
+    }
+
+    if let Err(retry_after) = access.check_rate(budget, client, Instant::now()) {
+        let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded.");
+        let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        if let Ok(value) = HeaderValue::from_str(&seconds.to_string()) {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+        return response;
+    }
+    next.run(request).await
+}
+
+/// `X-API-Key` header, else the `api_key` query parameter.
+fn presented_api_key(request: &Request) -> Option<String> {
+    if let Some(value) = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return Some(value.trim().to_string()).filter(|value| !value.is_empty());
+    }
+    request.uri().query()?.split('&').find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        if name != API_KEY_QUERY_PARAM {
+            return None;
+        }
+        urlencoding::decode(value)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    })
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budgets_are_separate_and_refill() {
+        let access = AccessControl {
+            api_keys: Vec::new(),
+            limits: RateLimits {
+                default_per_minute: 0,
+                volume_per_minute: 2,
+                traffic_per_minute: 60,
+                traffic_streams_per_client: 0,
+            },
+            trust_forwarded_for: false,
+            forwarded_for_hops: 1,
+            buckets: Mutex::new(RateLimitBuckets::default()),
+            open_streams: Mutex::new(HashMap::new()),
+        };
+        let client = ClientId::Ip("192.0.2.1".parse().unwrap());
+        let now = Instant::now();
+
+        assert!(access
+            .check_rate(Budget::Volume, client.clone(), now)
+            .is_ok());
+        assert!(access
+            .check_rate(Budget::Volume, client.clone(), now)
+            .is_ok());
+        let retry_after = access
+            .check_rate(Budget::Volume, client.clone(), now)
+            .expect_err("volume budget exhausted");
+        assert_eq!(retry_after.as_secs(), 30);
+
+        // Other budgets and other clients are unaffected; 0 means unlimited.
+        assert!(access
+            .check_rate(Budget::Traffic, client.clone(), now)
+            .is_ok());
+        assert!(access
+            .check_rate(Budget::Default, client.clone(), now)
+            .is_ok());
+        let other = ClientId::Key("key-b".to_string());
+        assert!(access.check_rate(Budget::Volume, other, now).is_ok());
+
+        let later = now + Duration::from_secs(30);
+        assert!(access.check_rate(Budget::Volume, client, later).is_ok());
+    }
+
+    #[test]
+    fn forwarded_for_uses_the_proxy_appended_entry() {
+        let mut access = AccessControl {
+            api_keys: Vec::new(),
+            limits: RateLimits {
+                default_per_minute: 0,
+                volume_per_minute: 0,
+                traffic_per_minute: 0,
+                traffic_streams_per_client: 0,
+            },
+            trust_forwarded_for: true,
+            forwarded_for_hops: 1,
+            buckets: Mutex::new(RateLimitBuckets::default()),
+            open_streams: Mutex::new(HashMap::new()),
+        };
+        let peer: SocketAddr = "10.0.0.2:443".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("203.0.113.9, 198.51.100.7"),
+        );
+
+        assert_eq!(
+            access.client_ip(&headers, Some(peer)),
+            Some("198.51.100.7".parse().unwrap())
+        );
+        access.forwarded_for_hops = 2;
+        assert_eq!(
+            access.client_ip(&headers, Some(peer)),
+            Some("203.0.113.9".parse().unwrap())
+        );
+        access.forwarded_for_hops = 3;
+        assert_eq!(access.client_ip(&headers, Some(peer)), Some(peer.ip()));
+        access.trust_forwarded_for = false;
+        assert_eq!(access.client_ip(&headers, Some(peer)), Some(peer.ip()));
+    }
+
+    #[test]
+    fn stream_slots_are_limited_per_client_and_released_on_drop() {
+        let access = Arc::new(AccessControl {
+            api_keys: Vec::new(),
+            limits: RateLimits {
+                default_per_minute: 0,
+                volume_per_minute: 0,
+                traffic_per_minute: 1,
+                traffic_streams_per_client: 2,
+            },
+            trust_forwarded_for: false,
+            forwarded_for_hops: 1,
+            buckets: Mutex::new(RateLimitBuckets::default()),
+            open_streams: Mutex::new(HashMap::new()),
+        });
+        let client = ClientId::Ip("192.0.2.1".parse().unwrap());
+
+        let first = TrafficStreamClient::open(&access, client.clone()).expect("first slot");
+        let second = TrafficStreamClient::open(&access, client.clone()).expect("second slot");
+        assert!(TrafficStreamClient::open(&access, client.clone()).is_none());
+        let other = ClientId::Ip("192.0.2.2".parse().unwrap());
+        assert!(TrafficStreamClient::open(&access, other).is_some());
+
+        // Streams of one client share its traffic budget.
+        assert!(first.charge_upstream_fetch().is_ok());
+        assert!(second.charge_upstream_fetch().is_err());
+
+        drop(first);
+        assert!(TrafficStreamClient::open(&access, client).is_some());
+        drop(second);
+        assert!(access.open_streams.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn routes_map_to_budgets() {
+        assert_eq!(
+            Budget::for_route("/v1/weather/volume"),
+            Some(Budget::Volume)
+        );
+        assert_eq!(Budget::for_route("/v1/volume"), Some(Budget::Volume));
+        assert_eq!(
+            Budget::for_route("/v1/weather/isosurface"),
+            Some(Budget::Volume)
+        );
+        assert_eq!(
+            Budget::for_route("/v1/traffic/adsbx"),
+            Some(Budget::Traffic)
+        );
+        assert_eq!(Budget::for_route("/v1/meta"), Some(Budget::Default));
+        assert_eq!(Budget::for_route("/v1/admin/config"), None);
+        assert_eq!(Budget::for_route("/healthz"), None);
+    }
+}
@@ -0,0 +1,200 @@
+use std::time::Instant;
+
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::dead_letter::DeadLetter;
+use crate::domain::MrmsDomain;
+use crate::types::{AppState, DomainState, PendingIngest};
+use crate::utils::{parse_timestamp_utc, tokens_match};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AdminIngestQuery {
+    #[serde(default)]
+    domain: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AdminRetryQuery {
+    #[serde(default)]
+    domain: Option<String>,
+    #[serde(default)]
+    timestamp: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PendingStatus {
+    timestamp: String,
+    attempts: u32,
+    upgrade: bool,
+    #[serde(rename = "dueInSeconds")]
+    due_in_seconds: f64,
+    #[serde(rename = "waitingSeconds")]
+    waiting_seconds: f64,
+    #[serde(rename = "lastError")]
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DomainIngestStatus {
+    domain: &'static str,
+    pending: Vec<PendingStatus>,
+    #[serde(rename = "deadLetters")]
+    dead_letters: Vec<DeadLetter>,
+}
+
+/// Pending queue and dead letters per domain (`?domain=` narrows to one).
+pub async fn admin_ingest(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminIngestQuery>,
+) -> Response {
+    if let Some(response) = reject_unauthorized(&state, &headers) {
+        return response;
+    }
+
+    let selected: Vec<&DomainState> = match query.domain.as_deref() {
+        Some(raw) => match MrmsDomain::parse(raw).and_then(|domain| state.domain_state(domain)) {
+            Some(domain_state) => vec![domain_state.as_ref()],
+            None => return error_response(StatusCode::NOT_FOUND, "Unknown or disabled domain."),
+        },
+        None => state.domains.values().map(|entry| entry.as_ref()).collect(),
+    };
+
+    let now = Instant::now();
+    let mut domains = Vec::with_capacity(selected.len());
+    for domain_state in selected {
+        let mut pending = domain_state
+            .pending
+            .lock()
+            .await
+            .iter()
+            .map(|(timestamp, entry)| pending_status(timestamp, entry, now))
+            .collect::<Vec<_>>();
+        pending.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        domains.push(DomainIngestStatus {
+            domain: domain_state.domain.id(),
+            pending,
+            dead_letters: domain_state.dead_letters.snapshot(),
+        });
+    }
+
+    Json(serde_json::json!({ "domains": domains })).into_response()
+}
+
+/// Requeues a timestamp for immediate ingest, removing its dead letter if any.
+/// The completeness deadline is treated as already passed, so the scheduler
+/// ingests with whatever inputs exist instead of waiting again.
+pub async fn admin_ingest_retry(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminRetryQuery>,
+) -> Response {
+    if let Some(response) = reject_unauthorized(&state, &headers) {
+        return response;
+    }
+
+    let domain_state = match query.domain.as_deref() {
+        Some(raw) => MrmsDomain::parse(raw).and_then(|domain| state.domain_state(domain)),
+        None => state.domains.values().next(),
+    };
+    let Some(domain_state) = domain_state else {
+        return error_response(StatusCode::NOT_FOUND, "Unknown or disabled domain.");
+    };
+    let Some(timestamp) = query
+        .timestamp
+        .filter(|value| parse_timestamp_utc(value).is_some())
+    else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "timestamp must be YYYYMMDD-HHMMSS.",
+        );
+    };
+
+    let dead_letter = domain_state.dead_letters.take(&timestamp);
+    let now = Instant::now();
+    {
+        let mut pending = domain_state.pending.lock().await;
+        let entry = pending
+            .entry(timestamp.clone())
+            .or_insert_with(|| PendingIngest::new(false, now));
+        entry.attempts = 0;
+        entry.errors.clear();
+        entry.upgrade = false;
+        entry.next_attempt_at = now;
+        entry.first_seen_at = now
+            .checked_sub(state.cfg.ingest_deadline)
+            .unwrap_or(entry.first_seen_at);
+    }
+
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "domain": domain_state.domain.id(),
+            "timestamp": timestamp,
+            "queued": true,
+            "wasDeadLettered": dead_letter.is_some(),
+        })),
+    )
+        .into_response()
+}
+
+/// Effective runtime config: each setting's value and whether it came from
+/// an env var, the config file or the default. Secrets are redacted.
+pub async fn admin_config(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(response) = reject_unauthorized(&state, &headers) {
+        return response;
+    }
+
+    Json(serde_json::json!({
+        "configFile": state.cfg.config_file.as_ref().map(|path| path.display().to_string()),
+        "settings": state.cfg.effective,
+    }))
+    .into_response()
+}
+
+fn pending_status(timestamp: &str, entry: &PendingIngest, now: Instant) -> PendingStatus {
+    PendingStatus {
+        timestamp: timestamp.to_string(),
+        attempts: entry.attempts,
+        upgrade: entry.upgrade,
+        due_in_seconds: entry
+            .next_attempt_at
+            .saturating_duration_since(now)
+            .as_secs_f64(),
+        waiting_seconds: now
+            .saturating_duration_since(entry.first_seen_at)
+            .as_secs_f64(),
+        last_error: entry.errors.last().map(|error| error.error.clone()),
+    }
+}
+
+/// Admin endpoints are disabled unless `RUNTIME_ADMIN_TOKEN` is set, and then
+/// require `Authorization: Bearer <token>`.
+fn reject_unauthorized(state: &AppState, headers: &HeaderMap) -> Option<Response> {
+    let Some(expected) = state.cfg.admin_token.as_deref() else {
+        return Some(error_response(
+            StatusCode::NOT_FOUND,
+            "Admin endpoints are disabled.",
+        ));
+    };
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    match provided {
+        Some(token) if tokens_match(token, expected) => None,
+        _ => Some(error_response(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid admin token.",
+        )),
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
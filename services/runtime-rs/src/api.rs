@@ -6,18 +6,25 @@ use axum::Json;
 use serde::{Deserialize, Serialize};
 use std::cmp::min;
 use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
 use tracing::warn;
 
 use crate::constants::{
-    DEFAULT_MAX_RANGE_NM, DEFAULT_MIN_DBZ, MAX_ALLOWED_DBZ, MAX_ALLOWED_RANGE_NM, MIN_ALLOWED_DBZ,
-    MIN_ALLOWED_RANGE_NM, WIRE_HEADER_BYTES, WIRE_MAGIC, WIRE_V2_DBZ_QUANT_STEP_TENTHS,
+    DEFAULT_ISOSURFACE_DBZ, DEFAULT_MAX_RANGE_NM, DEFAULT_MIN_DBZ, ECHO_TOPS_WIRE_HEADER_BYTES,
+    ECHO_TOPS_WIRE_MAGIC, ECHO_TOPS_WIRE_MEDIA_TYPE, ECHO_TOPS_WIRE_RECORD_BYTES,
+    ECHO_TOPS_WIRE_V1_VERSION, MAX_ALLOWED_ALT_FEET, MAX_ALLOWED_DBZ, MAX_ALLOWED_RANGE_NM,
+    MIN_ALLOWED_DBZ, MIN_ALLOWED_RANGE_NM, PHASE_MIXED, PHASE_RAIN, PHASE_SNOW, WIRE_HEADER_BYTES,
+    WIRE_LEVEL_MASK_OFFSET, WIRE_LOD_MAX_FACTOR, WIRE_LOD_RINGS, WIRE_LOD_RINGS_OFFSET, WIRE_MAGIC,
+    WIRE_MEDIA_TYPE_PREFIX, WIRE_SUPPORTED_VERSIONS, WIRE_V2_DBZ_QUANT_STEP_TENTHS,
     WIRE_V2_MAX_SPAN_HIGH_DBZ, WIRE_V2_MAX_SPAN_LOW_DBZ, WIRE_V2_MAX_VERTICAL_SPAN,
-    WIRE_V2_RECORD_BYTES, WIRE_V3_VERSION,
+    WIRE_V2_RECORD_BYTES, WIRE_V3_VERSION, WIRE_V4_HEADER_BYTES, WIRE_V4_VERSION,
 };
-use crate::types::{AppState, ScanSnapshot};
+use crate::domain::MrmsDomain;
+use crate::isosurface::{build_isosurface_mesh, encode_isosurface_wire};
+use crate::query_shape::{parse_query_shape, ProjectedShape, QueryRegion};
+use crate::types::{AppState, LevelBounds, ScanSnapshot, StoredEchoTop};
 use crate::utils::{
-    clamp, clamp_i64, iso_from_ms, projection_scales_nm_per_degree, round_i16, round_u16,
-    shortest_lon_delta_degrees, to_lon360,
+    clamp, clamp_i64, iso_from_ms, round_i16, round_u16, shortest_lon_delta_degrees, to_lon360,
 };
 
 #[derive(Debug, Deserialize)]
@@ -26,8 +33,24 @@ pub(crate) struct VolumeQuery {
     lon: f64,
     #[serde(default, rename = "minDbz")]
     min_dbz: Option<f64>,
+    #[serde(default, rename = "maxDbz")]
+    max_dbz: Option<f64>,
     #[serde(default, rename = "maxRangeNm")]
     max_range_nm: Option<f64>,
+    #[serde(default, rename = "minAltFeet")]
+    min_alt_feet: Option<f64>,
+    #[serde(default, rename = "maxAltFeet")]
+    max_alt_feet: Option<f64>,
+    #[serde(default)]
+    phases: Option<String>,
+    #[serde(default)]
+    lod: Option<String>,
+    #[serde(default)]
+    bbox: Option<String>,
+    #[serde(default)]
+    corridor: Option<String>,
+    #[serde(default, rename = "corridorNm")]
+    corridor_nm: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,10 +59,58 @@ pub(crate) struct EchoTopsQuery {
     lon: f64,
     #[serde(default, rename = "maxRangeNm")]
     max_range_nm: Option<f64>,
+    #[serde(default)]
+    bbox: Option<String>,
+    #[serde(default)]
+    corridor: Option<String>,
+    #[serde(default, rename = "corridorNm")]
+    corridor_nm: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct IsosurfaceQuery {
+    lat: f64,
+    lon: f64,
+    #[serde(default, rename = "thresholdDbz")]
+    threshold_dbz: Option<f64>,
+    #[serde(default, rename = "maxRangeNm")]
+    max_range_nm: Option<f64>,
+    #[serde(default)]
+    bbox: Option<String>,
+    #[serde(default)]
+    corridor: Option<String>,
+    #[serde(default, rename = "corridorNm")]
+    corridor_nm: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct MetaQuery {
+    #[serde(default)]
+    domain: Option<String>,
+    #[serde(default)]
+    lat: Option<f64>,
+    #[serde(default)]
+    lon: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct MetaDomainStatus {
+    domain: &'static str,
+    ready: bool,
+    timestamp: Option<String>,
+    #[serde(rename = "scanTime")]
+    scan_time: Option<String>,
+    #[serde(rename = "pendingCount")]
+    pending_count: usize,
+    /// Products with at least one object seen in SNS notifications.
+    #[serde(rename = "availabilityProducts")]
+    availability_products: usize,
 }
 
 #[derive(Debug, Serialize)]
 pub(crate) struct MetaResponse {
+    domain: &'static str,
+    domains: Vec<MetaDomainStatus>,
     ready: bool,
     #[serde(rename = "generatedAt")]
     generated_at: Option<String>,
@@ -86,6 +157,13 @@ pub(crate) struct MetaResponse {
     zdr_age_seconds: Option<i64>,
     #[serde(rename = "rhohvAgeSeconds")]
     rhohv_age_seconds: Option<i64>,
+    #[serde(rename = "ingestPolicy")]
+    ingest_policy: Option<String>,
+    #[serde(rename = "missingScanProducts")]
+    missing_scan_products: Option<u32>,
+    /// Level tags absent from a partial scan; empty when complete.
+    #[serde(rename = "missingLevels")]
+    missing_levels: Vec<String>,
     #[serde(rename = "storageDir")]
     storage_dir: String,
     #[serde(rename = "retentionBytes")]
@@ -129,8 +207,42 @@ pub async fn healthz() -> &'static str {
     "ok"
 }
 
-pub async fn meta(State(state): State<AppState>) -> Json<MetaResponse> {
-    let latest = state.latest.read().await;
+pub async fn meta(State(state): State<AppState>, Query(query): Query<MetaQuery>) -> Response {
+    let selected = match (query.domain.as_deref(), query.lat, query.lon) {
+        (Some(raw), _, _) => match MrmsDomain::parse(raw) {
+            Some(domain) => state.domain_state(domain),
+            None => None,
+        },
+        (None, Some(lat), Some(lon)) => state.domain_state_for_position(lat, lon),
+        _ => state.domains.values().next(),
+    };
+    let Some(domain_state) = selected else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Requested MRMS domain is not enabled."
+            })),
+        )
+            .into_response();
+    };
+
+    let mut domains = Vec::with_capacity(state.domains.len());
+    for status in state.domains.values() {
+        let latest = status.latest.read().await;
+        let pending_count = status.pending.lock().await.len();
+        domains.push(MetaDomainStatus {
+            domain: status.domain.id(),
+            ready: latest.is_some(),
+            timestamp: latest.as_ref().map(|scan| scan.timestamp.clone()),
+            scan_time: latest
+                .as_ref()
+                .and_then(|scan| iso_from_ms(scan.scan_time_ms)),
+            pending_count,
+            availability_products: status.availability.product_count(),
+        });
+    }
+
+    let latest = domain_state.latest.read().await;
     let (
         ready,
         generated_at,
@@ -191,6 +303,8 @@ pub async fn meta(State(state): State<AppState>) -> Json<MetaResponse> {
     };
 
     Json(MetaResponse {
+        domain: domain_state.domain.id(),
+        domains,
         ready,
         generated_at,
         scan_time,
@@ -215,13 +329,50 @@ pub async fn meta(State(state): State<AppState>) -> Json<MetaResponse> {
         freezing_level_timestamp,
         zdr_age_seconds,
         rhohv_age_seconds,
+        ingest_policy: latest
+            .as_ref()
+            .map(|scan| scan.phase_debug.ingest_policy.clone()),
+        missing_scan_products: latest
+            .as_ref()
+            .map(|scan| scan.phase_debug.missing_scan_products),
+        missing_levels: latest
+            .as_ref()
+            .map(|scan| {
+                let level_tags = domain_state.level_tags();
+                scan.missing_levels
+                    .iter()
+                    .filter_map(|level_idx| level_tags.get(*level_idx as usize).cloned())
+                    .collect()
+            })
+            .unwrap_or_default(),
         storage_dir: state.cfg.storage_dir.display().to_string(),
-        retention_bytes: state.cfg.retention_bytes,
+        retention_bytes: state.cfg.retention_bytes_for(domain_state.domain),
         sqs_enabled: state.cfg.sqs_queue_url.is_some(),
     })
+    .into_response()
 }
 
-pub async fn volume(State(state): State<AppState>, Query(query): Query<VolumeQuery>) -> Response {
+pub async fn volume(
+    State(state): State<AppState>,
+    request_headers: HeaderMap,
+    Query(query): Query<VolumeQuery>,
+) -> Response {
+    let Some(wire_version) = negotiate_volume_wire_version(&request_headers) else {
+        let supported: Vec<String> = WIRE_SUPPORTED_VERSIONS
+            .iter()
+            .map(|version| format!("{WIRE_MEDIA_TYPE_PREFIX}{version}"))
+            .collect();
+        return (
+            StatusCode::NOT_ACCEPTABLE,
+            [("Vary", "Accept")],
+            Json(serde_json::json!({
+                "error": "Requested MRMS wire version is not supported.",
+                "supported": supported
+            })),
+        )
+            .into_response();
+    };
+
     if query.lat < -90.0 || query.lat > 90.0 || query.lon < -180.0 || query.lon > 180.0 {
         return (
             StatusCode::BAD_REQUEST,
@@ -242,36 +393,92 @@ pub async fn volume(State(state): State<AppState>, Query(query): Query<VolumeQue
         MIN_ALLOWED_RANGE_NM,
         MAX_ALLOWED_RANGE_NM,
     );
+    let shape = match resolve_query_shape(
+        query.lat,
+        query.lon,
+        query.bbox.as_deref(),
+        query.corridor.as_deref(),
+        query.corridor_nm,
+        max_range_nm,
+    ) {
+        Ok(shape) => shape,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": message })),
+            )
+                .into_response();
+        }
+    };
+    let filter = match build_volume_filter(&query, min_dbz) {
+        Ok(filter) => filter,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": message })),
+            )
+                .into_response();
+        }
+    };
+
+    let lod = match query.lod.as_deref() {
+        Some(raw) => match LodMode::parse(raw) {
+            Some(lod) => lod,
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "lod must be one of max, mean, or off."
+                    })),
+                )
+                    .into_response();
+            }
+        },
+        None if wire_version >= WIRE_V4_VERSION => LodMode::Max,
+        None => LodMode::Off,
+    };
+    if lod != LodMode::Off && wire_version < WIRE_V4_VERSION {
+        return (
+            StatusCode::BAD_REQUEST,
+            [("Vary", "Accept")],
+            Json(serde_json::json!({
+                "error": format!("lod requires {WIRE_MEDIA_TYPE_PREFIX}{WIRE_V4_VERSION}.")
+            })),
+        )
+            .into_response();
+    }
 
-    let latest = state.latest.read().await;
+    let Some(domain_state) = state.domain_state_for_position(query.lat, query.lon) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "error": "No MRMS domain is configured."
+            })),
+        )
+            .into_response();
+    };
+    let domain = domain_state.domain;
+    let latest = domain_state.latest.read().await;
     let Some(scan) = latest.as_ref() else {
         return (
             StatusCode::SERVICE_UNAVAILABLE,
             Json(serde_json::json!({
-                "error": "No MRMS scan is available yet."
+                "error": format!("No MRMS {} scan is available yet.", domain.id())
             })),
         )
             .into_response();
     };
 
-    match build_volume_wire(scan, query.lat, query.lon, min_dbz, max_range_nm) {
+    match build_volume_wire(scan, min_dbz, shape, &filter, lod, wire_version) {
         Ok(body) => {
             let mut headers = HeaderMap::new();
-            headers.insert(
-                "Content-Type",
-                HeaderValue::from_static("application/vnd.approach-viz.mrms.v3"),
-            );
-            headers.insert("Cache-Control", HeaderValue::from_static("no-store"));
-            if let Some(scan_time) = iso_from_ms(scan.scan_time_ms) {
-                if let Ok(value) = HeaderValue::from_str(&scan_time) {
-                    headers.insert("X-AV-SCAN-TIME", value);
-                }
-            }
-            if let Some(generated_at) = iso_from_ms(scan.generated_at_ms) {
-                if let Ok(value) = HeaderValue::from_str(&generated_at) {
-                    headers.insert("X-AV-GENERATED-AT", value);
-                }
+            if let Ok(value) =
+                HeaderValue::from_str(&format!("{WIRE_MEDIA_TYPE_PREFIX}{wire_version}"))
+            {
+                headers.insert("Content-Type", value);
             }
+            headers.insert("Vary", HeaderValue::from_static("Accept"));
+            scan_time_headers(&mut headers, domain, scan);
             if !scan.phase_debug.mode.is_empty() {
                 if let Ok(value) = HeaderValue::from_str(&scan.phase_debug.mode) {
                     headers.insert("X-AV-PHASE-MODE", value);
@@ -282,6 +489,11 @@ pub async fn volume(State(state): State<AppState>, Query(query): Query<VolumeQue
                     headers.insert("X-AV-PHASE-DETAIL", value);
                 }
             }
+            if !scan.phase_debug.ingest_policy.is_empty() {
+                if let Ok(value) = HeaderValue::from_str(&scan.phase_debug.ingest_policy) {
+                    headers.insert("X-AV-INGEST-POLICY", value);
+                }
+            }
             if let Some(value) = scan.phase_debug.zdr_age_seconds {
                 if let Ok(header) = HeaderValue::from_str(&value.to_string()) {
                     headers.insert("X-AV-ZDR-AGE-SECONDS", header);
@@ -312,26 +524,7 @@ pub async fn volume(State(state): State<AppState>, Query(query): Query<VolumeQue
                     headers.insert("X-AV-FREEZING-TIMESTAMP", header);
                 }
             }
-            if let Some(value) = scan.echo_top_debug.top18_timestamp.as_ref() {
-                if let Ok(header) = HeaderValue::from_str(value) {
-                    headers.insert("X-AV-ECHOTOP18-TIMESTAMP", header);
-                }
-            }
-            if let Some(value) = scan.echo_top_debug.top30_timestamp.as_ref() {
-                if let Ok(header) = HeaderValue::from_str(value) {
-                    headers.insert("X-AV-ECHOTOP30-TIMESTAMP", header);
-                }
-            }
-            if let Some(value) = scan.echo_top_debug.top50_timestamp.as_ref() {
-                if let Ok(header) = HeaderValue::from_str(value) {
-                    headers.insert("X-AV-ECHOTOP50-TIMESTAMP", header);
-                }
-            }
-            if let Some(value) = scan.echo_top_debug.top60_timestamp.as_ref() {
-                if let Ok(header) = HeaderValue::from_str(value) {
-                    headers.insert("X-AV-ECHOTOP60-TIMESTAMP", header);
-                }
-            }
+            echo_top_timestamp_headers(&mut headers, scan);
             (headers, body).into_response()
         }
         Err(error) => {
@@ -349,6 +542,7 @@ pub async fn volume(State(state): State<AppState>, Query(query): Query<VolumeQue
 
 pub async fn echo_tops(
     State(state): State<AppState>,
+    request_headers: HeaderMap,
     Query(query): Query<EchoTopsQuery>,
 ) -> Response {
     if query.lat < -90.0 || query.lat > 90.0 || query.lon < -180.0 || query.lon > 180.0 {
@@ -366,19 +560,59 @@ pub async fn echo_tops(
         MIN_ALLOWED_RANGE_NM,
         MAX_ALLOWED_RANGE_NM,
     );
+    let shape = match resolve_query_shape(
+        query.lat,
+        query.lon,
+        query.bbox.as_deref(),
+        query.corridor.as_deref(),
+        query.corridor_nm,
+        max_range_nm,
+    ) {
+        Ok(shape) => shape,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": message })),
+            )
+                .into_response();
+        }
+    };
 
-    let latest = state.latest.read().await;
+    let Some(domain_state) = state.domain_state_for_position(query.lat, query.lon) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "error": "No MRMS domain is configured."
+            })),
+        )
+            .into_response();
+    };
+    let domain = domain_state.domain;
+    let latest = domain_state.latest.read().await;
     let Some(scan) = latest.as_ref() else {
         return (
             StatusCode::SERVICE_UNAVAILABLE,
             Json(serde_json::json!({
-                "error": "No MRMS scan is available yet."
+                "error": format!("No MRMS {} scan is available yet.", domain.id())
             })),
         )
             .into_response();
     };
 
-    let window = build_query_window(scan, query.lat, query.lon, DEFAULT_MIN_DBZ, max_range_nm);
+    let window = build_shaped_query_window(scan, DEFAULT_MIN_DBZ, shape);
+    if accepts_media_type(&request_headers, ECHO_TOPS_WIRE_MEDIA_TYPE) {
+        let body = build_echo_tops_wire_v1(scan, &window);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Content-Type",
+            HeaderValue::from_static(ECHO_TOPS_WIRE_MEDIA_TYPE),
+        );
+        headers.insert("Vary", HeaderValue::from_static("Accept"));
+        scan_time_headers(&mut headers, domain, scan);
+        echo_top_timestamp_headers(&mut headers, scan);
+        return (headers, body).into_response();
+    }
+
     let cells = build_echo_top_cells(scan, &window);
     let body = EchoTopsResponse {
         generated_at: iso_from_ms(scan.generated_at_ms),
@@ -399,93 +633,447 @@ pub async fn echo_tops(
     };
 
     let mut headers = HeaderMap::new();
+    headers.insert("Vary", HeaderValue::from_static("Accept"));
+    scan_time_headers(&mut headers, domain, scan);
+    echo_top_timestamp_headers(&mut headers, scan);
+    (headers, Json(body)).into_response()
+}
+
+pub async fn isosurface(
+    State(state): State<AppState>,
+    Query(query): Query<IsosurfaceQuery>,
+) -> Response {
+    if query.lat < -90.0 || query.lat > 90.0 || query.lon < -180.0 || query.lon > 180.0 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "Invalid lat/lon query parameters."
+            })),
+        )
+            .into_response();
+    }
+
+    let threshold_dbz = clamp(
+        query.threshold_dbz.unwrap_or(DEFAULT_ISOSURFACE_DBZ),
+        MIN_ALLOWED_DBZ,
+        MAX_ALLOWED_DBZ,
+    );
+    let max_range_nm = clamp(
+        query.max_range_nm.unwrap_or(DEFAULT_MAX_RANGE_NM),
+        MIN_ALLOWED_RANGE_NM,
+        MAX_ALLOWED_RANGE_NM,
+    );
+    let shape = match resolve_query_shape(
+        query.lat,
+        query.lon,
+        query.bbox.as_deref(),
+        query.corridor.as_deref(),
+        query.corridor_nm,
+        max_range_nm,
+    ) {
+        Ok(shape) => shape,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": message })),
+            )
+                .into_response();
+        }
+    };
+
+    let Some(domain_state) = state.domain_state_for_position(query.lat, query.lon) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "error": "No MRMS domain is configured."
+            })),
+        )
+            .into_response();
+    };
+    let domain = domain_state.domain;
+    // Marching cubes can take a while on wide windows; hold our own reference
+    // so an ingest swap never waits on the read lock.
+    let Some(scan) = domain_state.latest.read().await.clone() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "error": format!("No MRMS {} scan is available yet.", domain.id())
+            })),
+        )
+            .into_response();
+    };
+
+    let mesh_scan = scan.clone();
+    let built = tokio::task::spawn_blocking(move || {
+        let window = build_shaped_query_window(&mesh_scan, threshold_dbz, shape);
+        build_isosurface_mesh(&mesh_scan, &window)
+            .map(|mesh| encode_isosurface_wire(&mesh_scan, &window, &mesh))
+    })
+    .await;
+    let body = match built {
+        Ok(Ok(body)) => body,
+        Ok(Err(too_large)) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(serde_json::json!({
+                    "error": format!(
+                        "Isosurface too large: {too_large}. Narrow the query or raise thresholdDbz."
+                    )
+                })),
+            )
+                .into_response();
+        }
+        Err(error) => {
+            warn!("Isosurface mesh task failed: {error}");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to build MRMS isosurface mesh."
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "Content-Type",
+        HeaderValue::from_static("application/vnd.approach-viz.mrms-mesh.v1"),
+    );
+    scan_time_headers(&mut headers, domain, &scan);
+    (headers, body).into_response()
+}
+
+/// `no-store`, the serving domain, and the scan and generation times.
+fn scan_time_headers(headers: &mut HeaderMap, domain: MrmsDomain, scan: &ScanSnapshot) {
     headers.insert("Cache-Control", HeaderValue::from_static("no-store"));
-    if let Some(scan_time) = iso_from_ms(scan.scan_time_ms) {
-        if let Ok(value) = HeaderValue::from_str(&scan_time) {
-            headers.insert("X-AV-SCAN-TIME", value);
+    headers.insert("X-AV-MRMS-DOMAIN", HeaderValue::from_static(domain.id()));
+    let times = [
+        ("X-AV-SCAN-TIME", scan.scan_time_ms),
+        ("X-AV-GENERATED-AT", scan.generated_at_ms),
+    ];
+    for (name, ms) in times {
+        if let Some(value) = iso_from_ms(ms).and_then(|iso| HeaderValue::from_str(&iso).ok()) {
+            headers.insert(name, value);
         }
     }
-    if let Some(generated_at) = iso_from_ms(scan.generated_at_ms) {
-        if let Ok(value) = HeaderValue::from_str(&generated_at) {
-            headers.insert("X-AV-GENERATED-AT", value);
+}
+
+/// Source object timestamps of the echo-top products the scan used.
+fn echo_top_timestamp_headers(headers: &mut HeaderMap, scan: &ScanSnapshot) {
+    let debug = &scan.echo_top_debug;
+    let timestamps = [
+        ("X-AV-ECHOTOP18-TIMESTAMP", &debug.top18_timestamp),
+        ("X-AV-ECHOTOP30-TIMESTAMP", &debug.top30_timestamp),
+        ("X-AV-ECHOTOP50-TIMESTAMP", &debug.top50_timestamp),
+        ("X-AV-ECHOTOP60-TIMESTAMP", &debug.top60_timestamp),
+    ];
+    for (name, timestamp) in timestamps {
+        if let Some(value) = timestamp
+            .as_deref()
+            .and_then(|timestamp| HeaderValue::from_str(timestamp).ok())
+        {
+            headers.insert(name, value);
         }
     }
-    (headers, Json(body)).into_response()
 }
 
-fn build_volume_wire(
-    scan: &ScanSnapshot,
+fn resolve_query_shape(
     origin_lat: f64,
     origin_lon: f64,
-    min_dbz: f64,
+    bbox: Option<&str>,
+    corridor: Option<&str>,
+    corridor_nm: Option<f64>,
     max_range_nm: f64,
+) -> Result<ProjectedShape, String> {
+    let shape = parse_query_shape(bbox, corridor, corridor_nm, max_range_nm)?;
+    let projected = shape.project(origin_lat, origin_lon);
+    // Shapes share the radius cap, which bounds payload size on every endpoint.
+    if projected.bounding_radius_nm > MAX_ALLOWED_RANGE_NM {
+        return Err(format!(
+            "Query shape extends beyond {MAX_ALLOWED_RANGE_NM} NM from the origin."
+        ));
+    }
+    Ok(projected)
+}
+
+fn build_volume_wire(
+    scan: &ScanSnapshot,
+    min_dbz: f64,
+    shape: ProjectedShape,
+    filter: &VolumeFilter,
+    lod: LodMode,
+    wire_version: u16,
 ) -> Result<Vec<u8>> {
-    let window = build_query_window(scan, origin_lat, origin_lon, min_dbz, max_range_nm);
-    Ok(build_volume_wire_v2(scan, &window))
+    let window = build_shaped_query_window(scan, min_dbz, shape);
+    Ok(build_volume_wire_v2(
+        scan,
+        &window,
+        filter,
+        lod,
+        wire_version,
+    ))
 }
 
-#[derive(Clone, Copy)]
-struct QueryWindow {
-    min_dbz_tenths: i16,
-    origin_lat: f64,
-    origin_lon: f64,
-    origin_lon360: f64,
-    max_range_nm: f64,
-    max_range_squared_nm: f64,
-    east_nm_per_lon_deg_safe: f64,
-    north_nm_per_lat_deg_safe: f64,
-    row_start: u32,
-    row_end: u32,
-    col_start: u32,
-    col_end: u32,
-    lon_wrapped: bool,
-    tile_row_start: u32,
-    tile_row_end: u32,
-    tile_col_start: u32,
-    tile_col_end: u32,
-    footprint_x_milli: u16,
-    footprint_y_milli: u16,
+/// How far-range cells are aggregated into super-cells before brick merging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LodMode {
+    Off,
+    Max,
+    Mean,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+impl LodMode {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "off" | "none" | "0" => Some(Self::Off),
+            "max" => Some(Self::Max),
+            "mean" => Some(Self::Mean),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct LodAccumulator {
+    max_dbz_tenths: i16,
+    sum_dbz_tenths: i64,
+    count: u32,
+    phase_counts: [u32; 3],
+    surface_phase_counts: [u32; 3],
+}
+
+impl LodAccumulator {
+    fn add(&mut self, dbz_tenths: i16, phase: u8, surface_phase: u8) {
+        if self.count == 0 || dbz_tenths > self.max_dbz_tenths {
+            self.max_dbz_tenths = dbz_tenths;
+        }
+        self.sum_dbz_tenths += dbz_tenths as i64;
+        self.count += 1;
+        if let Some(count) = self.phase_counts.get_mut(phase as usize) {
+            *count += 1;
+        }
+        if let Some(count) = self.surface_phase_counts.get_mut(surface_phase as usize) {
+            *count += 1;
+        }
+    }
+
+    fn dbz_tenths(&self, lod: LodMode) -> i16 {
+        match lod {
+            LodMode::Mean if self.count > 0 => {
+                (self.sum_dbz_tenths as f64 / self.count as f64).round() as i16
+            }
+            _ => self.max_dbz_tenths,
+        }
+    }
+}
+
+fn majority_phase(counts: &[u32; 3]) -> u8 {
+    let mut best = 0_usize;
+    for (idx, count) in counts.iter().enumerate() {
+        if *count > counts[best] {
+            best = idx;
+        }
+    }
+    best as u8
+}
+
+/// Super-cell factor for a grid cell. Rings are picked from the center of the
+/// coarsest block so every finer block lands wholly inside one ring.
+fn lod_factor_for_cell(
+    scan: &ScanSnapshot,
+    window: &QueryWindow,
+    lod: LodMode,
+    row: u32,
+    col: u32,
+) -> u32 {
+    if lod == LodMode::Off {
+        return 1;
+    }
+    let block_offset = (WIRE_LOD_MAX_FACTOR as f64 - 1.0) * 0.5;
+    let center_row = (row / WIRE_LOD_MAX_FACTOR * WIRE_LOD_MAX_FACTOR) as f64 + block_offset;
+    let center_col = (col / WIRE_LOD_MAX_FACTOR * WIRE_LOD_MAX_FACTOR) as f64 + block_offset;
+    let (x_nm, z_nm) = project_grid_position_nm(scan, window, center_row, center_col);
+    let distance_nm = x_nm.hypot(z_nm);
+    WIRE_LOD_RINGS
+        .iter()
+        .rev()
+        .find(|(start_nm, _)| distance_nm >= *start_nm)
+        .map(|(_, factor)| *factor as u32)
+        .unwrap_or(1)
+}
+
+/// Optional voxel filters applied before brick merging. Levels are kept when
+/// their bounds overlap the requested altitude band.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct VolumeFilter {
+    max_dbz_tenths: i16,
+    min_alt_feet: u16,
+    max_alt_feet: u16,
+    phase_mask: u8,
+}
+
+impl Default for VolumeFilter {
+    fn default() -> Self {
+        Self {
+            max_dbz_tenths: i16::MAX,
+            min_alt_feet: 0,
+            max_alt_feet: u16::MAX,
+            phase_mask: phase_bit(PHASE_RAIN) | phase_bit(PHASE_MIXED) | phase_bit(PHASE_SNOW),
+        }
+    }
+}
+
+impl VolumeFilter {
+    fn includes_level(&self, bounds: &LevelBounds) -> bool {
+        bounds.top_feet >= self.min_alt_feet && bounds.bottom_feet <= self.max_alt_feet
+    }
+
+    fn includes_voxel(&self, dbz_tenths: i16, phase: u8) -> bool {
+        dbz_tenths <= self.max_dbz_tenths && self.phase_mask & phase_bit(phase) != 0
+    }
+}
+
+fn phase_bit(phase: u8) -> u8 {
+    1_u8.checked_shl(phase as u32).unwrap_or(0)
+}
+
+fn parse_phase_mask(raw: &str) -> Result<u8, String> {
+    let mut mask = 0_u8;
+    for token in raw.split(',') {
+        let phase = match token.trim().to_ascii_lowercase().as_str() {
+            "" => continue,
+            "rain" => PHASE_RAIN,
+            "mixed" => PHASE_MIXED,
+            "snow" => PHASE_SNOW,
+            other => {
+                return Err(format!(
+                    "Unknown phase '{other}'. Expected rain, mixed, or snow."
+                ))
+            }
+        };
+        mask |= phase_bit(phase);
+    }
+    if mask == 0 {
+        return Err("phases must list at least one of rain, mixed, or snow.".to_string());
+    }
+    Ok(mask)
+}
+
+fn build_volume_filter(query: &VolumeQuery, min_dbz: f64) -> Result<VolumeFilter, String> {
+    let mut filter = VolumeFilter::default();
+
+    if let Some(max_dbz) = query.max_dbz {
+        let max_dbz = clamp(max_dbz, MIN_ALLOWED_DBZ, MAX_ALLOWED_DBZ);
+        if max_dbz < min_dbz {
+            return Err("maxDbz must be greater than or equal to minDbz.".to_string());
+        }
+        // The top of the clamp range stays open-ended so 60 means "60 and above".
+        if max_dbz < MAX_ALLOWED_DBZ {
+            filter.max_dbz_tenths = (max_dbz * 10.0).round() as i16;
+        }
+    }
+
+    if let Some(min_alt_feet) = query.min_alt_feet {
+        filter.min_alt_feet = clamp(min_alt_feet, 0.0, MAX_ALLOWED_ALT_FEET).round() as u16;
+    }
+    if let Some(max_alt_feet) = query.max_alt_feet {
+        filter.max_alt_feet = clamp(max_alt_feet, 0.0, MAX_ALLOWED_ALT_FEET).round() as u16;
+    }
+    if filter.min_alt_feet > filter.max_alt_feet {
+        return Err("minAltFeet must be less than or equal to maxAltFeet.".to_string());
+    }
+
+    if let Some(phases) = query.phases.as_deref() {
+        filter.phase_mask = parse_phase_mask(phases)?;
+    }
+
+    Ok(filter)
+}
+
+#[derive(Clone)]
+pub(crate) struct QueryWindow {
+    pub(crate) min_dbz_tenths: i16,
+    pub(crate) origin_lat: f64,
+    pub(crate) origin_lon: f64,
+    pub(crate) origin_lon360: f64,
+    /// Bounding radius of the query shape; the header range for non-radius shapes.
+    pub(crate) max_range_nm: f64,
+    pub(crate) region: QueryRegion,
+    pub(crate) east_nm_per_lon_deg_safe: f64,
+    pub(crate) north_nm_per_lat_deg_safe: f64,
+    pub(crate) row_start: u32,
+    pub(crate) row_end: u32,
+    pub(crate) col_start: u32,
+    pub(crate) col_end: u32,
+    pub(crate) lon_wrapped: bool,
+    pub(crate) tile_row_start: u32,
+    pub(crate) tile_row_end: u32,
+    pub(crate) tile_col_start: u32,
+    pub(crate) tile_col_end: u32,
+    pub(crate) footprint_x_milli: u16,
+    pub(crate) footprint_y_milli: u16,
+}
+
+impl QueryWindow {
+    pub(crate) fn contains_nm(&self, x_nm: f64, z_nm: f64) -> bool {
+        self.region.contains_nm(x_nm, z_nm)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct EchoTopKey {
+    top18_feet: u16,
+    top30_feet: u16,
+    top50_feet: u16,
+    top60_feet: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 struct MergeKey {
     phase: u8,
     dbz_tenths: i16,
 }
 
+/// A grid cell fed to the run/rectangle merge. Cells merge when their keys
+/// match; the payload rides along from the first cell of each run.
 #[derive(Clone, Copy, Debug)]
-struct MergeCell {
+struct MergeCell<K, P = ()> {
     row: u32,
     col: u32,
-    key: MergeKey,
-    surface_phase: u8,
+    key: K,
+    payload: P,
 }
 
 #[derive(Clone, Copy, Debug)]
-struct RowRun {
+struct RowRun<K, P> {
     col_start: u32,
     col_end: u32,
-    key: MergeKey,
-    surface_phase: u8,
+    key: K,
+    payload: P,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-struct RunSignature {
+struct RunSignature<K> {
     col_start: u32,
     col_end: u32,
-    key: MergeKey,
+    key: K,
 }
 
 #[derive(Clone, Copy, Debug)]
-struct HorizontalRect {
+struct HorizontalRect<K, P = ()> {
     row_start: u32,
     row_end: u32,
     col_start: u32,
     col_end: u32,
-    key: MergeKey,
-    surface_phase: u8,
+    key: K,
+    payload: P,
 }
 
+/// Volume cells carry the surface precip phase alongside the merge key.
+type VoxelCell = MergeCell<MergeKey, u8>;
+type VoxelRect = HorizontalRect<MergeKey, u8>;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 struct VerticalSignature {
     row_start: u32,
@@ -507,28 +1095,44 @@ struct BrickCandidate {
     surface_phase: u8,
 }
 
-fn build_query_window(
+#[cfg(test)]
+pub(crate) fn build_query_window(
     scan: &ScanSnapshot,
     origin_lat: f64,
     origin_lon: f64,
     min_dbz: f64,
     max_range_nm: f64,
 ) -> QueryWindow {
-    let min_dbz_tenths = (min_dbz * 10.0).round() as i16;
-    let max_range_squared_nm = max_range_nm * max_range_nm;
-
-    let origin_lon360 = to_lon360(origin_lon);
-    let (east_nm_per_lon_deg, north_nm_per_lat_deg) = projection_scales_nm_per_degree(origin_lat);
-    let east_nm_per_lon_deg_safe = east_nm_per_lon_deg.abs().max(1e-6);
-    let north_nm_per_lat_deg_safe = north_nm_per_lat_deg.abs().max(1e-6);
-
-    let lat_padding_deg = max_range_nm / north_nm_per_lat_deg_safe;
-    let lon_padding_deg = max_range_nm / east_nm_per_lon_deg_safe;
+    let shape =
+        crate::query_shape::QueryShape::Radius { max_range_nm }.project(origin_lat, origin_lon);
+    build_shaped_query_window(scan, min_dbz, shape)
+}
 
-    let lat_min = origin_lat - lat_padding_deg;
-    let lat_max = origin_lat + lat_padding_deg;
-    let lon_min360 = origin_lon360 - lon_padding_deg;
-    let lon_max360 = origin_lon360 + lon_padding_deg;
+pub(crate) fn build_shaped_query_window(
+    scan: &ScanSnapshot,
+    min_dbz: f64,
+    shape: ProjectedShape,
+) -> QueryWindow {
+    let min_dbz_tenths = (min_dbz * 10.0).round() as i16;
+    let ProjectedShape {
+        origin_lat,
+        origin_lon,
+        origin_lon360,
+        east_nm_per_lon_deg_safe,
+        north_nm_per_lat_deg_safe,
+        region,
+        x_min_nm,
+        x_max_nm,
+        z_min_nm,
+        z_max_nm,
+        bounding_radius_nm,
+    } = shape;
+
+    // z grows southward, so the north edge comes from the smallest z.
+    let lat_min = origin_lat - z_max_nm / north_nm_per_lat_deg_safe;
+    let lat_max = origin_lat - z_min_nm / north_nm_per_lat_deg_safe;
+    let lon_min360 = origin_lon360 + x_min_nm / east_nm_per_lon_deg_safe;
+    let lon_max360 = origin_lon360 + x_max_nm / east_nm_per_lon_deg_safe;
     let lon_wrapped = lon_min360 < 0.0 || lon_max360 >= 360.0;
 
     let row_from_lat = |lat: f64| (lat - scan.grid.la1_deg) / scan.grid.lat_step_deg;
@@ -582,8 +1186,8 @@ fn build_query_window(
         origin_lat,
         origin_lon,
         origin_lon360,
-        max_range_nm,
-        max_range_squared_nm,
+        max_range_nm: bounding_radius_nm,
+        region,
         east_nm_per_lon_deg_safe,
         north_nm_per_lat_deg_safe,
         row_start,
@@ -607,10 +1211,11 @@ fn build_wire_header(
     record_bytes: u16,
     encoding_hint: u16,
 ) -> Vec<u8> {
-    let mut body = vec![0_u8; WIRE_HEADER_BYTES + scan.level_bounds.len() * 4];
+    let header_bytes = wire_header_bytes(wire_version);
+    let mut body = vec![0_u8; header_bytes + scan.level_bounds.len() * 4];
     body[0..4].copy_from_slice(&WIRE_MAGIC);
     body[4..6].copy_from_slice(&wire_version.to_le_bytes());
-    body[6..8].copy_from_slice(&(WIRE_HEADER_BYTES as u16).to_le_bytes());
+    body[6..8].copy_from_slice(&(header_bytes as u16).to_le_bytes());
     body[8..12].copy_from_slice(&0_u32.to_le_bytes());
     body[12..16].copy_from_slice(&0_u32.to_le_bytes());
     body[16..18].copy_from_slice(&(scan.level_bounds.len() as u16).to_le_bytes());
@@ -625,10 +1230,23 @@ fn build_wire_header(
     body[46..48].copy_from_slice(&encoding_hint.to_le_bytes());
     body[48..52].copy_from_slice(&((window.origin_lat * 1_000_000.0).round() as i32).to_le_bytes());
     body[52..56].copy_from_slice(&((window.origin_lon * 1_000_000.0).round() as i32).to_le_bytes());
+    if wire_version >= WIRE_V4_VERSION {
+        body[WIRE_LEVEL_MASK_OFFSET..WIRE_LEVEL_MASK_OFFSET + 8]
+            .copy_from_slice(&scan.level_present_mask().to_le_bytes());
+    }
     body
 }
 
-fn project_grid_position_nm(
+/// v2 and v3 keep the original 64-byte header; v4 extends it.
+fn wire_header_bytes(wire_version: u16) -> usize {
+    if wire_version >= WIRE_V4_VERSION {
+        WIRE_V4_HEADER_BYTES
+    } else {
+        WIRE_HEADER_BYTES
+    }
+}
+
+pub(crate) fn project_grid_position_nm(
     scan: &ScanSnapshot,
     window: &QueryWindow,
     row: f64,
@@ -642,48 +1260,232 @@ fn project_grid_position_nm(
     (x_nm, z_nm)
 }
 
-fn build_echo_top_cells(scan: &ScanSnapshot, window: &QueryWindow) -> Vec<EchoTopCellRecord> {
-    let mut cells = Vec::new();
-    for record in &scan.echo_tops {
+/// Echo-top records inside the query window, with their projected x/z NM.
+fn echo_tops_in_window<'a>(
+    scan: &'a ScanSnapshot,
+    window: &'a QueryWindow,
+) -> impl Iterator<Item = (&'a StoredEchoTop, f64, f64)> + 'a {
+    scan.echo_tops.iter().filter_map(move |record| {
         let row = record.row as u32;
         let col = record.col as u32;
         if row < window.row_start || row > window.row_end {
-            continue;
+            return None;
         }
         if !window.lon_wrapped && (col < window.col_start || col > window.col_end) {
-            continue;
+            return None;
         }
 
         let (x_nm, z_nm) = project_grid_position_nm(scan, window, row as f64, col as f64);
-        if x_nm * x_nm + z_nm * z_nm > window.max_range_squared_nm {
-            continue;
-        }
+        window
+            .contains_nm(x_nm, z_nm)
+            .then_some((record, x_nm, z_nm))
+    })
+}
 
-        cells.push(EchoTopCellRecord {
+fn build_echo_top_cells(scan: &ScanSnapshot, window: &QueryWindow) -> Vec<EchoTopCellRecord> {
+    echo_tops_in_window(scan, window)
+        .map(|(record, x_nm, z_nm)| EchoTopCellRecord {
             x_nm: x_nm as f32,
             z_nm: z_nm as f32,
             top18_feet: record.top18_feet,
             top30_feet: record.top30_feet,
             top50_feet: record.top50_feet,
             top60_feet: record.top60_feet,
-        });
+        })
+        .collect()
+}
+
+fn build_echo_tops_wire_v1(scan: &ScanSnapshot, window: &QueryWindow) -> Vec<u8> {
+    let mut cells: Vec<MergeCell<EchoTopKey>> = echo_tops_in_window(scan, window)
+        .map(|(record, _, _)| MergeCell {
+            row: record.row as u32,
+            col: record.col as u32,
+            key: EchoTopKey {
+                top18_feet: record.top18_feet,
+                top30_feet: record.top30_feet,
+                top50_feet: record.top50_feet,
+                top60_feet: record.top60_feet,
+            },
+            payload: (),
+        })
+        .collect();
+    let window_cell_count = cells.len().min(u32::MAX as usize) as u32;
+    let rectangles = build_rectangles(&mut cells);
+
+    let mut body = vec![0_u8; ECHO_TOPS_WIRE_HEADER_BYTES];
+    body[0..4].copy_from_slice(&ECHO_TOPS_WIRE_MAGIC);
+    body[4..6].copy_from_slice(&ECHO_TOPS_WIRE_V1_VERSION.to_le_bytes());
+    body[6..8].copy_from_slice(&(ECHO_TOPS_WIRE_HEADER_BYTES as u16).to_le_bytes());
+    body[8..12].copy_from_slice(&window_cell_count.to_le_bytes());
+    body[16..18].copy_from_slice(&0_u16.to_le_bytes());
+    body[18..20].copy_from_slice(&(ECHO_TOPS_WIRE_RECORD_BYTES as u16).to_le_bytes());
+    body[20..28].copy_from_slice(&scan.generated_at_ms.to_le_bytes());
+    body[28..36].copy_from_slice(&scan.scan_time_ms.to_le_bytes());
+    body[36..38].copy_from_slice(&window.footprint_x_milli.to_le_bytes());
+    body[38..40].copy_from_slice(&window.footprint_y_milli.to_le_bytes());
+    body[42..44].copy_from_slice(&round_u16(window.max_range_nm * 10.0).to_le_bytes());
+    body[44..48]
+        .copy_from_slice(&(scan.echo_tops.len().min(u32::MAX as usize) as u32).to_le_bytes());
+    body[48..52].copy_from_slice(&((window.origin_lat * 1_000_000.0).round() as i32).to_le_bytes());
+    body[52..56].copy_from_slice(&((window.origin_lon * 1_000_000.0).round() as i32).to_le_bytes());
+    body[56..58].copy_from_slice(
+        &scan
+            .echo_top_debug
+            .max_top18_feet
+            .unwrap_or(0)
+            .to_le_bytes(),
+    );
+    body[58..60].copy_from_slice(
+        &scan
+            .echo_top_debug
+            .max_top30_feet
+            .unwrap_or(0)
+            .to_le_bytes(),
+    );
+    body[60..62].copy_from_slice(
+        &scan
+            .echo_top_debug
+            .max_top50_feet
+            .unwrap_or(0)
+            .to_le_bytes(),
+    );
+    body[62..64].copy_from_slice(
+        &scan
+            .echo_top_debug
+            .max_top60_feet
+            .unwrap_or(0)
+            .to_le_bytes(),
+    );
+
+    let mut record_count: u32 = 0;
+    for rect in rectangles {
+        let center_row = (rect.row_start as f64 + rect.row_end as f64) * 0.5;
+        let center_col = (rect.col_start as f64 + rect.col_end as f64) * 0.5;
+        let (x_nm, z_nm) = project_grid_position_nm(scan, window, center_row, center_col);
+        let span_x = (rect.col_end - rect.col_start + 1).min(u16::MAX as u32) as u16;
+        let span_y = (rect.row_end - rect.row_start + 1).min(u16::MAX as u32) as u16;
+
+        body.extend_from_slice(&round_i16(x_nm * 100.0).to_le_bytes());
+        body.extend_from_slice(&round_i16(z_nm * 100.0).to_le_bytes());
+        body.extend_from_slice(&rect.key.top18_feet.to_le_bytes());
+        body.extend_from_slice(&rect.key.top30_feet.to_le_bytes());
+        body.extend_from_slice(&rect.key.top50_feet.to_le_bytes());
+        body.extend_from_slice(&rect.key.top60_feet.to_le_bytes());
+        body.extend_from_slice(&span_x.to_le_bytes());
+        body.extend_from_slice(&span_y.to_le_bytes());
+        record_count = record_count.saturating_add(1);
     }
-    cells
+
+    body[12..16].copy_from_slice(&record_count.to_le_bytes());
+    body
+}
+
+/// Media ranges from the request `Accept` header with their q-values.
+fn parse_accept(request_headers: &HeaderMap) -> Vec<(String, f32)> {
+    request_headers
+        .get_all("accept")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let media_type = parts.next()?.trim().to_ascii_lowercase();
+            if media_type.is_empty() {
+                return None;
+            }
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((media_type, quality))
+        })
+        .collect()
 }
 
-fn build_volume_wire_v2(scan: &ScanSnapshot, window: &QueryWindow) -> Vec<u8> {
+/// True when the request `Accept` header lists `media_type` with a non-zero q.
+fn accepts_media_type(request_headers: &HeaderMap, media_type: &str) -> bool {
+    parse_accept(request_headers)
+        .iter()
+        .any(|(candidate, quality)| candidate.eq_ignore_ascii_case(media_type) && *quality > 0.0)
+}
+
+/// Picks the volume wire version for a request. A missing `Accept` header or a
+/// wildcard gets the newest version; explicit `mrms.vN` entries are honored by
+/// q-value (ties go to the newer version). Returns `None` when nothing listed
+/// is supported, which the handler turns into 406.
+fn negotiate_volume_wire_version(request_headers: &HeaderMap) -> Option<u16> {
+    let latest = *WIRE_SUPPORTED_VERSIONS.iter().max()?;
+    let entries = parse_accept(request_headers);
+    if entries.is_empty() {
+        return Some(latest);
+    }
+
+    let mut best: Option<(f32, u16)> = None;
+    for (media_type, quality) in entries {
+        if quality <= 0.0 {
+            continue;
+        }
+        let version = match media_type.as_str() {
+            "*/*" | "application/*" | "application/octet-stream" => latest,
+            _ => match media_type
+                .strip_prefix(WIRE_MEDIA_TYPE_PREFIX)
+                .and_then(|value| value.parse::<u16>().ok())
+            {
+                Some(version) if WIRE_SUPPORTED_VERSIONS.contains(&version) => version,
+                _ => continue,
+            },
+        };
+        match best {
+            Some((best_quality, best_version))
+                if quality < best_quality
+                    || (quality == best_quality && version <= best_version) => {}
+            _ => best = Some((quality, version)),
+        }
+    }
+
+    best.map(|(_, version)| version)
+}
+
+/// Encodes merged bricks. v2 and v3 share the record layout; v3 fills byte 18
+/// with the surface precip phase, which v2 leaves reserved. v4 keeps v3 records
+/// and adds LOD rings and the level-present mask to the header; callers only
+/// pass a non-`Off` LOD mode for v4.
+fn build_volume_wire_v2(
+    scan: &ScanSnapshot,
+    window: &QueryWindow,
+    filter: &VolumeFilter,
+    lod: LodMode,
+    wire_version: u16,
+) -> Vec<u8> {
     let mut body = build_wire_header(
         scan,
         window,
-        WIRE_V3_VERSION,
+        wire_version,
         WIRE_V2_RECORD_BYTES as u16,
         WIRE_V2_DBZ_QUANT_STEP_TENTHS as u16,
     );
+    if lod != LodMode::Off {
+        // Only rings that start inside the query range are recorded; zeros mean unused.
+        let used_rings = WIRE_LOD_RINGS
+            .iter()
+            .filter(|(start_nm, _)| *start_nm < window.max_range_nm);
+        for (idx, (start_nm, factor)) in used_rings.enumerate() {
+            let offset = WIRE_LOD_RINGS_OFFSET + idx * 4;
+            body[offset..offset + 2].copy_from_slice(&round_u16(*start_nm).to_le_bytes());
+            body[offset + 2..offset + 4].copy_from_slice(&factor.to_le_bytes());
+        }
+    }
 
-    let layer_counts_offset = WIRE_HEADER_BYTES;
+    let layer_counts_offset = wire_header_bytes(wire_version);
     let mut layer_counts = vec![0_u32; scan.level_bounds.len()];
     let mut source_voxel_count: u32 = 0;
-    let mut cells_by_level: Vec<Vec<MergeCell>> = vec![Vec::new(); scan.level_bounds.len()];
+    let mut cells_by_level: Vec<Vec<VoxelCell>> = vec![Vec::new(); scan.level_bounds.len()];
+    let level_included: Vec<bool> = scan
+        .level_bounds
+        .iter()
+        .map(|bounds| filter.includes_level(bounds))
+        .collect();
+    let mut lod_blocks: HashMap<(usize, u32, u32, u32), LodAccumulator> = HashMap::new();
 
     for tile_row in window.tile_row_start..=window.tile_row_end {
         for tile_col in window.tile_col_start..=window.tile_col_end {
@@ -705,18 +1507,31 @@ fn build_volume_wire_v2(scan: &ScanSnapshot, window: &QueryWindow) -> Vec<u8> {
                 if record.dbz_tenths < window.min_dbz_tenths {
                     continue;
                 }
-
-                let (x_nm, z_nm) = project_grid_position_nm(scan, window, row as f64, col as f64);
-                if x_nm * x_nm + z_nm * z_nm > window.max_range_squared_nm {
+                if !filter.includes_voxel(record.dbz_tenths, record.phase) {
                     continue;
                 }
 
                 let level_idx = record.level_idx as usize;
-                if level_idx >= cells_by_level.len() {
+                if level_idx >= cells_by_level.len() || !level_included[level_idx] {
                     continue;
                 }
+
+                let (x_nm, z_nm) = project_grid_position_nm(scan, window, row as f64, col as f64);
+                if !window.contains_nm(x_nm, z_nm) {
+                    continue;
+                }
+
                 layer_counts[level_idx] = layer_counts[level_idx].saturating_add(1);
                 source_voxel_count = source_voxel_count.saturating_add(1);
+
+                let factor = lod_factor_for_cell(scan, window, lod, row, col);
+                if factor > 1 {
+                    lod_blocks
+                        .entry((level_idx, factor, row / factor, col / factor))
+                        .or_default()
+                        .add(record.dbz_tenths, record.phase, record.surface_phase);
+                    continue;
+                }
                 cells_by_level[level_idx].push(MergeCell {
                     row,
                     col,
@@ -727,17 +1542,44 @@ fn build_volume_wire_v2(scan: &ScanSnapshot, window: &QueryWindow) -> Vec<u8> {
                             WIRE_V2_DBZ_QUANT_STEP_TENTHS,
                         ),
                     },
-                    surface_phase: record.surface_phase,
+                    payload: record.surface_phase,
                 });
             }
         }
     }
 
-    let mut rectangles_by_level: Vec<Vec<HorizontalRect>> =
-        Vec::with_capacity(cells_by_level.len());
-    for cells in &mut cells_by_level {
-        let mut rectangles = build_level_rectangles(cells);
-        let mut split_rectangles: Vec<HorizontalRect> = Vec::with_capacity(rectangles.len());
+    // Each super-cell becomes one cell of a coarse grid per (level, factor);
+    // merged coarse rectangles are scaled back to grid cells afterwards.
+    let mut super_cells: HashMap<(usize, u32), Vec<VoxelCell>> = HashMap::new();
+    for ((level_idx, factor, block_row, block_col), block) in lod_blocks {
+        super_cells
+            .entry((level_idx, factor))
+            .or_default()
+            .push(MergeCell {
+                row: block_row,
+                col: block_col,
+                key: MergeKey {
+                    phase: majority_phase(&block.phase_counts),
+                    dbz_tenths: quantize_dbz_tenths(
+                        block.dbz_tenths(lod),
+                        WIRE_V2_DBZ_QUANT_STEP_TENTHS,
+                    ),
+                },
+                payload: majority_phase(&block.surface_phase_counts),
+            });
+    }
+    let mut super_rects_by_level: Vec<Vec<VoxelRect>> = vec![Vec::new(); cells_by_level.len()];
+    for ((level_idx, factor), mut cells) in super_cells {
+        for rect in build_rectangles(&mut cells) {
+            super_rects_by_level[level_idx].push(scale_super_cell_rect(scan, window, rect, factor));
+        }
+    }
+
+    let mut rectangles_by_level: Vec<Vec<VoxelRect>> = Vec::with_capacity(cells_by_level.len());
+    for (cells, super_rects) in cells_by_level.iter_mut().zip(super_rects_by_level) {
+        let mut rectangles = build_rectangles(cells);
+        rectangles.extend(super_rects);
+        let mut split_rectangles: Vec<VoxelRect> = Vec::with_capacity(rectangles.len());
         for rect in rectangles.drain(..) {
             let max_span = max_span_for_dbz(rect.key.dbz_tenths);
             split_rectangle(rect, max_span, &mut split_rectangles);
@@ -786,7 +1628,7 @@ fn build_volume_wire_v2(scan: &ScanSnapshot, window: &QueryWindow) -> Vec<u8> {
                     level_start: level_idx as u8,
                     level_end: level_idx as u8,
                     key: rect.key,
-                    surface_phase: rect.surface_phase,
+                    surface_phase: rect.payload,
                 });
                 next_active.insert(signature, new_idx);
             }
@@ -808,7 +1650,7 @@ fn build_volume_wire_v2(scan: &ScanSnapshot, window: &QueryWindow) -> Vec<u8> {
         let center_row = (brick.row_start as f64 + brick.row_end as f64) * 0.5;
         let center_col = (brick.col_start as f64 + brick.col_end as f64) * 0.5;
         let (x_nm, z_nm) = project_grid_position_nm(scan, window, center_row, center_col);
-        if x_nm * x_nm + z_nm * z_nm > window.max_range_squared_nm {
+        if !window.contains_nm(x_nm, z_nm) {
             continue;
         }
 
@@ -826,7 +1668,11 @@ fn build_volume_wire_v2(scan: &ScanSnapshot, window: &QueryWindow) -> Vec<u8> {
         body.extend_from_slice(&span_x.to_le_bytes());
         body.extend_from_slice(&span_y.to_le_bytes());
         body.extend_from_slice(&span_z.to_le_bytes());
-        body.push(brick.surface_phase); // offset 18: surface_phase
+        if wire_version >= WIRE_V3_VERSION {
+            body.push(brick.surface_phase); // offset 18: surface_phase
+        } else {
+            body.push(0); // offset 18: reserved in v2
+        }
         body.push(0); // offset 19: reserved
         brick_count = brick_count.saturating_add(1);
     }
@@ -841,6 +1687,33 @@ fn build_volume_wire_v2(scan: &ScanSnapshot, window: &QueryWindow) -> Vec<u8> {
     body
 }
 
+/// Scales a rectangle of `factor`-sized super-cells back to grid cells,
+/// clipped to the query window and the grid.
+fn scale_super_cell_rect(
+    scan: &ScanSnapshot,
+    window: &QueryWindow,
+    rect: VoxelRect,
+    factor: u32,
+) -> VoxelRect {
+    let row_start = (rect.row_start * factor).max(window.row_start);
+    let row_end = (rect.row_end * factor + factor - 1)
+        .min(window.row_end)
+        .min(scan.grid.ny.saturating_sub(1));
+    let mut col_start = rect.col_start * factor;
+    let mut col_end = (rect.col_end * factor + factor - 1).min(scan.grid.nx.saturating_sub(1));
+    if !window.lon_wrapped {
+        col_start = col_start.max(window.col_start);
+        col_end = col_end.min(window.col_end);
+    }
+    HorizontalRect {
+        row_start,
+        row_end,
+        col_start,
+        col_end,
+        ..rect
+    }
+}
+
 fn quantize_dbz_tenths(dbz_tenths: i16, step_tenths: i16) -> i16 {
     if step_tenths <= 1 {
         return dbz_tenths;
@@ -864,7 +1737,7 @@ fn max_span_for_dbz(dbz_tenths: i16) -> u16 {
     }
 }
 
-fn split_rectangle(rect: HorizontalRect, max_span: u16, out: &mut Vec<HorizontalRect>) {
+fn split_rectangle(rect: VoxelRect, max_span: u16, out: &mut Vec<VoxelRect>) {
     let chunk_size = max_span.max(1) as u32;
     let mut row_start = rect.row_start;
     while row_start <= rect.row_end {
@@ -878,7 +1751,7 @@ fn split_rectangle(rect: HorizontalRect, max_span: u16, out: &mut Vec<Horizontal
                 col_start,
                 col_end,
                 key: rect.key,
-                surface_phase: rect.surface_phase,
+                payload: rect.payload,
             });
             if col_end == rect.col_end {
                 break;
@@ -892,7 +1765,13 @@ fn split_rectangle(rect: HorizontalRect, max_span: u16, out: &mut Vec<Horizontal
     }
 }
 
-fn build_level_rectangles(cells: &mut [MergeCell]) -> Vec<HorizontalRect> {
+/// Merges same-key cells into row runs, then stacks identical runs on
+/// consecutive rows into rectangles.
+fn build_rectangles<K, P>(cells: &mut [MergeCell<K, P>]) -> Vec<HorizontalRect<K, P>>
+where
+    K: Copy + Eq + Hash + Ord,
+    P: Copy,
+{
     if cells.is_empty() {
         return Vec::new();
     }
@@ -901,49 +1780,41 @@ fn build_level_rectangles(cells: &mut [MergeCell]) -> Vec<HorizontalRect> {
         a.row
             .cmp(&b.row)
             .then(a.col.cmp(&b.col))
-            .then(a.key.phase.cmp(&b.key.phase))
-            .then(a.key.dbz_tenths.cmp(&b.key.dbz_tenths))
+            .then(a.key.cmp(&b.key))
     });
 
-    let mut runs_by_row: BTreeMap<u32, Vec<RowRun>> = BTreeMap::new();
+    let mut runs_by_row: BTreeMap<u32, Vec<RowRun<K, P>>> = BTreeMap::new();
     let mut run_row = cells[0].row;
-    let mut run_col_start = cells[0].col;
-    let mut run_col_end = cells[0].col;
-    let mut run_key = cells[0].key;
-    let mut run_surface_phase = cells[0].surface_phase;
+    let mut run = RowRun {
+        col_start: cells[0].col,
+        col_end: cells[0].col,
+        key: cells[0].key,
+        payload: cells[0].payload,
+    };
 
     for cell in &cells[1..] {
-        if cell.row == run_row && cell.key == run_key {
-            if cell.col == run_col_end {
+        if cell.row == run_row && cell.key == run.key {
+            if cell.col == run.col_end {
                 continue;
             }
-            if cell.col == run_col_end.saturating_add(1) {
-                run_col_end = cell.col;
+            if cell.col == run.col_end.saturating_add(1) {
+                run.col_end = cell.col;
                 continue;
             }
         }
-        runs_by_row.entry(run_row).or_default().push(RowRun {
-            col_start: run_col_start,
-            col_end: run_col_end,
-            key: run_key,
-            surface_phase: run_surface_phase,
-        });
+        runs_by_row.entry(run_row).or_default().push(run);
         run_row = cell.row;
-        run_col_start = cell.col;
-        run_col_end = cell.col;
-        run_key = cell.key;
-        run_surface_phase = cell.surface_phase;
+        run = RowRun {
+            col_start: cell.col,
+            col_end: cell.col,
+            key: cell.key,
+            payload: cell.payload,
+        };
     }
+    runs_by_row.entry(run_row).or_default().push(run);
 
-    runs_by_row.entry(run_row).or_default().push(RowRun {
-        col_start: run_col_start,
-        col_end: run_col_end,
-        key: run_key,
-        surface_phase: run_surface_phase,
-    });
-
-    let mut rectangles: Vec<HorizontalRect> = Vec::new();
-    let mut active: HashMap<RunSignature, usize> = HashMap::new();
+    let mut rectangles: Vec<HorizontalRect<K, P>> = Vec::new();
+    let mut active: HashMap<RunSignature<K>, usize> = HashMap::new();
     let mut prev_row: Option<u32> = None;
 
     for (row, runs) in runs_by_row {
@@ -952,7 +1823,7 @@ fn build_level_rectangles(cells: &mut [MergeCell]) -> Vec<HorizontalRect> {
                 active.clear();
             }
         }
-        let mut next_active: HashMap<RunSignature, usize> = HashMap::new();
+        let mut next_active: HashMap<RunSignature<K>, usize> = HashMap::new();
         for run in runs {
             let signature = RunSignature {
                 col_start: run.col_start,
@@ -970,7 +1841,7 @@ fn build_level_rectangles(cells: &mut [MergeCell]) -> Vec<HorizontalRect> {
                     col_start: run.col_start,
                     col_end: run.col_end,
                     key: run.key,
-                    surface_phase: run.surface_phase,
+                    payload: run.payload,
                 });
                 next_active.insert(signature, rect_idx);
             }
@@ -981,3 +1852,370 @@ fn build_level_rectangles(cells: &mut [MergeCell]) -> Vec<HorizontalRect> {
 
     rectangles
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::WIRE_V2_VERSION;
+    use crate::types::{test_scan, StoredVoxel};
+
+    fn echo_top_cell(row: u32, col: u32, top18_feet: u16) -> MergeCell<EchoTopKey> {
+        MergeCell {
+            row,
+            col,
+            key: EchoTopKey {
+                top18_feet,
+                top30_feet: 0,
+                top50_feet: 0,
+                top60_feet: 0,
+            },
+            payload: (),
+        }
+    }
+
+    #[test]
+    fn build_rectangles_merges_identical_echo_top_blocks() {
+        let mut cells = vec![
+            echo_top_cell(4, 10, 25_000),
+            echo_top_cell(4, 11, 25_000),
+            echo_top_cell(5, 10, 25_000),
+            echo_top_cell(5, 11, 25_000),
+            echo_top_cell(5, 12, 31_000),
+        ];
+        let mut rectangles = build_rectangles(&mut cells);
+        rectangles.sort_by_key(|rect| rect.col_start);
+
+        assert_eq!(rectangles.len(), 2);
+        assert_eq!(
+            (
+                rectangles[0].row_start,
+                rectangles[0].row_end,
+                rectangles[0].col_start,
+                rectangles[0].col_end
+            ),
+            (4, 5, 10, 11)
+        );
+        assert_eq!(rectangles[1].key.top18_feet, 31_000);
+    }
+
+    #[test]
+    fn accepts_media_type_honors_lists_and_zero_quality() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "accept",
+            HeaderValue::from_static("application/json, application/vnd.approach-viz.echo-tops.v1"),
+        );
+        assert!(accepts_media_type(&headers, ECHO_TOPS_WIRE_MEDIA_TYPE));
+
+        headers.insert(
+            "accept",
+            HeaderValue::from_static("application/vnd.approach-viz.echo-tops.v1;q=0"),
+        );
+        assert!(!accepts_media_type(&headers, ECHO_TOPS_WIRE_MEDIA_TYPE));
+        assert!(!accepts_media_type(
+            &HeaderMap::new(),
+            ECHO_TOPS_WIRE_MEDIA_TYPE
+        ));
+    }
+
+    fn accept_headers(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("accept", HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn negotiate_volume_wire_version_defaults_to_latest() {
+        assert_eq!(
+            negotiate_volume_wire_version(&HeaderMap::new()),
+            Some(WIRE_V4_VERSION)
+        );
+        assert_eq!(
+            negotiate_volume_wire_version(&accept_headers("*/*")),
+            Some(WIRE_V4_VERSION)
+        );
+    }
+
+    #[test]
+    fn negotiate_volume_wire_version_pins_requested_version() {
+        assert_eq!(
+            negotiate_volume_wire_version(&accept_headers("application/vnd.approach-viz.mrms.v2")),
+            Some(WIRE_V2_VERSION)
+        );
+        assert_eq!(
+            negotiate_volume_wire_version(&accept_headers(
+                "application/vnd.approach-viz.mrms.v3;q=0.5, application/vnd.approach-viz.mrms.v2"
+            )),
+            Some(WIRE_V2_VERSION)
+        );
+        assert_eq!(
+            negotiate_volume_wire_version(&accept_headers(
+                "application/vnd.approach-viz.mrms.v9, application/vnd.approach-viz.mrms.v3"
+            )),
+            Some(WIRE_V3_VERSION)
+        );
+    }
+
+    #[test]
+    fn negotiate_volume_wire_version_rejects_unknown_versions() {
+        assert_eq!(
+            negotiate_volume_wire_version(&accept_headers("application/vnd.approach-viz.mrms.v9")),
+            None
+        );
+        assert_eq!(
+            negotiate_volume_wire_version(&accept_headers("application/json")),
+            None
+        );
+    }
+
+    fn volume_query(
+        max_dbz: Option<f64>,
+        min_alt_feet: Option<f64>,
+        max_alt_feet: Option<f64>,
+        phases: Option<&str>,
+    ) -> VolumeQuery {
+        VolumeQuery {
+            lat: 40.0,
+            lon: -75.0,
+            min_dbz: None,
+            max_dbz,
+            max_range_nm: None,
+            min_alt_feet,
+            max_alt_feet,
+            phases: phases.map(str::to_string),
+            lod: None,
+            bbox: None,
+            corridor: None,
+            corridor_nm: None,
+        }
+    }
+
+    #[test]
+    fn build_volume_filter_applies_band_phase_and_dbz_limits() {
+        let query = volume_query(Some(40.0), Some(0.0), Some(15_000.0), Some("rain, Snow"));
+        let filter = build_volume_filter(&query, DEFAULT_MIN_DBZ).expect("valid filter");
+
+        assert!(filter.includes_voxel(400, PHASE_RAIN));
+        assert!(!filter.includes_voxel(405, PHASE_RAIN));
+        assert!(!filter.includes_voxel(200, PHASE_MIXED));
+        assert!(filter.includes_voxel(200, PHASE_SNOW));
+        assert!(filter.includes_level(&LevelBounds {
+            bottom_feet: 14_500,
+            top_feet: 16_000,
+        }));
+        assert!(!filter.includes_level(&LevelBounds {
+            bottom_feet: 15_500,
+            top_feet: 17_000,
+        }));
+
+        let unfiltered = build_volume_filter(&volume_query(None, None, None, None), 5.0)
+            .expect("defaults are valid");
+        assert_eq!(unfiltered, VolumeFilter::default());
+        assert!(unfiltered.includes_voxel(700, PHASE_MIXED));
+    }
+
+    #[test]
+    fn build_volume_filter_rejects_inverted_or_unknown_values() {
+        assert!(build_volume_filter(&volume_query(Some(10.0), None, None, None), 20.0).is_err());
+        assert!(
+            build_volume_filter(&volume_query(None, Some(9_000.0), Some(3_000.0), None), 5.0)
+                .is_err()
+        );
+        assert!(build_volume_filter(&volume_query(None, None, None, Some("hail")), 5.0).is_err());
+        assert!(build_volume_filter(&volume_query(None, None, None, Some(",")), 5.0).is_err());
+    }
+
+    fn stored_voxel(row: u16, col: u16, phase: u8, dbz_tenths: i16) -> StoredVoxel {
+        StoredVoxel {
+            row,
+            col,
+            level_idx: 0,
+            phase,
+            surface_phase: phase,
+            dbz_tenths,
+        }
+    }
+
+    #[test]
+    fn far_cells_aggregate_into_lod_super_cells() {
+        // Cols 160..=163 sit ~70 NM east of the origin, inside the 2x ring.
+        let scan = test_scan(
+            200,
+            8,
+            1,
+            vec![
+                stored_voxel(4, 160, PHASE_SNOW, 200),
+                stored_voxel(4, 161, PHASE_SNOW, 400),
+                stored_voxel(5, 160, PHASE_RAIN, 150),
+                stored_voxel(5, 161, PHASE_SNOW, 250),
+                stored_voxel(4, 163, PHASE_RAIN, 300),
+            ],
+        );
+        let window = build_query_window(&scan, 39.955, -105.0, 5.0, 120.0);
+        let filter = VolumeFilter::default();
+        let record_at = |body: &[u8], idx: usize| {
+            let offset = WIRE_V4_HEADER_BYTES + 4 + idx * WIRE_V2_RECORD_BYTES;
+            body[offset..offset + WIRE_V2_RECORD_BYTES].to_vec()
+        };
+
+        let full = build_volume_wire_v2(&scan, &window, &filter, LodMode::Off, WIRE_V4_VERSION);
+        assert_eq!(u32::from_le_bytes(full[12..16].try_into().unwrap()), 5);
+        assert_eq!(&full[56..64], &[0_u8; 8]);
+        assert_eq!(u64::from_le_bytes(full[64..72].try_into().unwrap()), 1);
+
+        let lod = build_volume_wire_v2(&scan, &window, &filter, LodMode::Max, WIRE_V4_VERSION);
+        assert_eq!(u32::from_le_bytes(lod[8..12].try_into().unwrap()), 5);
+        assert_eq!(u32::from_le_bytes(lod[12..16].try_into().unwrap()), 2);
+        assert_eq!(u16::from_le_bytes(lod[56..58].try_into().unwrap()), 60);
+        assert_eq!(u16::from_le_bytes(lod[58..60].try_into().unwrap()), 2);
+        assert_eq!(&lod[60..64], &[0_u8; 4]);
+
+        let mut records: Vec<Vec<u8>> = (0..2).map(|idx| record_at(&lod, idx)).collect();
+        records.sort_by_key(|record| i16::from_le_bytes([record[0], record[1]]));
+        let block = &records[0];
+        assert_eq!(i16::from_le_bytes([block[8], block[9]]), 400);
+        assert_eq!(block[10], PHASE_SNOW);
+        assert_eq!(u16::from_le_bytes([block[12], block[13]]), 2);
+        assert_eq!(u16::from_le_bytes([block[14], block[15]]), 2);
+
+        let mean = build_volume_wire_v2(&scan, &window, &filter, LodMode::Mean, WIRE_V4_VERSION);
+        let mut records: Vec<Vec<u8>> = (0..2).map(|idx| record_at(&mean, idx)).collect();
+        records.sort_by_key(|record| i16::from_le_bytes([record[0], record[1]]));
+        assert_eq!(i16::from_le_bytes([records[0][8], records[0][9]]), 250);
+    }
+
+    #[test]
+    fn far_super_cell_is_one_brick_clipped_to_the_window() {
+        // Col 150 sits ~69 NM east of the origin, inside the 2x ring.
+        let scan = test_scan(200, 8, 1, vec![stored_voxel(5, 150, PHASE_RAIN, 300)]);
+        let mut window = build_query_window(&scan, 39.955, -105.0, 5.0, 120.0);
+        let filter = VolumeFilter::default();
+        let record_spans = |body: &[u8]| {
+            assert_eq!(u32::from_le_bytes(body[12..16].try_into().unwrap()), 1);
+            let offset = WIRE_V4_HEADER_BYTES + 4;
+            let record = &body[offset..offset + WIRE_V2_RECORD_BYTES];
+            (
+                u16::from_le_bytes([record[12], record[13]]),
+                u16::from_le_bytes([record[14], record[15]]),
+            )
+        };
+
+        let lod = build_volume_wire_v2(&scan, &window, &filter, LodMode::Max, WIRE_V4_VERSION);
+        assert_eq!(record_spans(&lod), (2, 2));
+
+        // The block spans rows 4..=5; row 4 falls outside this window.
+        window.row_start = 5;
+        let clipped = build_volume_wire_v2(&scan, &window, &filter, LodMode::Max, WIRE_V4_VERSION);
+        assert_eq!(record_spans(&clipped), (2, 1));
+    }
+
+    #[test]
+    fn lod_shrinks_sparse_far_ring_payloads() {
+        // Isolated voxels ~64-69 NM east of the origin never touch at full
+        // resolution, so only the super-cell grid can merge them.
+        let voxels: Vec<StoredVoxel> = (0..4_u16)
+            .flat_map(|row| (0..6_u16).map(move |col| (row * 2, 140 + col * 2)))
+            .map(|(row, col)| stored_voxel(row, col, PHASE_RAIN, 300))
+            .collect();
+        let scan = test_scan(200, 8, 1, voxels);
+        let window = build_query_window(&scan, 39.955, -105.0, 5.0, 120.0);
+        let filter = VolumeFilter::default();
+        let brick_count = |body: &[u8]| u32::from_le_bytes(body[12..16].try_into().unwrap());
+
+        let full = build_volume_wire_v2(&scan, &window, &filter, LodMode::Off, WIRE_V4_VERSION);
+        let lod = build_volume_wire_v2(&scan, &window, &filter, LodMode::Max, WIRE_V4_VERSION);
+        assert_eq!(brick_count(&full), 24);
+        assert!(brick_count(&lod) < brick_count(&full));
+        assert!(lod.len() < full.len());
+    }
+
+    #[test]
+    fn query_shapes_share_the_range_cap() {
+        let resolve = |bbox: &str| {
+            resolve_query_shape(40.0, -105.0, Some(bbox), None, None, DEFAULT_MAX_RANGE_NM)
+        };
+        assert!(resolve("38,-106,42,-104").is_ok());
+        assert_eq!(
+            resolve("36,-106,44,-104").unwrap_err(),
+            "Query shape extends beyond 220 NM from the origin."
+        );
+        let corridor = resolve_query_shape(
+            40.0,
+            -105.0,
+            None,
+            Some("40,-105;40,-99"),
+            None,
+            DEFAULT_MAX_RANGE_NM,
+        );
+        assert!(corridor.is_err());
+    }
+
+    #[test]
+    fn scan_headers_carry_times_and_echo_top_timestamps() {
+        let mut scan = test_scan(200, 8, 1, Vec::new());
+        scan.scan_time_ms = 1_770_899_696_000;
+        scan.echo_top_debug.top30_timestamp = Some("20260212-123400".to_string());
+        let mut headers = HeaderMap::new();
+        scan_time_headers(&mut headers, MrmsDomain::Conus, &scan);
+        echo_top_timestamp_headers(&mut headers, &scan);
+
+        assert_eq!(headers["X-AV-MRMS-DOMAIN"], "conus");
+        assert!(headers.contains_key("X-AV-SCAN-TIME"));
+        assert_eq!(headers["X-AV-ECHOTOP30-TIMESTAMP"], "20260212-123400");
+        assert!(!headers.contains_key("X-AV-ECHOTOP18-TIMESTAMP"));
+    }
+
+    #[test]
+    fn wire_header_marks_missing_levels() {
+        let mut scan = test_scan(200, 8, 1, vec![stored_voxel(4, 160, PHASE_RAIN, 300)]);
+        let base = scan.level_bounds[0];
+        scan.level_bounds = vec![base; 4];
+        scan.missing_levels = vec![1, 3];
+        let window = build_query_window(&scan, 39.955, -105.0, 5.0, 120.0);
+
+        let body = build_volume_wire_v2(
+            &scan,
+            &window,
+            &VolumeFilter::default(),
+            LodMode::Off,
+            WIRE_V4_VERSION,
+        );
+        assert_eq!(
+            u16::from_le_bytes(body[6..8].try_into().unwrap()) as usize,
+            WIRE_V4_HEADER_BYTES
+        );
+        assert_eq!(
+            u64::from_le_bytes(
+                body[WIRE_LEVEL_MASK_OFFSET..WIRE_V4_HEADER_BYTES]
+                    .try_into()
+                    .unwrap()
+            ),
+            0b0101
+        );
+    }
+
+    #[test]
+    fn v2_and_v3_keep_the_64_byte_header() {
+        let scan = test_scan(200, 8, 1, vec![stored_voxel(4, 160, PHASE_RAIN, 300)]);
+        let window = build_query_window(&scan, 39.955, -105.0, 5.0, 120.0);
+        for version in [WIRE_V2_VERSION, WIRE_V3_VERSION] {
+            let body = build_volume_wire_v2(
+                &scan,
+                &window,
+                &VolumeFilter::default(),
+                LodMode::Off,
+                version,
+            );
+            assert_eq!(
+                u16::from_le_bytes(body[6..8].try_into().unwrap()) as usize,
+                WIRE_HEADER_BYTES
+            );
+            assert_eq!(&body[56..WIRE_HEADER_BYTES], &[0_u8; 8]);
+            let layer_count = u16::from_le_bytes(body[16..18].try_into().unwrap()) as usize;
+            assert_eq!(
+                body.len(),
+                WIRE_HEADER_BYTES + layer_count * 4 + WIRE_V2_RECORD_BYTES
+            );
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{PoisonError, RwLock};
+
+use crate::constants::{
+    AVAILABILITY_RETENTION_SECONDS, MRMS_PRODUCT_PREFIX, MRMS_RHOHV_PRODUCT_PREFIX,
+    MRMS_ZDR_PRODUCT_PREFIX,
+};
+use crate::utils::parse_timestamp_utc;
+
+/// Object timestamps seen in SNS notifications, per product directory
+/// (`MergedZdr_03.00`, `PrecipFlag_00.00`, ...) for one domain.
+///
+/// Objects of a product are published in timestamp order, so once the index
+/// holds a timestamp at or before a target, any newer object at or before that
+/// target would have been seen too. Lookups that find nothing still fall back
+/// to bucket listing, which covers objects published before startup.
+#[derive(Default)]
+pub struct AvailabilityIndex {
+    by_product: RwLock<HashMap<String, BTreeSet<String>>>,
+}
+
+impl AvailabilityIndex {
+    /// Records an object; returns `true` when it was not already known.
+    pub fn record(&self, product: &str, timestamp: &str) -> bool {
+        let mut by_product = self
+            .by_product
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let timestamps = by_product.entry(product.to_string()).or_default();
+        let inserted = timestamps.insert(timestamp.to_string());
+        if inserted && timestamps.last().map(String::as_str) == Some(timestamp) {
+            prune_older_than_retention(timestamps, timestamp);
+        }
+        inserted
+    }
+
+    pub fn contains(&self, product: &str, timestamp: &str) -> bool {
+        self.by_product
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(product)
+            .is_some_and(|timestamps| timestamps.contains(timestamp))
+    }
+
+    pub fn latest_at_or_before(&self, product: &str, target_timestamp: &str) -> Option<String> {
+        self.by_product
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(product)?
+            .range(..=target_timestamp.to_string())
+            .next_back()
+            .cloned()
+    }
+
+    /// Reflectivity and dual-pol level products not yet seen for `timestamp`.
+    pub fn missing_scan_products(&self, level_tags: &[String], timestamp: &str) -> Vec<String> {
+        scan_products(level_tags)
+            .filter(|product| !self.contains(product, timestamp))
+            .collect()
+    }
+
+    pub fn product_count(&self) -> usize {
+        self.by_product
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+/// Level product directories a complete scan needs at its own timestamp.
+pub fn scan_products(level_tags: &[String]) -> impl Iterator<Item = String> + '_ {
+    [
+        MRMS_PRODUCT_PREFIX,
+        MRMS_ZDR_PRODUCT_PREFIX,
+        MRMS_RHOHV_PRODUCT_PREFIX,
+    ]
+    .into_iter()
+    .flat_map(move |prefix| {
+        level_tags
+            .iter()
+            .map(move |level_tag| format!("{prefix}_{level_tag}"))
+    })
+}
+
+fn prune_older_than_retention(timestamps: &mut BTreeSet<String>, newest: &str) {
+    let Some(newest_dt) = parse_timestamp_utc(newest) else {
+        return;
+    };
+    let cutoff = (newest_dt - chrono::Duration::seconds(AVAILABILITY_RETENTION_SECONDS))
+        .format("%Y%m%d-%H%M%S")
+        .to_string();
+    *timestamps = timestamps.split_off(&cutoff);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::MrmsDomain;
+
+    #[test]
+    fn index_answers_lookups_and_prunes_old_entries() {
+        let index = AvailabilityIndex::default();
+        assert!(index.record("PrecipFlag_00.00", "20260212-120000"));
+        assert!(!index.record("PrecipFlag_00.00", "20260212-120000"));
+        index.record("PrecipFlag_00.00", "20260212-120200");
+
+        assert_eq!(
+            index
+                .latest_at_or_before("PrecipFlag_00.00", "20260212-120159")
+                .as_deref(),
+            Some("20260212-120000")
+        );
+        assert_eq!(
+            index.latest_at_or_before("PrecipFlag_00.00", "20260212-115959"),
+            None
+        );
+
+        index.record("PrecipFlag_00.00", "20260213-120000");
+        assert!(!index.contains("PrecipFlag_00.00", "20260212-120000"));
+    }
+
+    #[test]
+    fn missing_scan_products_tracks_levels_and_dual_pol() {
+        let index = AvailabilityIndex::default();
+        let timestamp = "20260212-120000";
+        let level_tags: Vec<String> = MrmsDomain::Conus
+            .builtin_level_tags()
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect();
+        let total = scan_products(&level_tags).count();
+        assert_eq!(
+            index.missing_scan_products(&level_tags, timestamp).len(),
+            total
+        );
+
+        for product in scan_products(&level_tags) {
+            index.record(&product, timestamp);
+        }
+        assert!(index
+            .missing_scan_products(&level_tags, timestamp)
+            .is_empty());
+    }
+}
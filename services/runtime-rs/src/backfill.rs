@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::discovery::{discover_level_tags, list_base_level_timestamps_for_day};
+use crate::domain::MrmsDomain;
+use crate::ingest::{ingest_timestamp, probe_missing_scan_products};
+use crate::ingest_policy::IngestPolicy;
+use crate::readiness::RuntimeHealth;
+use crate::source::IngestSource;
+use crate::storage::persist_snapshot;
+use crate::traffic_api::TrafficCache;
+use crate::traffic_poller::TrafficStore;
+use crate::types::{AppState, DomainState};
+use crate::utils::parse_timestamp_utc;
+
+const BACKFILL_USAGE: &str = "usage: approach-viz-runtime backfill --from YYYYMMDD-HHMMSS --to YYYYMMDD-HHMMSS [--out DIR] [--domains conus,alaska,...]";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackfillArgs {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub out_dir: Option<PathBuf>,
+    pub domains: Option<Vec<MrmsDomain>>,
+}
+
+/// Parses the arguments that follow the `backfill` subcommand.
+pub fn parse_backfill_args(args: &[String]) -> Result<BackfillArgs> {
+    let mut from = None;
+    let mut to = None;
+    let mut out_dir = None;
+    let mut domains = None;
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let Some(value) = iter.next() else {
+            bail!("Missing value for {flag}\n{BACKFILL_USAGE}");
+        };
+        match flag.as_str() {
+            "--from" => from = Some(parse_backfill_timestamp(flag, value)?),
+            "--to" => to = Some(parse_backfill_timestamp(flag, value)?),
+            "--out" => out_dir = Some(PathBuf::from(value)),
+            "--domains" => {
+                let mut parsed = Vec::new();
+                for entry in value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|entry| !entry.is_empty())
+                {
+                    let Some(domain) = MrmsDomain::parse(entry) else {
+                        bail!("Unknown MRMS domain '{entry}' in --domains");
+                    };
+                    if !parsed.contains(&domain) {
+                        parsed.push(domain);
+                    }
+                }
+                if parsed.is_empty() {
+                    bail!("--domains must list at least one domain");
+                }
+                domains = Some(parsed);
+            }
+            other => bail!("Unknown backfill argument '{other}'\n{BACKFILL_USAGE}"),
+        }
+    }
+
+    let (Some(from), Some(to)) = (from, to) else {
+        bail!("backfill requires --from and --to\n{BACKFILL_USAGE}");
+    };
+    if from > to {
+        bail!("--from must not be after --to");
+    }
+
+    Ok(BackfillArgs {
+        from,
+        to,
+        out_dir,
+        domains,
+    })
+}
+
+fn parse_backfill_timestamp(flag: &str, value: &str) -> Result<DateTime<Utc>> {
+    parse_timestamp_utc(value)
+        .with_context(|| format!("{flag} must be YYYYMMDD-HHMMSS, got '{value}'"))
+}
+
+/// Ingests every base-level timestamp in `[from, to]` and writes one snapshot
+/// per scan, without starting the HTTP server. Retention is disabled so the
+/// output directory keeps the whole case study.
+pub async fn run_backfill(
+    mut cfg: Config,
+    http: Client,
+    source: Arc<dyn IngestSource>,
+    args: BackfillArgs,
+) -> Result<()> {
+    let from_timestamp = args.from.format("%Y%m%d-%H%M%S").to_string();
+    let to_timestamp = args.to.format("%Y%m%d-%H%M%S").to_string();
+
+    cfg.storage_dir = args.out_dir.clone().unwrap_or_else(|| {
+        cfg.storage_dir
+            .join("backfill")
+            .join(format!("{from_timestamp}_{to_timestamp}"))
+    });
+    cfg.retention_bytes = u64::MAX;
+    cfg.domain_retention_bytes.clear();
+    if let Some(domains) = args.domains.clone() {
+        cfg.mrms_domains = domains;
+    }
+
+    let mut domains = BTreeMap::new();
+    for domain in &cfg.mrms_domains {
+        let domain_state = DomainState::new(*domain, None);
+        discover_level_tags(source.as_ref(), &domain_state).await;
+        domains.insert(*domain, Arc::new(domain_state));
+    }
+    let state = AppState {
+        cfg: Arc::new(cfg),
+        http,
+        source,
+        domains: Arc::new(domains),
+        health: Arc::new(RuntimeHealth::default()),
+        traffic_cache: Arc::new(TrafficCache::default()),
+        traffic_store: Arc::new(TrafficStore::default()),
+    };
+
+    info!(
+        "Backfilling {from_timestamp}..{to_timestamp} from {} into {}",
+        state.source.describe(),
+        state.cfg.scans_dir().display()
+    );
+
+    let mut total_written = 0usize;
+    let mut total_failed = 0usize;
+    for domain in state.cfg.mrms_domains.clone() {
+        let mut timestamps = Vec::new();
+        let mut day = args.from.date_naive();
+        while day <= args.to.date_naive() {
+            let day_part = day.format("%Y%m%d").to_string();
+            let listed =
+                list_base_level_timestamps_for_day(state.source.as_ref(), domain, &day_part)
+                    .await
+                    .with_context(|| format!("Failed listing {} {day_part}", domain.id()))?;
+            timestamps.extend(listed.into_iter().filter(|timestamp| {
+                timestamp.as_str() >= from_timestamp.as_str()
+                    && timestamp.as_str() <= to_timestamp.as_str()
+            }));
+            day = day.succ_opt().context("Backfill date overflow")?;
+        }
+
+        if timestamps.is_empty() {
+            warn!("No {} base-level scans found in range", domain.id());
+            continue;
+        }
+        info!("Backfilling {} {} scans", timestamps.len(), domain.id());
+
+        for timestamp in timestamps {
+            let Some(domain_state) = state.domain_state(domain) else {
+                continue;
+            };
+            let missing_scan_products =
+                probe_missing_scan_products(&state, domain_state, &timestamp).await;
+            let result = match ingest_timestamp(
+                &state,
+                domain,
+                &timestamp,
+                IngestPolicy::Backfill,
+                missing_scan_products,
+            )
+            .await
+            {
+                Ok(scan) => persist_snapshot(&state.cfg, domain, scan).await,
+                Err(error) => Err(error),
+            };
+            match result {
+                Ok(()) => {
+                    total_written += 1;
+                    info!("Backfilled {} {timestamp}", domain.id());
+                }
+                Err(error) => {
+                    total_failed += 1;
+                    warn!("Backfill failed for {} {timestamp}: {error:#}", domain.id());
+                }
+            }
+        }
+    }
+
+    info!("Backfill finished: {total_written} snapshots written, {total_failed} failed");
+    if total_written == 0 && total_failed > 0 {
+        bail!("Backfill wrote no snapshots");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_backfill_args_reads_range_and_options() {
+        let parsed = parse_backfill_args(&args(&[
+            "--from",
+            "20250601-000000",
+            "--to",
+            "20250601-060000",
+            "--out",
+            "/tmp/case",
+            "--domains",
+            "conus,alaska",
+        ]))
+        .expect("valid args");
+
+        assert_eq!(parsed.from, parse_timestamp_utc("20250601-000000").unwrap());
+        assert_eq!(parsed.to, parse_timestamp_utc("20250601-060000").unwrap());
+        assert_eq!(parsed.out_dir, Some(PathBuf::from("/tmp/case")));
+        assert_eq!(
+            parsed.domains,
+            Some(vec![MrmsDomain::Conus, MrmsDomain::Alaska])
+        );
+    }
+
+    #[test]
+    fn parse_backfill_args_rejects_bad_input() {
+        assert!(parse_backfill_args(&args(&["--from", "20250601-000000"])).is_err());
+        assert!(parse_backfill_args(&args(&[
+            "--from",
+            "20250601-060000",
+            "--to",
+            "20250601-000000"
+        ]))
+        .is_err());
+        assert!(parse_backfill_args(&args(&["--from", "2025-06-01", "--to", "x"])).is_err());
+        assert!(parse_backfill_args(&args(&["--bogus", "1"])).is_err());
+    }
+}
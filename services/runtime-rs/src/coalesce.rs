@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use futures::future::{BoxFuture, Shared};
+use futures::FutureExt;
+
+type SharedFetch<V> = Shared<BoxFuture<'static, Result<V, String>>>;
+
+enum Slot<V> {
+    Ready { value: V, expires_at: Instant },
+    InFlight(SharedFetch<V>),
+}
+
+/// How `get_or_fetch` was answered, for metrics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheOutcome {
+    Hit,
+    /// Joined a fetch another request had already started.
+    Coalesced,
+    Miss,
+}
+
+impl CacheOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheOutcome::Hit => "hit",
+            CacheOutcome::Coalesced => "coalesced",
+            CacheOutcome::Miss => "miss",
+        }
+    }
+}
+
+/// Short-lived cache that also merges concurrent fetches of the same key into
+/// one. Fetches run on their own task, so a caller that disconnects does not
+/// cancel the fetch for the others. Only successful results are cached.
+pub struct CoalescingCache<K, V> {
+    ttl: Duration,
+    slots: Mutex<HashMap<K, Slot<V>>>,
+}
+
+impl<K, V> CoalescingCache<K, V>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub fn new(ttl: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            ttl,
+            slots: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub async fn get_or_fetch<F>(
+        self: &Arc<Self>,
+        key: K,
+        fetch: F,
+    ) -> (Result<V, String>, CacheOutcome)
+    where
+        F: Future<Output = Result<V, String>> + Send + 'static,
+    {
+        let (shared, outcome) = {
+            let mut slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            match slots.get(&key) {
+                Some(Slot::Ready { value, expires_at }) if *expires_at > now => {
+                    return (Ok(value.clone()), CacheOutcome::Hit);
+                }
+                Some(Slot::InFlight(shared)) => (shared.clone(), CacheOutcome::Coalesced),
+                _ => {
+                    slots.retain(|_, slot| match slot {
+                        Slot::Ready { expires_at, .. } => *expires_at > now,
+                        Slot::InFlight(_) => true,
+                    });
+                    let shared = self.spawn_fetch(key.clone(), fetch);
+                    slots.insert(key, Slot::InFlight(shared.clone()));
+                    (shared, CacheOutcome::Miss)
+                }
+            }
+        };
+        (shared.await, outcome)
+    }
+
+    fn spawn_fetch<F>(self: &Arc<Self>, key: K, fetch: F) -> SharedFetch<V>
+    where
+        F: Future<Output = Result<V, String>> + Send + 'static,
+    {
+        let cache = Arc::clone(self);
+        let task_key = key.clone();
+        let task = tokio::spawn(async move {
+            let result = fetch.await;
+            cache.complete(&task_key, &result);
+            result
+        });
+
+        let cache = Arc::clone(self);
+        async move {
+            task.await.unwrap_or_else(|error| {
+                cache.complete(&key, &Err(String::new()));
+                Err(format!("Upstream fetch task failed: {error}"))
+            })
+        }
+        .boxed()
+        .shared()
+    }
+
+    fn complete(&self, key: &K, result: &Result<V, String>) {
+        let mut slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        match result {
+            Ok(value) => {
+                slots.insert(
+                    key.clone(),
+                    Slot::Ready {
+                        value: value.clone(),
+                        expires_at: Instant::now() + self.ttl,
+                    },
+                );
+            }
+            Err(_) => {
+                if matches!(slots.get(key), Some(Slot::InFlight(_))) {
+                    slots.remove(key);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn concurrent_fetches_share_one_upstream_call() {
+        let cache = CoalescingCache::<&str, usize>::new(Duration::from_millis(100));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let fetch = |calls: Arc<AtomicUsize>| async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(calls.fetch_add(1, Ordering::SeqCst) + 1)
+        };
+
+        let (first, second) = tokio::join!(
+            cache.get_or_fetch("kjfk", fetch(calls.clone())),
+            cache.get_or_fetch("kjfk", fetch(calls.clone())),
+        );
+        assert_eq!(first, (Ok(1), CacheOutcome::Miss));
+        assert_eq!(second, (Ok(1), CacheOutcome::Coalesced));
+        assert_eq!(
+            cache.get_or_fetch("kjfk", fetch(calls.clone())).await,
+            (Ok(1), CacheOutcome::Hit)
+        );
+
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(
+            cache.get_or_fetch("kjfk", fetch(calls.clone())).await,
+            (Ok(2), CacheOutcome::Miss)
+        );
+
+        let failed = cache
+            .get_or_fetch("kbos", async { Err::<usize, _>("HTTP 429".to_string()) })
+            .await;
+        assert_eq!(failed, (Err("HTTP 429".to_string()), CacheOutcome::Miss));
+        assert_eq!(
+            cache.get_or_fetch("kbos", fetch(calls.clone())).await,
+            (Ok(3), CacheOutcome::Miss)
+        );
+    }
+}
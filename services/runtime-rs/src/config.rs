@@ -1,101 +1,316 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
 
+use crate::access::RateLimits;
 use crate::constants::{
-    DEFAULT_BOOTSTRAP_INTERVAL_SECONDS, DEFAULT_PENDING_RETRY_SECONDS,
-    DEFAULT_REQUEST_TIMEOUT_SECONDS, DEFAULT_RETENTION_BYTES, DEFAULT_SQS_POLL_DELAY_SECONDS,
-    DEFAULT_TILE_SIZE,
+    DEFAULT_BOOTSTRAP_INTERVAL_SECONDS, DEFAULT_FORWARDED_FOR_HOPS,
+    DEFAULT_INGEST_DEADLINE_SECONDS, DEFAULT_LISTING_CACHE_TTL_SECONDS, DEFAULT_MRMS_BUCKET_URL,
+    DEFAULT_PENDING_RETRY_SECONDS, DEFAULT_RATE_LIMIT_PER_MINUTE,
+    DEFAULT_READY_MAX_CONSECUTIVE_FAILURES, DEFAULT_READY_MAX_SCAN_AGE_SECONDS,
+    DEFAULT_READY_MAX_SQS_SILENCE_SECONDS, DEFAULT_REQUEST_TIMEOUT_SECONDS,
+    DEFAULT_RETENTION_BYTES, DEFAULT_SHUTDOWN_GRACE_SECONDS, DEFAULT_SQS_POLL_DELAY_SECONDS,
+    DEFAULT_TILE_SIZE, DEFAULT_TRAFFIC_POLL_INTERVAL_SECONDS, DEFAULT_TRAFFIC_STREAMS_PER_CLIENT,
+    DEFAULT_UPGRADE_WINDOW_SECONDS, MAX_TILE_SIZE, MIN_TILE_SIZE, TRAFFIC_TRACK_RETENTION,
 };
+use crate::domain::MrmsDomain;
+use crate::readiness::ReadinessLimits;
+use crate::s3_listing::S3ListStyle;
+use crate::traffic_poller::{TrafficPollConfig, TrafficPollRegion};
 
 #[derive(Clone)]
 pub struct Config {
     pub listen_addr: String,
     pub storage_dir: PathBuf,
     pub retention_bytes: u64,
+    pub domain_retention_bytes: HashMap<MrmsDomain, u64>,
+    pub mrms_domains: Vec<MrmsDomain>,
     pub request_timeout: Duration,
     pub bootstrap_interval: Duration,
     pub sqs_poll_delay: Duration,
     pub pending_retry_delay: Duration,
+    pub ingest_deadline: Duration,
+    pub upgrade_window: Duration,
     pub aws_region: String,
     pub sqs_queue_url: Option<String>,
+    pub source_dir: Option<PathBuf>,
+    pub mrms_bucket_url: String,
+    pub mrms_list_style: S3ListStyle,
+    pub listing_cache_ttl: Duration,
     pub tile_size: u16,
     pub adsbx_primary_base_url: String,
     pub adsbx_fallback_base_urls: Vec<String>,
+    pub traffic_poll: TrafficPollConfig,
+    /// Bearer token for `/v1/admin/*`; admin endpoints are disabled when unset.
+    pub admin_token: Option<String>,
+    /// Keys accepted on `/v1/*` data routes; no key is required when empty.
+    pub api_keys: Vec<String>,
+    pub rate_limits: RateLimits,
+    /// Take the client IP for rate limiting from `X-Forwarded-For`.
+    pub trust_forwarded_for: bool,
+    /// Trusted proxies appending to `X-Forwarded-For`; the client IP is the
+    /// entry this many places from the right.
+    pub forwarded_for_hops: usize,
+    /// `["*"]` allows any origin.
+    pub cors_allowed_origins: Vec<String>,
+    pub readiness: ReadinessLimits,
+    /// How long shutdown waits for an in-flight ingest and open HTTP
+    /// connections before giving up on them.
+    pub shutdown_grace: Duration,
+    /// TOML file layered under the env vars, from `RUNTIME_CONFIG_FILE`.
+    pub config_file: Option<PathBuf>,
+    /// Every setting's resolved value and source, secrets redacted.
+    pub effective: Vec<EffectiveSetting>,
 }
 
+/// Env var naming the optional TOML config file.
+const CONFIG_FILE_ENV: &str = "RUNTIME_CONFIG_FILE";
+
+/// File keys whose values `/v1/admin/config` never echoes.
+const SECRET_KEYS: &[&str] = &["admin_token", "api_keys"];
+
 impl Config {
+    /// Reads env vars layered over the optional `RUNTIME_CONFIG_FILE` TOML
+    /// file, then the built-in defaults.
     pub fn from_env() -> Result<Self> {
-        let listen_addr =
-            env_string_with_fallback("RUNTIME_LISTEN_ADDR", "MRMS_LISTEN_ADDR", "127.0.0.1:9191");
-        let storage_dir = PathBuf::from(env_string_with_fallback(
+        Self::from_layers(ConfigLayers::from_process()?)
+    }
+
+    fn from_layers(layers: ConfigLayers) -> Result<Self> {
+        let listen_addr = layers.parsed(
+            "RUNTIME_LISTEN_ADDR",
+            Some("MRMS_LISTEN_ADDR"),
+            "127.0.0.1:9191",
+            parse_listen_addr,
+        )?;
+        let storage_dir = PathBuf::from(layers.string(
             "RUNTIME_STORAGE_DIR",
-            "MRMS_STORAGE_DIR",
+            Some("MRMS_STORAGE_DIR"),
             "/var/lib/approach-viz-runtime",
-        ));
-        let retention_bytes = env_u64_with_fallback(
+        )?);
+        let retention_bytes = layers.u64_in(
             "RUNTIME_MRMS_RETENTION_BYTES",
-            "MRMS_RETENTION_BYTES",
+            Some("MRMS_RETENTION_BYTES"),
             DEFAULT_RETENTION_BYTES,
+            1..=u64::MAX,
         )?;
-        let request_timeout = Duration::from_secs(env_u64_with_fallback(
+        let mrms_domains = layers.parsed(
+            "RUNTIME_MRMS_DOMAINS",
+            Some("MRMS_DOMAINS"),
+            "conus",
+            parse_domain_list,
+        )?;
+        let mut domain_retention_bytes = HashMap::new();
+        for domain in MrmsDomain::ALL {
+            let name = format!(
+                "RUNTIME_MRMS_RETENTION_BYTES_{}",
+                domain.id().to_ascii_uppercase()
+            );
+            let bytes = layers.optional_u64_in(&name, 1..=u64::MAX)?;
+            if let Some(bytes) = bytes.filter(|_| mrms_domains.contains(&domain)) {
+                domain_retention_bytes.insert(domain, bytes);
+            }
+        }
+        let request_timeout = layers.seconds_in(
             "RUNTIME_MRMS_REQUEST_TIMEOUT_SECONDS",
-            "MRMS_REQUEST_TIMEOUT_SECONDS",
+            Some("MRMS_REQUEST_TIMEOUT_SECONDS"),
             DEFAULT_REQUEST_TIMEOUT_SECONDS,
-        )?);
-        let bootstrap_interval = Duration::from_secs(env_u64_with_fallback(
+            1..=300,
+        )?;
+        let bootstrap_interval = layers.seconds_in(
             "RUNTIME_MRMS_BOOTSTRAP_INTERVAL_SECONDS",
-            "MRMS_BOOTSTRAP_INTERVAL_SECONDS",
+            Some("MRMS_BOOTSTRAP_INTERVAL_SECONDS"),
             DEFAULT_BOOTSTRAP_INTERVAL_SECONDS,
-        )?);
-        let sqs_poll_delay = Duration::from_secs(env_u64_with_fallback(
+            1..=86_400,
+        )?;
+        let sqs_poll_delay = layers.seconds_in(
             "RUNTIME_MRMS_SQS_POLL_DELAY_SECONDS",
-            "MRMS_SQS_POLL_DELAY_SECONDS",
+            Some("MRMS_SQS_POLL_DELAY_SECONDS"),
             DEFAULT_SQS_POLL_DELAY_SECONDS,
-        )?);
-        let pending_retry_delay = Duration::from_secs(env_u64_with_fallback(
+            0..=300,
+        )?;
+        let pending_retry_delay = layers.seconds_in(
             "RUNTIME_MRMS_PENDING_RETRY_SECONDS",
-            "MRMS_PENDING_RETRY_SECONDS",
+            Some("MRMS_PENDING_RETRY_SECONDS"),
             DEFAULT_PENDING_RETRY_SECONDS,
-        )?);
-        let aws_region = env_string("AWS_REGION", "us-east-1");
-        let sqs_queue_url = env_optional("RUNTIME_MRMS_SQS_QUEUE_URL")
-            .or_else(|| env_optional("MRMS_SQS_QUEUE_URL"));
-        let tile_size = env_u16_with_fallback(
-            "RUNTIME_MRMS_TILE_SIZE",
-            "MRMS_TILE_SIZE",
-            DEFAULT_TILE_SIZE,
+            1..=3_600,
+        )?;
+        let ingest_deadline = layers.seconds_in(
+            "RUNTIME_MRMS_INGEST_DEADLINE_SECONDS",
+            None,
+            DEFAULT_INGEST_DEADLINE_SECONDS,
+            0..=3_600,
+        )?;
+        let upgrade_window = layers.seconds_in(
+            "RUNTIME_MRMS_UPGRADE_WINDOW_SECONDS",
+            None,
+            DEFAULT_UPGRADE_WINDOW_SECONDS,
+            0..=86_400,
         )?;
+        let aws_region = layers.string("AWS_REGION", None, "us-east-1")?;
+        let sqs_queue_url =
+            layers.optional("RUNTIME_MRMS_SQS_QUEUE_URL", Some("MRMS_SQS_QUEUE_URL"))?;
+        let source_dir = layers
+            .optional("RUNTIME_MRMS_SOURCE_DIR", None)?
+            .map(PathBuf::from);
+        let mrms_bucket_url = layers.parsed(
+            "RUNTIME_MRMS_BUCKET_URL",
+            Some("MRMS_BUCKET_URL"),
+            DEFAULT_MRMS_BUCKET_URL,
+            parse_base_url,
+        )?;
+        let mrms_list_style =
+            layers.parsed("RUNTIME_MRMS_BUCKET_LIST_STYLE", None, "v2", |value| {
+                S3ListStyle::parse(value).ok_or_else(|| format!("must be v1 or v2, got '{value}'"))
+            })?;
+        let listing_cache_ttl = layers.seconds_in(
+            "RUNTIME_MRMS_LISTING_CACHE_TTL_SECONDS",
+            None,
+            DEFAULT_LISTING_CACHE_TTL_SECONDS,
+            0..=3_600,
+        )?;
+        let tile_size = layers.u64_in(
+            "RUNTIME_MRMS_TILE_SIZE",
+            Some("MRMS_TILE_SIZE"),
+            u64::from(DEFAULT_TILE_SIZE),
+            MIN_TILE_SIZE..=MAX_TILE_SIZE,
+        )? as u16;
 
-        let adsbx_primary_base_url = trim_base_url(&env_string_with_fallback(
+        let adsbx_primary_base_url = layers.parsed(
             "RUNTIME_ADSBX_TAR1090_BASE_URL",
-            "ADSBX_TAR1090_BASE_URL",
+            Some("ADSBX_TAR1090_BASE_URL"),
             "https://globe.adsbexchange.com",
-        ));
-        let adsbx_fallback_base_urls = env_string_with_fallback(
+            parse_base_url,
+        )?;
+        let adsbx_fallback_base_urls = layers.parsed(
             "RUNTIME_ADSBX_TAR1090_FALLBACK_BASE_URLS",
-            "ADSBX_TAR1090_FALLBACK_BASE_URLS",
+            Some("ADSBX_TAR1090_FALLBACK_BASE_URLS"),
             "https://globe.theairtraffic.com",
-        )
-        .split(',')
-        .map(trim_base_url)
-        .filter(|entry| !entry.is_empty())
-        .collect::<Vec<_>>();
+            |value| {
+                split_list(value)
+                    .map(parse_base_url)
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+        let traffic_poll_interval = layers.seconds_in(
+            "RUNTIME_TRAFFIC_POLL_INTERVAL_SECONDS",
+            None,
+            DEFAULT_TRAFFIC_POLL_INTERVAL_SECONDS,
+            1..=300,
+        )?;
+        // Trails must reach back as far as the longest `historyMinutes`.
+        let min_history_points = TRAFFIC_TRACK_RETENTION
+            .as_secs()
+            .div_ceil(traffic_poll_interval.as_secs());
+        let traffic_poll = TrafficPollConfig {
+            regions: layers.parsed("RUNTIME_TRAFFIC_POLL_REGIONS", None, "", |value| {
+                split_list(value)
+                    .map(TrafficPollRegion::parse)
+                    .collect::<Result<Vec<_>, _>>()
+            })?,
+            interval: traffic_poll_interval,
+            history_points: layers.u64_in(
+                "RUNTIME_TRAFFIC_HISTORY_POINTS",
+                None,
+                min_history_points,
+                min_history_points..=10_000,
+            )? as usize,
+        };
+        let admin_token = layers.optional("RUNTIME_ADMIN_TOKEN", None)?;
+        let api_keys = layers.parsed("RUNTIME_API_KEYS", None, "", |value| {
+            Ok(split_list(value).map(str::to_string).collect())
+        })?;
+        let rate_limit = |name| {
+            layers
+                .u64_in(name, None, DEFAULT_RATE_LIMIT_PER_MINUTE, 0..=1_000_000)
+                .map(|value| value as u32)
+        };
+        let rate_limits = RateLimits {
+            default_per_minute: rate_limit("RUNTIME_RATE_LIMIT_PER_MINUTE")?,
+            volume_per_minute: rate_limit("RUNTIME_RATE_LIMIT_VOLUME_PER_MINUTE")?,
+            traffic_per_minute: rate_limit("RUNTIME_RATE_LIMIT_TRAFFIC_PER_MINUTE")?,
+            traffic_streams_per_client: layers.u64_in(
+                "RUNTIME_TRAFFIC_STREAMS_PER_CLIENT",
+                None,
+                DEFAULT_TRAFFIC_STREAMS_PER_CLIENT,
+                0..=1_000,
+            )? as u32,
+        };
+        let trust_forwarded_for =
+            layers.parsed("RUNTIME_TRUST_FORWARDED_FOR", None, "false", parse_bool)?;
+        let forwarded_for_hops = layers.u64_in(
+            "RUNTIME_FORWARDED_FOR_HOPS",
+            None,
+            DEFAULT_FORWARDED_FOR_HOPS,
+            1..=10,
+        )? as usize;
+        let cors_allowed_origins =
+            layers.parsed("RUNTIME_CORS_ALLOWED_ORIGINS", None, "*", parse_origin_list)?;
+        let readiness = ReadinessLimits {
+            max_scan_age: layers.seconds_in(
+                "RUNTIME_READY_MAX_SCAN_AGE_SECONDS",
+                None,
+                DEFAULT_READY_MAX_SCAN_AGE_SECONDS,
+                1..=u64::MAX,
+            )?,
+            max_sqs_silence: layers.seconds_in(
+                "RUNTIME_READY_MAX_SQS_SILENCE_SECONDS",
+                None,
+                DEFAULT_READY_MAX_SQS_SILENCE_SECONDS,
+                1..=u64::MAX,
+            )?,
+            max_consecutive_failures: layers.u64_in(
+                "RUNTIME_READY_MAX_CONSECUTIVE_FAILURES",
+                None,
+                DEFAULT_READY_MAX_CONSECUTIVE_FAILURES,
+                1..=u64::from(u32::MAX),
+            )? as u32,
+        };
+        let shutdown_grace = layers.seconds_in(
+            "RUNTIME_SHUTDOWN_GRACE_SECONDS",
+            None,
+            DEFAULT_SHUTDOWN_GRACE_SECONDS,
+            0..=600,
+        )?;
+        let (config_file, effective) = layers.finish()?;
 
         Ok(Self {
             listen_addr,
             storage_dir,
             retention_bytes,
+            domain_retention_bytes,
+            mrms_domains,
             request_timeout,
             bootstrap_interval,
             sqs_poll_delay,
             pending_retry_delay,
+            ingest_deadline,
+            upgrade_window,
             aws_region,
             sqs_queue_url,
+            source_dir,
+            mrms_bucket_url,
+            mrms_list_style,
+            listing_cache_ttl,
             tile_size,
             adsbx_primary_base_url,
             adsbx_fallback_base_urls,
+            traffic_poll,
+            admin_token,
+            api_keys,
+            rate_limits,
+            trust_forwarded_for,
+            forwarded_for_hops,
+            cors_allowed_origins,
+            readiness,
+            shutdown_grace,
+            config_file,
+            effective,
         })
     }
 
@@ -103,6 +318,29 @@ impl Config {
         self.storage_dir.join("scans")
     }
 
+    /// CONUS snapshots stay at the top of `scans/` so existing deployments
+    /// keep loading them; other domains get a subdirectory each.
+    pub fn domain_scans_dir(&self, domain: MrmsDomain) -> PathBuf {
+        match domain {
+            MrmsDomain::Conus => self.scans_dir(),
+            other => self.scans_dir().join(other.id()),
+        }
+    }
+
+    /// Pending ingest queue saved on shutdown and reloaded on start.
+    pub fn pending_queue_path(&self, domain: MrmsDomain) -> PathBuf {
+        self.storage_dir
+            .join("pending")
+            .join(format!("{}.json", domain.id()))
+    }
+
+    pub fn retention_bytes_for(&self, domain: MrmsDomain) -> u64 {
+        self.domain_retention_bytes
+            .get(&domain)
+            .copied()
+            .unwrap_or(self.retention_bytes)
+    }
+
     pub fn traffic_base_urls(&self) -> Vec<String> {
         let mut deduped = Vec::new();
         for candidate in std::iter::once(&self.adsbx_primary_base_url)
@@ -123,59 +361,396 @@ impl Config {
     }
 }
 
-fn trim_base_url(value: &str) -> String {
-    value.trim().trim_end_matches('/').to_string()
+fn parse_domain_list(value: &str) -> Result<Vec<MrmsDomain>, String> {
+    let mut domains = Vec::new();
+    for entry in split_list(value) {
+        let Some(domain) = MrmsDomain::parse(entry) else {
+            return Err(format!("lists unknown MRMS domain '{entry}'"));
+        };
+        if !domains.contains(&domain) {
+            domains.push(domain);
+        }
+    }
+    if domains.is_empty() {
+        return Err("must list at least one domain".to_string());
+    }
+    Ok(domains)
 }
 
-fn env_optional(name: &str) -> Option<String> {
-    std::env::var(name)
-        .ok()
-        .map(|value| value.trim().to_string())
-        .filter(|value| !value.is_empty())
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
 }
 
-fn env_string_with_fallback(primary: &str, fallback: &str, default: &str) -> String {
-    env_optional(primary)
-        .or_else(|| env_optional(fallback))
-        .unwrap_or_else(|| default.to_string())
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" => Ok(true),
+        "false" | "0" | "no" => Ok(false),
+        _ => Err(format!("must be true or false, got '{value}'")),
+    }
+}
+
+fn parse_origin_list(value: &str) -> Result<Vec<String>, String> {
+    let origins = split_list(value)
+        .map(|origin| {
+            if origin == "*" {
+                return Ok(origin.to_string());
+            }
+            let url = parse_base_url(origin)?;
+            let host = url.split_once("://").map_or("", |(_, host)| host);
+            if host.is_empty() || host.contains('/') {
+                return Err(format!(
+                    "must list origins like https://example.com, got '{origin}'"
+                ));
+            }
+            Ok(url)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if origins.is_empty() {
+        return Err("must list at least one origin or *".to_string());
+    }
+    Ok(origins)
 }
 
-fn env_u64_with_fallback(primary: &str, fallback: &str, default: u64) -> Result<u64> {
-    if let Some(value) = env_optional(primary) {
-        return value
-            .parse::<u64>()
-            .with_context(|| format!("Failed to parse {}={} as u64", primary, value));
+fn parse_listen_addr(value: &str) -> Result<String, String> {
+    match value.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+            Ok(value.to_string())
+        }
+        _ => Err(format!("must be host:port, got '{value}'")),
     }
-    env_u64(fallback, default)
 }
 
-fn env_u16_with_fallback(primary: &str, fallback: &str, default: u16) -> Result<u16> {
-    if let Some(value) = env_optional(primary) {
-        return value
-            .parse::<u16>()
-            .with_context(|| format!("Failed to parse {}={} as u16", primary, value));
+fn parse_base_url(value: &str) -> Result<String, String> {
+    let trimmed = value.trim().trim_end_matches('/');
+    if trimmed.starts_with("http://") || trimmed.starts_with("https://") {
+        Ok(trimmed.to_string())
+    } else {
+        Err(format!("must be an http(s) URL, got '{value}'"))
     }
-    env_u16(fallback, default)
 }
 
-fn env_string(name: &str, default: &str) -> String {
-    std::env::var(name).unwrap_or_else(|_| default.to_string())
+/// Where a setting's effective value came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingSource {
+    Env,
+    File,
+    Default,
+}
+
+/// One resolved setting as reported by `/v1/admin/config`.
+#[derive(Clone, Debug, Serialize)]
+pub struct EffectiveSetting {
+    /// Config file key: the env var name, lowercased, without `RUNTIME_`.
+    pub key: String,
+    pub env: String,
+    /// `None` when unset; secrets are replaced with `REDACTED`.
+    pub value: Option<String>,
+    pub source: SettingSource,
+}
+
+struct RawSetting {
+    value: String,
+    /// Env var name, or `key in <file>`, for error messages.
+    origin: String,
+    source: SettingSource,
 }
 
-fn env_u64(name: &str, default: u64) -> Result<u64> {
-    match std::env::var(name) {
-        Ok(value) => value
-            .parse::<u64>()
-            .with_context(|| format!("Failed to parse {}={} as u64", name, value)),
-        Err(_) => Ok(default),
+/// Reads one env var; injected so tests do not touch the process env.
+type EnvLookup = Box<dyn Fn(&str) -> Option<String>>;
+
+/// Resolves settings from env vars (primary name, then legacy fallback), then
+/// the config file, then defaults, and records each result for the config
+/// dump.
+struct ConfigLayers {
+    env: EnvLookup,
+    file_path: Option<PathBuf>,
+    file: toml::Table,
+    effective: RefCell<Vec<EffectiveSetting>>,
+}
+
+impl ConfigLayers {
+    fn from_process() -> Result<Self> {
+        let file_path = env_optional(CONFIG_FILE_ENV).map(PathBuf::from);
+        let file = match &file_path {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read config file {}", path.display()))?
+                .parse::<toml::Table>()
+                .with_context(|| format!("Failed to parse config file {}", path.display()))?,
+            None => toml::Table::new(),
+        };
+        Ok(Self::new(Box::new(env_optional), file_path, file))
+    }
+
+    fn new(env: EnvLookup, file_path: Option<PathBuf>, file: toml::Table) -> Self {
+        Self {
+            env,
+            file_path,
+            file,
+            effective: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn lookup(&self, primary: &str, fallback: Option<&str>) -> Result<Option<RawSetting>> {
+        for name in std::iter::once(primary).chain(fallback) {
+            if let Some(value) = (self.env)(name) {
+                return Ok(Some(RawSetting {
+                    value,
+                    origin: name.to_string(),
+                    source: SettingSource::Env,
+                }));
+            }
+        }
+
+        let key = file_key(primary);
+        let Some(value) = self.file.get(&key) else {
+            return Ok(None);
+        };
+        let origin = format!("{key} in {}", self.file_display());
+        let value = match value {
+            toml::Value::String(text) => text.trim().to_string(),
+            toml::Value::Integer(number) => number.to_string(),
+            toml::Value::Boolean(flag) => flag.to_string(),
+            toml::Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    toml::Value::String(text) => Ok(text.trim().to_string()),
+                    toml::Value::Integer(number) => Ok(number.to_string()),
+                    _ => bail!("{origin} must only contain strings or integers"),
+                })
+                .collect::<Result<Vec<_>>>()?
+                .join(","),
+            _ => bail!("{origin} must be a string, integer, boolean or array"),
+        };
+        if value.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(RawSetting {
+            value,
+            origin,
+            source: SettingSource::File,
+        }))
+    }
+
+    fn record(&self, env: &str, raw: Option<&RawSetting>, value: Option<String>) {
+        let key = file_key(env);
+        let value = value.map(|value| {
+            if SECRET_KEYS.contains(&key.as_str()) {
+                "REDACTED".to_string()
+            } else {
+                value
+            }
+        });
+        self.effective.borrow_mut().push(EffectiveSetting {
+            key,
+            env: env.to_string(),
+            value,
+            source: raw.map_or(SettingSource::Default, |raw| raw.source),
+        });
+    }
+
+    fn optional(&self, primary: &str, fallback: Option<&str>) -> Result<Option<String>> {
+        let raw = self.lookup(primary, fallback)?;
+        let value = raw.as_ref().map(|raw| raw.value.clone());
+        self.record(primary, raw.as_ref(), value.clone());
+        Ok(value)
+    }
+
+    fn string(&self, primary: &str, fallback: Option<&str>, default: &str) -> Result<String> {
+        Ok(self
+            .optional(primary, fallback)?
+            .unwrap_or_else(|| default.to_string()))
+    }
+
+    /// Like `string`, then runs `parse`; its error is prefixed with the
+    /// env var or file key the value came from.
+    fn parsed<T>(
+        &self,
+        primary: &str,
+        fallback: Option<&str>,
+        default: &str,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> Result<T> {
+        let raw = self.lookup(primary, fallback)?;
+        let value = raw
+            .as_ref()
+            .map_or_else(|| default.to_string(), |raw| raw.value.clone());
+        self.record(primary, raw.as_ref(), Some(value.clone()));
+        parse(&value).map_err(|message| {
+            let origin = raw.as_ref().map_or(primary, |raw| raw.origin.as_str());
+            anyhow!("{origin} {message}")
+        })
+    }
+
+    fn u64_in(
+        &self,
+        primary: &str,
+        fallback: Option<&str>,
+        default: u64,
+        range: RangeInclusive<u64>,
+    ) -> Result<u64> {
+        let raw = self.lookup(primary, fallback)?;
+        let value = match &raw {
+            Some(raw) => parse_u64_in(raw, &range)?,
+            None => default,
+        };
+        self.record(primary, raw.as_ref(), Some(value.to_string()));
+        Ok(value)
+    }
+
+    fn optional_u64_in(&self, name: &str, range: RangeInclusive<u64>) -> Result<Option<u64>> {
+        let raw = self.lookup(name, None)?;
+        let value = raw
+            .as_ref()
+            .map(|raw| parse_u64_in(raw, &range))
+            .transpose()?;
+        self.record(name, raw.as_ref(), value.map(|value| value.to_string()));
+        Ok(value)
+    }
+
+    fn seconds_in(
+        &self,
+        primary: &str,
+        fallback: Option<&str>,
+        default: u64,
+        range: RangeInclusive<u64>,
+    ) -> Result<Duration> {
+        self.u64_in(primary, fallback, default, range)
+            .map(Duration::from_secs)
     }
+
+    /// Rejects file keys no setting read, so typos fail loudly instead of
+    /// silently falling back to a default.
+    fn finish(self) -> Result<(Option<PathBuf>, Vec<EffectiveSetting>)> {
+        let effective = self.effective.take();
+        for key in self.file.keys() {
+            if !effective.iter().any(|setting| &setting.key == key) {
+                bail!("Unknown key '{key}' in {}", self.file_display());
+            }
+        }
+        Ok((self.file_path, effective))
+    }
+
+    fn file_display(&self) -> String {
+        self.file_path.as_deref().map_or_else(
+            || "config file".to_string(),
+            |path| path.display().to_string(),
+        )
+    }
+}
+
+fn parse_u64_in(raw: &RawSetting, range: &RangeInclusive<u64>) -> Result<u64> {
+    let value = raw
+        .value
+        .parse::<u64>()
+        .with_context(|| format!("Failed to parse {}={} as u64", raw.origin, raw.value))?;
+    if range.contains(&value) {
+        return Ok(value);
+    }
+    if *range.end() == u64::MAX {
+        bail!(
+            "{} must be at least {}, got {value}",
+            raw.origin,
+            range.start()
+        );
+    }
+    bail!(
+        "{} must be between {} and {}, got {value}",
+        raw.origin,
+        range.start(),
+        range.end()
+    );
+}
+
+/// `RUNTIME_MRMS_TILE_SIZE` -> `mrms_tile_size`, `AWS_REGION` -> `aws_region`.
+fn file_key(env: &str) -> String {
+    env.strip_prefix("RUNTIME_")
+        .unwrap_or(env)
+        .to_ascii_lowercase()
+}
+
+fn env_optional(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
 }
 
-fn env_u16(name: &str, default: u16) -> Result<u16> {
-    match std::env::var(name) {
-        Ok(value) => value
-            .parse::<u16>()
-            .with_context(|| format!("Failed to parse {}={} as u16", name, value)),
-        Err(_) => Ok(default),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(env: &[(&str, &str)], file: &str) -> ConfigLayers {
+        let env = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        ConfigLayers::new(
+            Box::new(move |name| env.get(name).cloned()),
+            Some(PathBuf::from("runtime.toml")),
+            file.parse::<toml::Table>().expect("valid toml"),
+        )
+    }
+
+    #[test]
+    fn env_overrides_file_and_secrets_are_redacted() {
+        let cfg = Config::from_layers(layers(
+            &[("MRMS_TILE_SIZE", "128"), ("RUNTIME_ADMIN_TOKEN", "s3cret")],
+            "mrms_tile_size = 32\nmrms_domains = [\"conus\", \"alaska\"]\nmrms_retention_bytes_alaska = 1024\n",
+        ))
+        .expect("valid config");
+        assert_eq!(cfg.tile_size, 128);
+        assert_eq!(
+            cfg.mrms_domains,
+            vec![MrmsDomain::Conus, MrmsDomain::Alaska]
+        );
+        assert_eq!(cfg.retention_bytes_for(MrmsDomain::Alaska), 1024);
+
+        let setting = |key: &str| {
+            cfg.effective
+                .iter()
+                .find(|setting| setting.key == key)
+                .cloned()
+                .expect("setting recorded")
+        };
+        assert_eq!(setting("mrms_tile_size").source, SettingSource::Env);
+        assert_eq!(setting("mrms_domains").source, SettingSource::File);
+        assert_eq!(setting("listen_addr").source, SettingSource::Default);
+        assert_eq!(setting("admin_token").value.as_deref(), Some("REDACTED"));
+    }
+
+    #[test]
+    fn validation_errors_name_the_offending_key() {
+        let error = |env: &[(&str, &str)], file: &str| {
+            Config::from_layers(layers(env, file))
+                .err()
+                .expect("invalid config")
+                .to_string()
+        };
+        assert_eq!(
+            error(&[], "mrms_tile_size = 8\n"),
+            "mrms_tile_size in runtime.toml must be between 16 and 1024, got 8"
+        );
+        assert_eq!(
+            error(&[("RUNTIME_MRMS_DOMAINS", "conus,mars")], ""),
+            "RUNTIME_MRMS_DOMAINS lists unknown MRMS domain 'mars'"
+        );
+        assert_eq!(
+            error(&[], "mrms_tile_sise = 64\n"),
+            "Unknown key 'mrms_tile_sise' in runtime.toml"
+        );
+        assert_eq!(
+            error(
+                &[
+                    ("RUNTIME_TRAFFIC_POLL_INTERVAL_SECONDS", "10"),
+                    ("RUNTIME_TRAFFIC_HISTORY_POINTS", "60"),
+                ],
+                ""
+            ),
+            "RUNTIME_TRAFFIC_HISTORY_POINTS must be between 180 and 10000, got 60"
+        );
     }
 }
@@ -1,5 +1,11 @@
-pub const MRMS_BUCKET_URL: &str = "https://noaa-mrms-pds.s3.amazonaws.com";
+use std::time::Duration;
+
+pub const DEFAULT_MRMS_BUCKET_URL: &str = "https://noaa-mrms-pds.s3.amazonaws.com";
 pub const MRMS_CONUS_PREFIX: &str = "CONUS";
+pub const MRMS_ALASKA_PREFIX: &str = "ALASKA";
+pub const MRMS_HAWAII_PREFIX: &str = "HAWAII";
+pub const MRMS_CARIB_PREFIX: &str = "CARIB";
+pub const MRMS_GUAM_PREFIX: &str = "GUAM";
 pub const MRMS_PRODUCT_PREFIX: &str = "MergedReflectivityQC";
 pub const MRMS_BASE_LEVEL_TAG: &str = "00.50";
 pub const MRMS_ZDR_PRODUCT_PREFIX: &str = "MergedZdr";
@@ -15,12 +21,26 @@ pub const MRMS_ECHO_TOP_18_PRODUCT: &str = "EchoTop_18_00.50";
 pub const MRMS_ECHO_TOP_30_PRODUCT: &str = "EchoTop_30_00.50";
 pub const MRMS_ECHO_TOP_50_PRODUCT: &str = "EchoTop_50_00.50";
 pub const MRMS_ECHO_TOP_60_PRODUCT: &str = "EchoTop_60_00.50";
-pub const LEVEL_TAGS: [&str; 33] = [
+pub const CONUS_LEVEL_TAGS: [&str; 33] = [
     "00.50", "00.75", "01.00", "01.25", "01.50", "01.75", "02.00", "02.25", "02.50", "02.75",
     "03.00", "03.50", "04.00", "04.50", "05.00", "05.50", "06.00", "06.50", "07.00", "07.50",
     "08.00", "08.50", "09.00", "10.00", "11.00", "12.00", "13.00", "14.00", "15.00", "16.00",
     "17.00", "18.00", "19.00",
 ];
+// Built-in heights, used until `discover_level_tags` replaces them with the
+// `MergedReflectivityQC_*` prefixes a domain's bucket listing actually has.
+// Discovery is retried from each domain's bootstrap loop until it succeeds.
+pub const ALASKA_LEVEL_TAGS: &[&str] = &CONUS_LEVEL_TAGS;
+pub const HAWAII_LEVEL_TAGS: &[&str] = &CONUS_LEVEL_TAGS;
+pub const CARIB_LEVEL_TAGS: &[&str] = &CONUS_LEVEL_TAGS;
+pub const GUAM_LEVEL_TAGS: &[&str] = &CONUS_LEVEL_TAGS;
+
+// Approximate domain grid extents as (south, north, west lon360, east lon360).
+pub const MRMS_CONUS_BOUNDS: (f64, f64, f64, f64) = (20.0, 55.0, 230.0, 300.0);
+pub const MRMS_ALASKA_BOUNDS: (f64, f64, f64, f64) = (50.0, 72.0, 180.0, 235.0);
+pub const MRMS_HAWAII_BOUNDS: (f64, f64, f64, f64) = (15.0, 26.0, 196.0, 209.0);
+pub const MRMS_CARIB_BOUNDS: (f64, f64, f64, f64) = (10.0, 25.0, 280.0, 300.0);
+pub const MRMS_GUAM_BOUNDS: (f64, f64, f64, f64) = (9.0, 19.0, 140.0, 150.0);
 
 pub const FEET_PER_KM: f64 = 3280.84;
 pub const FEET_PER_METER: f64 = 3.28084;
@@ -44,6 +64,10 @@ pub const PHASE_ZDR_SNOW_HIGH_CONF_MAX_DB: f32 = 0.2;
 pub const FREEZING_LEVEL_TRANSITION_FEET: f64 = 1500.0;
 pub const DUAL_POL_STALE_THRESHOLD_SECONDS: i64 = 300;
 pub const AUX_TIMESTAMP_LOOKBACK_DAYS: i64 = 1;
+// Optional `DOMAIN/product/YYYYMMDD/` path, then `MRMS_<product>_<timestamp>.grib2.gz`.
+pub const SQS_OBJECT_KEY_PATTERN: &str =
+    r"(?:([A-Z]+)/[A-Za-z0-9_.]+/\d{8}/)?MRMS_([A-Za-z0-9_.]+)_(\d{8}-\d{6})\.grib2\.gz";
+pub const AVAILABILITY_RETENTION_SECONDS: i64 = 6 * 3600;
 pub const THERMO_NEAR_FREEZING_FEET: f64 = 1500.0;
 pub const THERMO_STRONG_COLD_WET_BULB_C: f32 = -1.5;
 pub const THERMO_STRONG_WARM_WET_BULB_C: f32 = 2.0;
@@ -57,33 +81,104 @@ pub const MIXED_COMPETING_PROMOTION_MARGIN: f32 = 0.14;
 pub const MIXED_DUAL_SUPPORT_CONFIDENCE_MIN: f32 = 0.5;
 
 pub const DEFAULT_MIN_DBZ: f64 = 5.0;
+pub const DEFAULT_ISOSURFACE_DBZ: f64 = 30.0;
+// Bound marching-cubes memory and time; larger requests get 422.
+pub const MAX_ISOSURFACE_SAMPLES: usize = 1_000_000;
+pub const MAX_ISOSURFACE_TRIANGLES: usize = 1_000_000;
 pub const DEFAULT_MAX_RANGE_NM: f64 = 120.0;
 pub const MIN_ALLOWED_DBZ: f64 = 5.0;
 pub const MAX_ALLOWED_DBZ: f64 = 60.0;
 pub const MIN_ALLOWED_RANGE_NM: f64 = 30.0;
 pub const MAX_ALLOWED_RANGE_NM: f64 = 220.0;
+pub const MAX_ALLOWED_ALT_FEET: f64 = 65_000.0;
+pub const DEFAULT_CORRIDOR_HALF_WIDTH_NM: f64 = 10.0;
+pub const MIN_CORRIDOR_HALF_WIDTH_NM: f64 = 1.0;
+pub const MAX_CORRIDOR_HALF_WIDTH_NM: f64 = 50.0;
+pub const MAX_CORRIDOR_POINTS: usize = 64;
 
 pub const DEFAULT_TILE_SIZE: u16 = 64;
+pub const MIN_TILE_SIZE: u64 = 16;
+pub const MAX_TILE_SIZE: u64 = 1024;
 pub const DEFAULT_RETENTION_BYTES: u64 = 5 * 1024 * 1024 * 1024;
 pub const DEFAULT_REQUEST_TIMEOUT_SECONDS: u64 = 10;
 pub const DEFAULT_BOOTSTRAP_INTERVAL_SECONDS: u64 = 300;
 pub const DEFAULT_SQS_POLL_DELAY_SECONDS: u64 = 3;
 pub const DEFAULT_PENDING_RETRY_SECONDS: u64 = 30;
+// Kept below the pending retry delay so retries see newly published aux objects.
+pub const DEFAULT_LISTING_CACHE_TTL_SECONDS: u64 = 20;
+pub const LISTING_CACHE_MAX_ENTRIES: usize = 512;
+/// Day-prefix listings in flight while probing one scan's missing products.
+pub const PROBE_LISTING_CONCURRENCY: usize = 8;
+pub const MAX_LIST_PAGES: usize = 64;
 pub const MAX_PENDING_ATTEMPTS: u32 = 20;
+pub const DEAD_LETTER_MAX_ENTRIES: usize = 128;
+pub const DEFAULT_INGEST_DEADLINE_SECONDS: u64 = 240;
+pub const DEFAULT_UPGRADE_WINDOW_SECONDS: u64 = 900;
+pub const DEFAULT_READY_MAX_SCAN_AGE_SECONDS: u64 = 900;
+pub const DEFAULT_READY_MAX_SQS_SILENCE_SECONDS: u64 = 600;
+pub const DEFAULT_READY_MAX_CONSECUTIVE_FAILURES: u64 = 5;
+/// Below systemd's default 90 s stop timeout, leaving time to flush state.
+pub const DEFAULT_SHUTDOWN_GRACE_SECONDS: u64 = 60;
+pub const PENDING_QUEUE_FILE_VERSION: u16 = 1;
+pub const API_KEY_HEADER: &str = "x-api-key";
+pub const API_KEY_QUERY_PARAM: &str = "api_key";
+/// Rate limits are off unless configured, since every client behind an
+/// untrusted proxy would otherwise share one bucket.
+pub const DEFAULT_RATE_LIMIT_PER_MINUTE: u64 = 0;
+/// Buckets idle this long have refilled and are dropped.
+pub const RATE_LIMIT_IDLE_BUCKET_TTL: Duration = Duration::from_secs(600);
+/// Open traffic WebSockets allowed per client.
+pub const DEFAULT_TRAFFIC_STREAMS_PER_CLIENT: u64 = 4;
+/// Proxies in front of the runtime that append to `X-Forwarded-For`.
+pub const DEFAULT_FORWARDED_FOR_HOPS: u64 = 1;
+pub const DEFAULT_TRAFFIC_POLL_INTERVAL_SECONDS: u64 = 5;
+/// Polled tracks are kept this long after their last position, matching the
+/// longest trail `/v1/traffic/adsbx` serves.
+pub const TRAFFIC_TRACK_RETENTION: Duration = Duration::from_secs(30 * 60);
+/// Tracked aircraft without a newer position are left out of query results.
+pub const TRAFFIC_TRACK_STALE_AFTER: Duration = Duration::from_secs(60);
+/// A region answers queries while its last successful poll is at most this
+/// many intervals old; after that requests fetch upstream again.
+pub const TRAFFIC_POLL_FRESH_INTERVALS: u32 = 3;
+/// How often `/v1/traffic/stream` recomputes each subscription; matches the
+/// binCraft cache TTL so subscribers of one area share each fetch.
+pub const TRAFFIC_STREAM_UPDATE_INTERVAL: Duration = Duration::from_secs(2);
 pub const STORE_MIN_DBZ_TENTHS: i16 = 50;
 pub const MAX_BASE_KEYS_LOOKUP: usize = 120;
 pub const MAX_BASE_DAY_LOOKBACK: i64 = 1;
 
 pub const WIRE_MAGIC: [u8; 4] = *b"AVMR";
-#[allow(dead_code)]
 pub const WIRE_V2_VERSION: u16 = 2;
 pub const WIRE_V3_VERSION: u16 = 3;
+pub const WIRE_V4_VERSION: u16 = 4;
+pub const WIRE_SUPPORTED_VERSIONS: [u16; 3] = [WIRE_V2_VERSION, WIRE_V3_VERSION, WIRE_V4_VERSION];
+pub const WIRE_MEDIA_TYPE_PREFIX: &str = "application/vnd.approach-viz.mrms.v";
 pub const WIRE_HEADER_BYTES: usize = 64;
+// v4 appends the level-present mask to the v2/v3 header.
+pub const WIRE_V4_HEADER_BYTES: usize = 72;
 pub const WIRE_V2_RECORD_BYTES: usize = 20;
 pub const WIRE_V2_DBZ_QUANT_STEP_TENTHS: i16 = 50;
 pub const WIRE_V2_MAX_SPAN_LOW_DBZ: u16 = 48;
 pub const WIRE_V2_MAX_SPAN_HIGH_DBZ: u16 = 20;
 pub const WIRE_V2_MAX_VERTICAL_SPAN: u16 = 4;
+// (ring start NM, super-cell factor in grid cells); factors must divide WIRE_LOD_MAX_FACTOR.
+pub const WIRE_LOD_RINGS: [(f64, u16); 2] = [(60.0, 2), (120.0, 4)];
+pub const WIRE_LOD_MAX_FACTOR: u32 = 4;
+// v4 only; bytes 56..64 stay zero in v2/v3.
+pub const WIRE_LOD_RINGS_OFFSET: usize = 56;
+// v4 only: u64 bitmask, bit i set when level i is present in the scan.
+pub const WIRE_LEVEL_MASK_OFFSET: usize = 64;
+
+pub const ECHO_TOPS_WIRE_MAGIC: [u8; 4] = *b"AVET";
+pub const ECHO_TOPS_WIRE_V1_VERSION: u16 = 1;
+pub const ECHO_TOPS_WIRE_HEADER_BYTES: usize = 64;
+pub const ECHO_TOPS_WIRE_RECORD_BYTES: usize = 16;
+pub const ECHO_TOPS_WIRE_MEDIA_TYPE: &str = "application/vnd.approach-viz.echo-tops.v1";
+
+pub const MESH_MAGIC: [u8; 4] = *b"AVMM";
+pub const MESH_V1_VERSION: u16 = 1;
+pub const MESH_HEADER_BYTES: usize = 64;
+pub const MESH_VERTEX_BYTES: usize = 6;
 
 pub const SNAPSHOT_MAGIC: [u8; 4] = *b"AVSN";
-pub const SNAPSHOT_VERSION: u16 = 1;
+pub const SNAPSHOT_VERSION: u16 = 3;
@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::DEAD_LETTER_MAX_ENTRIES;
+
+/// Error from one failed ingest attempt.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttemptError {
+    pub attempt: u32,
+    pub failed_at: DateTime<Utc>,
+    pub error: String,
+}
+
+/// A timestamp that was dropped from the pending queue after
+/// `MAX_PENDING_ATTEMPTS` failed ingests.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetter {
+    pub timestamp: String,
+    pub upgrade: bool,
+    pub dead_at: DateTime<Utc>,
+    pub attempts: Vec<AttemptError>,
+}
+
+/// Most recent dead letters for one domain, oldest first. A timestamp appears
+/// at most once; dead-lettering it again replaces the earlier record.
+#[derive(Default)]
+pub struct DeadLetterLog {
+    entries: Mutex<VecDeque<DeadLetter>>,
+}
+
+impl DeadLetterLog {
+    pub fn push(&self, letter: DeadLetter) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.retain(|entry| entry.timestamp != letter.timestamp);
+        while entries.len() >= DEAD_LETTER_MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(letter);
+    }
+
+    /// Removes and returns the record for `timestamp`, e.g. before a manual retry.
+    pub fn take(&self, timestamp: &str) -> Option<DeadLetter> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let index = entries
+            .iter()
+            .position(|entry| entry.timestamp == timestamp)?;
+        entries.remove(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    pub fn snapshot(&self) -> Vec<DeadLetter> {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letter(timestamp: &str) -> DeadLetter {
+        DeadLetter {
+            timestamp: timestamp.to_string(),
+            upgrade: false,
+            dead_at: Utc::now(),
+            attempts: vec![AttemptError {
+                attempt: 1,
+                failed_at: Utc::now(),
+                error: "boom".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn log_is_bounded_and_deduplicated() {
+        let log = DeadLetterLog::default();
+        for index in 0..DEAD_LETTER_MAX_ENTRIES + 5 {
+            log.push(letter(&format!("20260212-{index:06}")));
+        }
+        log.push(letter("20260212-000010"));
+
+        let entries = log.snapshot();
+        assert_eq!(entries.len(), DEAD_LETTER_MAX_ENTRIES);
+        assert_eq!(entries[0].timestamp, "20260212-000005");
+        assert_eq!(entries.last().unwrap().timestamp, "20260212-000010");
+        assert_eq!(
+            entries
+                .iter()
+                .filter(|entry| entry.timestamp == "20260212-000010")
+                .count(),
+            1
+        );
+
+        assert!(log.take("20260212-000010").is_some());
+        assert!(log.take("20260212-000010").is_none());
+    }
+}
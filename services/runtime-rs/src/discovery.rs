@@ -1,16 +1,60 @@
+use std::sync::LazyLock;
+
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use regex::Regex;
-use reqwest::Client;
+use tracing::{info, warn};
 
-use crate::constants::{
-    MAX_BASE_DAY_LOOKBACK, MRMS_BASE_LEVEL_TAG, MRMS_BUCKET_URL, MRMS_CONUS_PREFIX,
-    MRMS_PRODUCT_PREFIX,
-};
-use crate::http_client::fetch_text;
+use crate::constants::{MAX_BASE_DAY_LOOKBACK, MRMS_BASE_LEVEL_TAG, MRMS_PRODUCT_PREFIX};
+use crate::domain::{level_tags_from_prefixes, MrmsDomain};
+use crate::source::IngestSource;
+use crate::types::DomainState;
+
+/// Replaces the domain state's built-in level tags with the reflectivity
+/// heights its bucket prefix lists, so scans never wait on levels the domain
+/// does not publish. The built-in list stays when the listing fails or lacks
+/// the base level; the bootstrap loop then tries again on its next pass.
+pub async fn discover_level_tags(source: &dyn IngestSource, domain_state: &DomainState) {
+    let domain = domain_state.domain;
+    let prefixes = match source
+        .list_subprefixes(&format!("{}/", domain.s3_prefix()))
+        .await
+    {
+        Ok(prefixes) => prefixes,
+        Err(error) => {
+            warn!(
+                "Failed listing {} products; keeping built-in level tags: {error:#}",
+                domain.id()
+            );
+            return;
+        }
+    };
+    let tags = level_tags_from_prefixes(domain, &prefixes);
+    if tags.first().map(String::as_str) != Some(MRMS_BASE_LEVEL_TAG) {
+        warn!(
+            "{} listing has no {MRMS_PRODUCT_PREFIX}_{MRMS_BASE_LEVEL_TAG}; keeping built-in level tags",
+            domain.id()
+        );
+        return;
+    }
+    if tags
+        .iter()
+        .map(String::as_str)
+        .ne(domain_state.level_tags().iter().map(String::as_str))
+    {
+        info!(
+            "Using {} level tags listed for {}: {}",
+            tags.len(),
+            domain.id(),
+            tags.join(",")
+        );
+    }
+    domain_state.set_discovered_level_tags(tags);
+}
 
 pub async fn find_recent_base_level_keys(
-    http: &Client,
+    source: &dyn IngestSource,
+    domain: MrmsDomain,
     now: DateTime<Utc>,
     limit: usize,
 ) -> Result<Vec<String>> {
@@ -19,9 +63,9 @@ pub async fn find_recent_base_level_keys(
     for day_offset in 0..=MAX_BASE_DAY_LOOKBACK {
         let date = now - chrono::Duration::days(day_offset);
         let day = date.format("%Y%m%d").to_string();
-        let prefix =
-            format!("{MRMS_CONUS_PREFIX}/{MRMS_PRODUCT_PREFIX}_{MRMS_BASE_LEVEL_TAG}/{day}/");
-        let keys = list_keys_for_prefix(http, &prefix).await?;
+        let keys = source
+            .list_keys(&base_level_day_prefix(domain, &day))
+            .await?;
         let mut filtered: Vec<String> = keys
             .into_iter()
             .filter(|key| is_mrms_grib2_key(key))
@@ -40,59 +84,42 @@ pub async fn find_recent_base_level_keys(
     Ok(candidates)
 }
 
-async fn list_keys_for_prefix(http: &Client, prefix: &str) -> Result<Vec<String>> {
-    let mut keys = Vec::new();
-    let mut continuation_token: Option<String> = None;
-
-    for _ in 0..4 {
-        let mut url = format!(
-            "{MRMS_BUCKET_URL}/?list-type=2&prefix={}&max-keys=1000",
-            urlencoding::encode(prefix)
-        );
-        if let Some(token) = continuation_token.as_ref() {
-            url.push_str("&continuation-token=");
-            url.push_str(&urlencoding::encode(token));
-        }
-
-        let xml = fetch_text(http, &url).await?;
-        keys.extend(parse_xml_tag_values(&xml, "Key"));
-
-        let is_truncated = parse_xml_tag_value(&xml, "IsTruncated")
-            .map(|value| value == "true")
-            .unwrap_or(false);
-        if !is_truncated {
-            break;
-        }
-
-        continuation_token = parse_xml_tag_value(&xml, "NextContinuationToken");
-        if continuation_token.is_none() {
-            break;
-        }
-    }
-
-    Ok(keys)
-}
-
-fn parse_xml_tag_values(xml: &str, tag_name: &str) -> Vec<String> {
-    let regex = Regex::new(&format!(r"<{0}>([^<]+)</{0}>", regex::escape(tag_name)))
-        .unwrap_or_else(|_| Regex::new(r"$^").unwrap());
-    regex
-        .captures_iter(xml)
-        .filter_map(|captures| captures.get(1).map(|value| value.as_str().to_string()))
-        .collect()
+/// Sorted base-level timestamps published on `day` (`YYYYMMDD`).
+pub async fn list_base_level_timestamps_for_day(
+    source: &dyn IngestSource,
+    domain: MrmsDomain,
+    day: &str,
+) -> Result<Vec<String>> {
+    let keys = source
+        .list_keys(&base_level_day_prefix(domain, day))
+        .await?;
+    let mut timestamps: Vec<String> = keys
+        .iter()
+        .filter(|key| is_mrms_grib2_key(key))
+        .filter_map(|key| extract_timestamp_from_key(key))
+        .collect();
+    timestamps.sort();
+    timestamps.dedup();
+    Ok(timestamps)
 }
 
-fn parse_xml_tag_value(xml: &str, tag_name: &str) -> Option<String> {
-    parse_xml_tag_values(xml, tag_name).into_iter().next()
+fn base_level_day_prefix(domain: MrmsDomain, day: &str) -> String {
+    format!(
+        "{}/{MRMS_PRODUCT_PREFIX}_{MRMS_BASE_LEVEL_TAG}/{day}/",
+        domain.s3_prefix()
+    )
 }
 
 fn is_mrms_grib2_key(key: &str) -> bool {
     key.ends_with(".grib2.gz")
 }
 
+/// Matches the `_YYYYMMDD-HHMMSS.grib2.gz` suffix of MRMS object keys.
+static KEY_TIMESTAMP_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"_(\d{8}-\d{6})\.grib2\.gz$").expect("valid key regex"));
+
 pub fn extract_timestamp_from_key(key: &str) -> Option<String> {
-    let regex = Regex::new(r"_(\d{8}-\d{6})\.grib2\.gz$").ok()?;
-    regex
+    KEY_TIMESTAMP_REGEX
         .captures(key)
         .and_then(|captures| captures.get(1).map(|value| value.as_str().to_string()))
 }
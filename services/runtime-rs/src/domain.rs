@@ -0,0 +1,172 @@
+use crate::constants::{
+    ALASKA_LEVEL_TAGS, CARIB_LEVEL_TAGS, CONUS_LEVEL_TAGS, GUAM_LEVEL_TAGS, HAWAII_LEVEL_TAGS,
+    MRMS_ALASKA_BOUNDS, MRMS_ALASKA_PREFIX, MRMS_CARIB_BOUNDS, MRMS_CARIB_PREFIX,
+    MRMS_CONUS_BOUNDS, MRMS_CONUS_PREFIX, MRMS_GUAM_BOUNDS, MRMS_GUAM_PREFIX, MRMS_HAWAII_BOUNDS,
+    MRMS_HAWAII_PREFIX, MRMS_PRODUCT_PREFIX,
+};
+use crate::utils::to_lon360;
+
+/// MRMS grid domain, matching the top-level prefixes in the NOAA bucket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MrmsDomain {
+    Conus,
+    Alaska,
+    Hawaii,
+    Caribbean,
+    Guam,
+}
+
+impl MrmsDomain {
+    /// Routing order: CONUS wins where its grid overlaps a neighboring domain.
+    pub const ALL: [MrmsDomain; 5] = [
+        MrmsDomain::Conus,
+        MrmsDomain::Alaska,
+        MrmsDomain::Hawaii,
+        MrmsDomain::Caribbean,
+        MrmsDomain::Guam,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            MrmsDomain::Conus => "conus",
+            MrmsDomain::Alaska => "alaska",
+            MrmsDomain::Hawaii => "hawaii",
+            MrmsDomain::Caribbean => "caribbean",
+            MrmsDomain::Guam => "guam",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "conus" => Some(MrmsDomain::Conus),
+            "alaska" | "ak" => Some(MrmsDomain::Alaska),
+            "hawaii" | "hi" => Some(MrmsDomain::Hawaii),
+            "caribbean" | "carib" | "pr" => Some(MrmsDomain::Caribbean),
+            "guam" | "gu" => Some(MrmsDomain::Guam),
+            _ => None,
+        }
+    }
+
+    pub fn from_s3_prefix(prefix: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|domain| domain.s3_prefix() == prefix)
+    }
+
+    pub fn s3_prefix(self) -> &'static str {
+        match self {
+            MrmsDomain::Conus => MRMS_CONUS_PREFIX,
+            MrmsDomain::Alaska => MRMS_ALASKA_PREFIX,
+            MrmsDomain::Hawaii => MRMS_HAWAII_PREFIX,
+            MrmsDomain::Caribbean => MRMS_CARIB_PREFIX,
+            MrmsDomain::Guam => MRMS_GUAM_PREFIX,
+        }
+    }
+
+    /// Built-in reflectivity heights, lowest first. `DomainState` starts from
+    /// these until the bucket listing is read.
+    pub fn builtin_level_tags(self) -> &'static [&'static str] {
+        match self {
+            MrmsDomain::Conus => &CONUS_LEVEL_TAGS,
+            MrmsDomain::Alaska => ALASKA_LEVEL_TAGS,
+            MrmsDomain::Hawaii => HAWAII_LEVEL_TAGS,
+            MrmsDomain::Caribbean => CARIB_LEVEL_TAGS,
+            MrmsDomain::Guam => GUAM_LEVEL_TAGS,
+        }
+    }
+
+    fn bounds(self) -> (f64, f64, f64, f64) {
+        match self {
+            MrmsDomain::Conus => MRMS_CONUS_BOUNDS,
+            MrmsDomain::Alaska => MRMS_ALASKA_BOUNDS,
+            MrmsDomain::Hawaii => MRMS_HAWAII_BOUNDS,
+            MrmsDomain::Caribbean => MRMS_CARIB_BOUNDS,
+            MrmsDomain::Guam => MRMS_GUAM_BOUNDS,
+        }
+    }
+
+    pub fn contains(self, lat: f64, lon: f64) -> bool {
+        let (south, north, west360, east360) = self.bounds();
+        let lon360 = to_lon360(lon);
+        (south..=north).contains(&lat) && (west360..=east360).contains(&lon360)
+    }
+}
+
+/// Reflectivity level tags among a domain's product sub-prefixes (e.g.
+/// `ALASKA/MergedReflectivityQC_01.25/`), sorted by height.
+pub fn level_tags_from_prefixes(domain: MrmsDomain, prefixes: &[String]) -> Vec<String> {
+    let product_prefix = format!("{}/{MRMS_PRODUCT_PREFIX}_", domain.s3_prefix());
+    let mut tags: Vec<(f64, String)> = prefixes
+        .iter()
+        .filter_map(|prefix| {
+            let tag = prefix.strip_prefix(&product_prefix)?.trim_end_matches('/');
+            let height_km = tag.parse::<f64>().ok().filter(|km| km.is_finite())?;
+            Some((height_km, tag.to_string()))
+        })
+        .collect();
+    tags.sort_by(|a, b| a.0.total_cmp(&b.0));
+    tags.into_iter().map(|(_, tag)| tag).collect()
+}
+
+/// Picks the first enabled domain covering the origin, falling back to the
+/// first enabled domain so out-of-coverage queries keep returning empty payloads.
+pub fn route_domain(enabled: &[MrmsDomain], lat: f64, lon: f64) -> Option<MrmsDomain> {
+    MrmsDomain::ALL
+        .into_iter()
+        .filter(|domain| enabled.contains(domain))
+        .find(|domain| domain.contains(lat, lon))
+        .or_else(|| enabled.first().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_domain_matches_oconus_airports() {
+        let enabled = MrmsDomain::ALL.to_vec();
+        assert_eq!(
+            route_domain(&enabled, 61.17, -149.99),
+            Some(MrmsDomain::Alaska)
+        );
+        assert_eq!(
+            route_domain(&enabled, 21.32, -157.92),
+            Some(MrmsDomain::Hawaii)
+        );
+        assert_eq!(
+            route_domain(&enabled, 18.44, -66.0),
+            Some(MrmsDomain::Caribbean)
+        );
+        assert_eq!(route_domain(&enabled, 13.48, 144.8), Some(MrmsDomain::Guam));
+        assert_eq!(
+            route_domain(&enabled, 40.64, -73.78),
+            Some(MrmsDomain::Conus)
+        );
+    }
+
+    #[test]
+    fn level_tags_come_from_reflectivity_prefixes() {
+        let prefixes = [
+            "ALASKA/MergedReflectivityQC_01.00/",
+            "ALASKA/MergedReflectivityQCComposite_00.50/",
+            "ALASKA/MergedReflectivityQC_00.50/",
+            "ALASKA/MergedReflectivityQC_10.00/",
+            "ALASKA/MergedZdr_00.50/",
+            "CONUS/MergedReflectivityQC_19.00/",
+        ]
+        .map(str::to_string);
+        assert_eq!(
+            level_tags_from_prefixes(MrmsDomain::Alaska, &prefixes),
+            vec!["00.50", "01.00", "10.00"]
+        );
+    }
+
+    #[test]
+    fn route_domain_falls_back_to_first_enabled() {
+        assert_eq!(
+            route_domain(&[MrmsDomain::Conus], 61.17, -149.99),
+            Some(MrmsDomain::Conus)
+        );
+        assert_eq!(route_domain(&[], 40.0, -75.0), None);
+    }
+}
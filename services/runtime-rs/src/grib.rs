@@ -3,16 +3,19 @@ use std::io::{Cursor, Read};
 use anyhow::{anyhow, bail, Context, Result};
 use flate2::read::GzDecoder;
 use grib::{Grib2SubmessageDecoder, GridDefinitionTemplateValues};
+use tracing::instrument;
 
 use crate::types::{GridDef, ParsedAuxField, ParsedReflectivityField};
 use crate::utils::to_lon360;
 
+#[instrument(skip_all, fields(zipped_bytes = zipped.len()))]
 pub fn parse_reflectivity_grib_gzipped(zipped: &[u8]) -> Result<ParsedReflectivityField> {
     let (grid, values) = parse_grib_gzipped_values(zipped)?;
     let dbz_tenths = values.into_iter().map(float_to_tenths).collect();
     Ok(ParsedReflectivityField { grid, dbz_tenths })
 }
 
+#[instrument(skip_all, fields(zipped_bytes = zipped.len()))]
 pub fn parse_aux_grib_gzipped(zipped: &[u8]) -> Result<ParsedAuxField> {
     let (grid, values) = parse_grib_gzipped_values(zipped)?;
     Ok(ParsedAuxField { grid, values })
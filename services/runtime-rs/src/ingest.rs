@@ -8,92 +8,126 @@ use aws_sdk_sqs::Client as SqsClient;
 use chrono::Utc;
 use futures::stream::{FuturesUnordered, StreamExt};
 use regex::Regex;
-use reqwest::Client;
 use serde_json::Value;
+use tokio::task::JoinHandle;
 use tokio::time::sleep;
-use tracing::{error, info, warn};
+use tracing::{error, info, info_span, instrument, warn, Span};
 
+use crate::availability::AvailabilityIndex;
 use crate::constants::{
     AUX_TIMESTAMP_LOOKBACK_DAYS, DUAL_POL_STALE_THRESHOLD_SECONDS, FEET_PER_KM, FEET_PER_METER,
-    FREEZING_LEVEL_TRANSITION_FEET, LEVEL_TAGS, MAX_BASE_DAY_LOOKBACK, MAX_BASE_KEYS_LOOKUP,
+    FREEZING_LEVEL_TRANSITION_FEET, MAX_BASE_DAY_LOOKBACK, MAX_BASE_KEYS_LOOKUP,
     MAX_PENDING_ATTEMPTS, MIXED_COMPETING_PROMOTION_GAP_MAX, MIXED_COMPETING_PROMOTION_MARGIN,
     MIXED_COMPETING_PROMOTION_MIN_SCORE, MIXED_COMPETING_RAIN_SNOW_DELTA_MAX,
     MIXED_COMPETING_RAIN_SNOW_MIN_SCORE, MIXED_DUAL_SUPPORT_CONFIDENCE_MIN, MIXED_SELECTION_MARGIN,
     MIXED_SELECTION_MARGIN_TRANSITION, MRMS_BASE_LEVEL_TAG, MRMS_BRIGHT_BAND_BOTTOM_PRODUCT,
-    MRMS_BRIGHT_BAND_TOP_PRODUCT, MRMS_BUCKET_URL, MRMS_CONUS_PREFIX, MRMS_ECHO_TOP_18_PRODUCT,
-    MRMS_ECHO_TOP_30_PRODUCT, MRMS_ECHO_TOP_50_PRODUCT, MRMS_ECHO_TOP_60_PRODUCT,
-    MRMS_MODEL_FREEZING_HEIGHT_PRODUCT, MRMS_MODEL_SURFACE_TEMP_PRODUCT,
-    MRMS_MODEL_WET_BULB_TEMP_PRODUCT, MRMS_PRECIP_FLAG_PRODUCT, MRMS_PRODUCT_PREFIX,
-    MRMS_RHOHV_PRODUCT_PREFIX, MRMS_RQI_PRODUCT, MRMS_ZDR_PRODUCT_PREFIX, PHASE_MIXED, PHASE_RAIN,
-    PHASE_RHOHV_HIGH_CONFIDENCE_MIN, PHASE_RHOHV_LOW_CONFIDENCE_MAX, PHASE_RHOHV_MAX_VALID,
-    PHASE_RHOHV_MIN_VALID, PHASE_SNOW, PHASE_ZDR_MAX_VALID_DB, PHASE_ZDR_MIN_VALID_DB,
-    PHASE_ZDR_RAIN_HIGH_CONF_MIN_DB, PHASE_ZDR_SNOW_HIGH_CONF_MAX_DB, STORE_MIN_DBZ_TENTHS,
+    MRMS_BRIGHT_BAND_TOP_PRODUCT, MRMS_ECHO_TOP_18_PRODUCT, MRMS_ECHO_TOP_30_PRODUCT,
+    MRMS_ECHO_TOP_50_PRODUCT, MRMS_ECHO_TOP_60_PRODUCT, MRMS_MODEL_FREEZING_HEIGHT_PRODUCT,
+    MRMS_MODEL_SURFACE_TEMP_PRODUCT, MRMS_MODEL_WET_BULB_TEMP_PRODUCT, MRMS_PRECIP_FLAG_PRODUCT,
+    MRMS_PRODUCT_PREFIX, MRMS_RHOHV_PRODUCT_PREFIX, MRMS_RQI_PRODUCT, MRMS_ZDR_PRODUCT_PREFIX,
+    PHASE_MIXED, PHASE_RAIN, PHASE_RHOHV_HIGH_CONFIDENCE_MIN, PHASE_RHOHV_LOW_CONFIDENCE_MAX,
+    PHASE_RHOHV_MAX_VALID, PHASE_RHOHV_MIN_VALID, PHASE_SNOW, PHASE_ZDR_MAX_VALID_DB,
+    PHASE_ZDR_MIN_VALID_DB, PHASE_ZDR_RAIN_HIGH_CONF_MIN_DB, PHASE_ZDR_SNOW_HIGH_CONF_MAX_DB,
+    PROBE_LISTING_CONCURRENCY, SQS_OBJECT_KEY_PATTERN, STORE_MIN_DBZ_TENTHS,
     THERMO_NEAR_FREEZING_FEET, THERMO_STRONG_COLD_WET_BULB_C, THERMO_STRONG_WARM_WET_BULB_C,
 };
-use crate::discovery::{extract_timestamp_from_key, find_recent_base_level_keys};
+use crate::dead_letter::{AttemptError, DeadLetter};
+use crate::discovery::{
+    discover_level_tags, extract_timestamp_from_key, find_recent_base_level_keys,
+};
+use crate::domain::MrmsDomain;
 use crate::grib::{parse_aux_grib_gzipped, parse_reflectivity_grib_gzipped};
-use crate::http_client::fetch_bytes;
+use crate::ingest_policy::{decide_ingest, IngestDecision, IngestPolicy};
+use crate::metrics::{IngestStage, METRICS};
+use crate::shutdown::ShutdownSignal;
+use crate::source::IngestSource;
 use crate::storage::persist_snapshot;
 use crate::types::{
-    AppState, EchoTopDebugMetadata, GridDef, LevelBounds, ParsedAuxField, ParsedReflectivityField,
-    PendingIngest, PhaseDebugMetadata, ScanSnapshot, StoredEchoTop, StoredVoxel,
+    AppState, DomainState, EchoTopDebugMetadata, GridDef, LevelBounds, ParsedAuxField,
+    ParsedReflectivityField, PendingIngest, PhaseDebugMetadata, ScanSnapshot, StoredEchoTop,
+    StoredVoxel,
 };
 use crate::utils::{parse_timestamp_utc, round_u16, to_lon360};
 
-pub async fn spawn_background_workers(state: AppState) -> Result<()> {
-    let worker_state = state.clone();
-    tokio::spawn(async move {
-        ingest_scheduler_loop(worker_state).await;
-    });
-
-    let bootstrap_state = state.clone();
-    tokio::spawn(async move {
-        bootstrap_loop(bootstrap_state).await;
-    });
-
-    if let Some(queue_url) = state.cfg.sqs_queue_url.clone() {
+/// Starts the per-domain scheduler and bootstrap loops and the SQS loop.
+/// Each returns once `shutdown` fires; schedulers first finish (or, after the
+/// grace period, requeue) their in-flight ingest.
+pub async fn spawn_background_workers(
+    state: AppState,
+    shutdown: ShutdownSignal,
+) -> Result<Vec<JoinHandle<()>>> {
+    let mut workers = Vec::new();
+    for domain_state in state.domains.values() {
+        let worker_state = state.clone();
+        let worker_domain = domain_state.clone();
+        let worker_shutdown = shutdown.clone();
+        workers.push(tokio::spawn(async move {
+            ingest_scheduler_loop(worker_state, worker_domain, worker_shutdown).await;
+        }));
+
+        let bootstrap_state = state.clone();
+        let bootstrap_domain = domain_state.clone();
+        let bootstrap_shutdown = shutdown.clone();
+        workers.push(tokio::spawn(async move {
+            bootstrap_loop(bootstrap_state, bootstrap_domain, bootstrap_shutdown).await;
+        }));
+    }
+
+    if state.cfg.source_dir.is_some() {
+        if state.cfg.sqs_queue_url.is_some() {
+            warn!("Ignoring SQS queue because RUNTIME_MRMS_SOURCE_DIR is set; notifications reference bucket objects.");
+        }
+    } else if let Some(queue_url) = state.cfg.sqs_queue_url.clone() {
         let sqs_state = state.clone();
-        tokio::spawn(async move {
-            if let Err(error) = sqs_loop(sqs_state, &queue_url).await {
+        workers.push(tokio::spawn(async move {
+            if let Err(error) = sqs_loop(sqs_state, &queue_url, shutdown).await {
                 error!("SQS loop exited: {error:#}");
             }
-        });
+        }));
     } else {
         warn!(
             "RUNTIME_MRMS_SQS_QUEUE_URL/MRMS_SQS_QUEUE_URL is not set; relying only on periodic S3 bootstrap polling."
         );
     }
 
-    Ok(())
+    Ok(workers)
 }
 
-async fn sqs_loop(state: AppState, queue_url: &str) -> Result<()> {
+async fn sqs_loop(state: AppState, queue_url: &str, mut shutdown: ShutdownSignal) -> Result<()> {
     info!("Starting SQS loop for {queue_url}");
     let shared_config = aws_config::defaults(BehaviorVersion::latest())
         .region(aws_config::Region::new(state.cfg.aws_region.clone()))
         .load()
         .await;
     let sqs_client = SqsClient::new(&shared_config);
-
-    let base_key_regex =
-        Regex::new(r#"MergedReflectivityQC_00\.50[^\s"']*_(\d{8}-\d{6})\.grib2\.gz"#)
-            .context("Failed to compile base key regex")?;
-
-    loop {
-        let receive_result = sqs_client
-            .receive_message()
-            .queue_url(queue_url)
-            .max_number_of_messages(10)
-            .wait_time_seconds(20)
-            .visibility_timeout(90)
-            .send()
-            .await;
+    state.health.mark_sqs_started();
+
+    let object_key_regex =
+        Regex::new(SQS_OBJECT_KEY_PATTERN).context("Failed to compile SQS object key regex")?;
+    let base_product = format!("{MRMS_PRODUCT_PREFIX}_{MRMS_BASE_LEVEL_TAG}");
+
+    while !shutdown.is_triggered() {
+        // Messages already received are still handled and deleted below;
+        // only the long poll is cut short.
+        let receive_result = tokio::select! {
+            result = sqs_client
+                .receive_message()
+                .queue_url(queue_url)
+                .max_number_of_messages(10)
+                .wait_time_seconds(20)
+                .visibility_timeout(90)
+                .send() => result,
+            () = shutdown.triggered() => break,
+        };
 
         let response = match receive_result {
             Ok(response) => response,
             Err(error) => {
                 warn!("SQS receive_message failed: {error}");
-                sleep(state.cfg.sqs_poll_delay).await;
+                tokio::select! {
+                    () = sleep(state.cfg.sqs_poll_delay) => {}
+                    () = shutdown.triggered() => {}
+                }
                 continue;
             }
         };
@@ -102,15 +136,29 @@ async fn sqs_loop(state: AppState, queue_url: &str) -> Result<()> {
         if messages.is_empty() {
             continue;
         }
+        METRICS.sqs_messages_received.inc_by(messages.len() as u64);
+        state.health.mark_sqs_message();
 
         for message in messages {
-            let mut extracted_timestamps = Vec::new();
+            let mut observed_objects = Vec::new();
             if let Some(body) = message.body() {
-                extracted_timestamps = extract_timestamps_from_sqs_body(body, &base_key_regex);
+                observed_objects = extract_objects_from_sqs_body(body, &object_key_regex);
             }
 
-            for timestamp in extracted_timestamps {
-                enqueue_timestamp(&state, &timestamp).await;
+            for (domain, product, timestamp) in observed_objects {
+                let Some(domain_state) = state.domain_state(domain) else {
+                    continue;
+                };
+                if !domain_state.availability.record(&product, &timestamp) {
+                    continue;
+                }
+                if product == base_product {
+                    enqueue_timestamp(domain_state, &timestamp).await;
+                } else if is_scan_product(&product)
+                    && domain_state.missing_scan_products(&timestamp).is_empty()
+                {
+                    mark_pending_due(domain_state, &timestamp).await;
+                }
             }
 
             if let Some(receipt_handle) = message.receipt_handle() {
@@ -122,88 +170,150 @@ async fn sqs_loop(state: AppState, queue_url: &str) -> Result<()> {
                     .await
                 {
                     warn!("Failed to delete SQS message: {error}");
+                } else {
+                    METRICS.sqs_messages_deleted.inc();
                 }
             }
         }
     }
+    info!("SQS loop stopped");
+    Ok(())
+}
+
+/// `(domain, product, timestamp)` of an MRMS object named in an SNS notification.
+type ObservedObject = (MrmsDomain, String, String);
+
+fn is_scan_product(product: &str) -> bool {
+    [
+        MRMS_PRODUCT_PREFIX,
+        MRMS_ZDR_PRODUCT_PREFIX,
+        MRMS_RHOHV_PRODUCT_PREFIX,
+    ]
+    .iter()
+    .any(|prefix| {
+        product
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('_'))
+    })
 }
 
-fn extract_timestamps_from_sqs_body(body: &str, base_key_regex: &Regex) -> Vec<String> {
+fn extract_objects_from_sqs_body(body: &str, object_key_regex: &Regex) -> Vec<ObservedObject> {
     let mut candidates = HashSet::new();
 
-    for captures in base_key_regex.captures_iter(body) {
-        if let Some(timestamp) = captures.get(1) {
-            candidates.insert(timestamp.as_str().to_string());
-        }
-    }
+    collect_object_key_matches(body, &mut candidates, object_key_regex);
 
     let parsed = serde_json::from_str::<Value>(body);
     if let Ok(value) = parsed {
-        collect_json_strings(&value, &mut candidates, base_key_regex);
+        collect_json_strings(&value, &mut candidates, object_key_regex);
         if let Some(message_value) = value.get("Message") {
             if let Some(message_str) = message_value.as_str() {
                 if let Ok(inner_json) = serde_json::from_str::<Value>(message_str) {
-                    collect_json_strings(&inner_json, &mut candidates, base_key_regex);
+                    collect_json_strings(&inner_json, &mut candidates, object_key_regex);
                 }
             }
         }
     }
 
-    let mut sorted: Vec<String> = candidates.into_iter().collect();
+    let mut sorted: Vec<ObservedObject> = candidates.into_iter().collect();
     sorted.sort();
     sorted
 }
 
-fn collect_json_strings(value: &Value, candidates: &mut HashSet<String>, base_key_regex: &Regex) {
+/// Keys without a recognizable domain prefix are treated as CONUS, matching
+/// the pre-domain behavior.
+fn collect_object_key_matches(
+    text: &str,
+    candidates: &mut HashSet<ObservedObject>,
+    object_key_regex: &Regex,
+) {
+    for captures in object_key_regex.captures_iter(text) {
+        let (Some(product), Some(timestamp)) = (captures.get(2), captures.get(3)) else {
+            continue;
+        };
+        let domain = match captures.get(1) {
+            Some(prefix) => match MrmsDomain::from_s3_prefix(prefix.as_str()) {
+                Some(domain) => domain,
+                None => continue,
+            },
+            None => MrmsDomain::Conus,
+        };
+        candidates.insert((
+            domain,
+            product.as_str().to_string(),
+            timestamp.as_str().to_string(),
+        ));
+    }
+}
+
+fn collect_json_strings(
+    value: &Value,
+    candidates: &mut HashSet<ObservedObject>,
+    object_key_regex: &Regex,
+) {
     match value {
         Value::String(text) => {
             let decoded = urlencoding::decode(text)
                 .map(|value| value.to_string())
                 .unwrap_or_else(|_| text.clone());
             for target in [text.as_str(), decoded.as_str()] {
-                for captures in base_key_regex.captures_iter(target) {
-                    if let Some(timestamp) = captures.get(1) {
-                        candidates.insert(timestamp.as_str().to_string());
-                    }
-                }
+                collect_object_key_matches(target, candidates, object_key_regex);
             }
         }
         Value::Array(items) => {
             for item in items {
-                collect_json_strings(item, candidates, base_key_regex);
+                collect_json_strings(item, candidates, object_key_regex);
             }
         }
         Value::Object(items) => {
             for (_key, item) in items {
-                collect_json_strings(item, candidates, base_key_regex);
+                collect_json_strings(item, candidates, object_key_regex);
             }
         }
         _ => {}
     }
 }
 
-async fn bootstrap_loop(state: AppState) {
-    loop {
-        if let Err(error) = enqueue_latest_from_s3(&state).await {
-            warn!("Periodic S3 bootstrap enqueue failed: {error:#}");
+async fn bootstrap_loop(
+    state: AppState,
+    domain_state: Arc<DomainState>,
+    mut shutdown: ShutdownSignal,
+) {
+    while !shutdown.is_triggered() {
+        if !domain_state.level_tags_discovered() {
+            discover_level_tags(state.source.as_ref(), &domain_state).await;
+        }
+        if let Err(error) = enqueue_latest_from_s3(&state, &domain_state).await {
+            warn!(
+                "Periodic S3 bootstrap enqueue failed for {}: {error:#}",
+                domain_state.domain.id()
+            );
+        }
+        tokio::select! {
+            () = sleep(state.cfg.bootstrap_interval) => {}
+            () = shutdown.triggered() => {}
         }
-        sleep(state.cfg.bootstrap_interval).await;
     }
 }
 
-pub async fn enqueue_latest_from_s3(state: &AppState) -> Result<()> {
+pub async fn enqueue_latest_from_s3(state: &AppState, domain_state: &DomainState) -> Result<()> {
     let now = Utc::now();
-    let base_keys = find_recent_base_level_keys(&state.http, now, MAX_BASE_KEYS_LOOKUP).await?;
+    let base_keys = find_recent_base_level_keys(
+        state.source.as_ref(),
+        domain_state.domain,
+        now,
+        MAX_BASE_KEYS_LOOKUP,
+    )
+    .await?;
     for key in base_keys {
         if let Some(timestamp) = extract_timestamp_from_key(&key) {
-            enqueue_timestamp(state, &timestamp).await;
+            enqueue_timestamp(domain_state, &timestamp).await;
         }
     }
     Ok(())
 }
 
-async fn enqueue_timestamp(state: &AppState, timestamp: &str) {
-    let latest_timestamp = state
+async fn enqueue_timestamp(domain_state: &DomainState, timestamp: &str) {
+    let latest_timestamp = domain_state
         .latest
         .read()
         .await
@@ -216,29 +326,38 @@ async fn enqueue_timestamp(state: &AppState, timestamp: &str) {
     }
 
     {
-        let recent = state.recent_timestamps.lock().await;
+        let recent = domain_state.recent_timestamps.lock().await;
         if recent.contains(timestamp) {
             return;
         }
     }
 
-    let mut pending = state.pending.lock().await;
+    let mut pending = domain_state.pending.lock().await;
     pending
         .entry(timestamp.to_string())
         .and_modify(|entry| {
             entry.next_attempt_at = Instant::now();
         })
-        .or_insert(PendingIngest {
-            attempts: 0,
-            next_attempt_at: Instant::now(),
-        });
+        .or_insert_with(|| PendingIngest::new(false, Instant::now()));
 }
 
-async fn ingest_scheduler_loop(state: AppState) {
-    loop {
+/// Makes a pending timestamp due now, e.g. once its scan products have all arrived.
+async fn mark_pending_due(domain_state: &DomainState, timestamp: &str) {
+    if let Some(entry) = domain_state.pending.lock().await.get_mut(timestamp) {
+        entry.next_attempt_at = Instant::now();
+    }
+}
+
+async fn ingest_scheduler_loop(
+    state: AppState,
+    domain_state: Arc<DomainState>,
+    mut shutdown: ShutdownSignal,
+) {
+    let domain = domain_state.domain;
+    while !shutdown.is_triggered() {
         let candidate = {
             let now = Instant::now();
-            let mut pending = state.pending.lock().await;
+            let mut pending = domain_state.pending.lock().await;
 
             let mut selected: Option<(String, Instant)> = None;
             for (timestamp, entry) in pending.iter() {
@@ -260,26 +379,89 @@ async fn ingest_scheduler_loop(state: AppState) {
         };
 
         let Some((timestamp, pending_entry)) = candidate else {
-            sleep(Duration::from_secs(2)).await;
+            tokio::select! {
+                () = sleep(Duration::from_secs(2)) => {}
+                () = shutdown.triggered() => {}
+            }
             continue;
         };
 
-        match ingest_timestamp(&state, &timestamp).await {
+        let missing_scan_products =
+            probe_missing_scan_products(&state, &domain_state, &timestamp).await;
+        let decision = decide_ingest(
+            missing_scan_products,
+            pending_entry.first_seen_at.elapsed(),
+            pending_entry.upgrade,
+            state.cfg.ingest_deadline,
+            state.cfg.upgrade_window,
+        );
+        let policy = match decision {
+            IngestDecision::Ingest(policy) => policy,
+            IngestDecision::Wait => {
+                // Re-check after the retry delay, but never sleep past the deadline.
+                let deadline_at = pending_entry.first_seen_at
+                    + if pending_entry.upgrade {
+                        state.cfg.upgrade_window
+                    } else {
+                        state.cfg.ingest_deadline
+                    };
+                let next_attempt_at =
+                    (Instant::now() + state.cfg.pending_retry_delay).min(deadline_at);
+                domain_state.pending.lock().await.insert(
+                    timestamp,
+                    PendingIngest {
+                        next_attempt_at,
+                        ..pending_entry
+                    },
+                );
+                continue;
+            }
+            IngestDecision::Drop => {
+                info!(
+                    "Giving up on upgrading MRMS {} scan {timestamp}; {missing_scan_products} level products still missing",
+                    domain.id()
+                );
+                continue;
+            }
+        };
+
+        let result = tokio::select! {
+            result = ingest_timestamp(&state, domain, &timestamp, policy, missing_scan_products) => result,
+            () = shutdown.grace_elapsed() => {
+                warn!(
+                    "Abandoning in-flight MRMS {} ingest {timestamp} at shutdown; it stays queued",
+                    domain.id()
+                );
+                domain_state
+                    .pending
+                    .lock()
+                    .await
+                    .insert(timestamp.clone(), pending_entry);
+                return;
+            }
+        };
+        METRICS.record_ingest(domain, result.is_ok());
+        domain_state.record_ingest_result(result.is_ok());
+        match result {
             Ok(scan) => {
                 info!(
-                    "Ingested MRMS scan {} with {} stored voxels (phase_mode={}, phase_detail={})",
+                    "Ingested MRMS {} scan {} with {} stored voxels (policy={}, missing_products={}, missing_levels={}, phase_mode={}, phase_detail={})",
+                    domain.id(),
                     scan.timestamp,
                     scan.voxels.len(),
+                    scan.phase_debug.ingest_policy,
+                    scan.phase_debug.missing_scan_products,
+                    scan.missing_levels.len(),
                     scan.phase_debug.mode,
                     scan.phase_debug.detail,
                 );
 
-                if let Err(error) = persist_snapshot(&state.cfg, scan.clone()).await {
+                if let Err(error) = persist_snapshot(&state.cfg, domain, scan.clone()).await {
                     error!("Failed to persist scan {}: {error:#}", scan.timestamp);
                 }
 
                 {
-                    let mut latest = state.latest.write().await;
+                    let mut latest = domain_state.latest.write().await;
                     let should_replace = match latest.as_ref() {
                         Some(current) => scan.timestamp >= current.timestamp,
                         None => true,
@@ -290,7 +472,7 @@ async fn ingest_scheduler_loop(state: AppState) {
                 }
 
                 {
-                    let mut recent = state.recent_timestamps.lock().await;
+                    let mut recent = domain_state.recent_timestamps.lock().await;
                     recent.insert(scan.timestamp.clone());
                     if recent.len() > 512 {
                         if let Some(first) = recent.iter().next().cloned() {
@@ -300,70 +482,194 @@ async fn ingest_scheduler_loop(state: AppState) {
                 }
 
                 {
-                    let mut pending = state.pending.lock().await;
-                    pending.retain(|timestamp, _| timestamp > &scan.timestamp);
+                    let mut pending = domain_state.pending.lock().await;
+                    pending.retain(|timestamp, entry| entry.upgrade || timestamp > &scan.timestamp);
+                    let partial = !scan.missing_levels.is_empty();
+                    if policy == IngestPolicy::Deadline
+                        || (policy == IngestPolicy::Complete && partial)
+                    {
+                        pending.insert(
+                            scan.timestamp.clone(),
+                            PendingIngest::new(
+                                true,
+                                Instant::now() + state.cfg.pending_retry_delay,
+                            ),
+                        );
+                    }
                 }
             }
             Err(error) => {
+                let attempt = pending_entry.attempts + 1;
                 warn!(
-                    "Ingest attempt {} failed (attempt {}): {error:#}",
+                    "Ingest attempt {} {} failed (attempt {}): {error:#}",
+                    domain.id(),
                     timestamp,
-                    pending_entry.attempts + 1
+                    attempt
                 );
 
-                if pending_entry.attempts + 1 < MAX_PENDING_ATTEMPTS {
-                    let mut pending = state.pending.lock().await;
+                let mut errors = pending_entry.errors;
+                errors.push(AttemptError {
+                    attempt,
+                    failed_at: Utc::now(),
+                    error: format!("{error:#}"),
+                });
+
+                if attempt < MAX_PENDING_ATTEMPTS {
+                    let mut pending = domain_state.pending.lock().await;
                     pending.insert(
                         timestamp,
                         PendingIngest {
-                            attempts: pending_entry.attempts + 1,
+                            attempts: attempt,
                             next_attempt_at: Instant::now() + state.cfg.pending_retry_delay,
+                            errors,
+                            ..pending_entry
                         },
                     );
+                } else {
+                    error!(
+                        "Dead-lettering MRMS {} {timestamp} after {attempt} failed attempts",
+                        domain.id()
+                    );
+                    domain_state.dead_letters.push(DeadLetter {
+                        timestamp,
+                        upgrade: pending_entry.upgrade,
+                        dead_at: Utc::now(),
+                        attempts: errors,
+                    });
                 }
             }
         }
     }
 }
 
-async fn ingest_timestamp(state: &AppState, timestamp: &str) -> Result<Arc<ScanSnapshot>> {
+/// Counts reflectivity and dual-pol level products still missing for
+/// `timestamp`. The availability index answers first. Listings only confirm
+/// the rest while the index may be cold: without SQS, or for scans that began
+/// publishing before SQS started. Each missing product's day prefix is listed
+/// once, a few at a time, and every timestamp it holds at or after `timestamp`
+/// is recorded, so later pending scans are answered by the index.
+pub async fn probe_missing_scan_products(
+    state: &AppState,
+    domain_state: &DomainState,
+    timestamp: &str,
+) -> usize {
+    let domain = domain_state.domain;
+    let availability = &domain_state.availability;
+    let missing = domain_state.missing_scan_products(timestamp);
+    let index_is_warm = parse_timestamp_utc(timestamp)
+        .is_some_and(|scan_time| state.health.sqs_covers(scan_time.timestamp_millis()));
+    if missing.is_empty() || index_is_warm {
+        return missing.len();
+    }
+    let Some(date_part) = timestamp.split('-').next() else {
+        return missing.len();
+    };
+
+    let listings = futures::stream::iter(missing)
+        .map(|product| {
+            let prefix = format!("{}/{product}/{date_part}/", domain.s3_prefix());
+            let source = state.source.clone();
+            async move {
+                let listing = source.list_keys(&prefix).await;
+                (product, listing)
+            }
+        })
+        .buffer_unordered(PROBE_LISTING_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    for (product, listing) in listings {
+        match listing {
+            Ok(keys) => {
+                for listed in keys
+                    .iter()
+                    .filter_map(|key| extract_timestamp_from_key(key))
+                {
+                    if listed.as_str() >= timestamp {
+                        availability.record(&product, &listed);
+                    }
+                }
+            }
+            Err(error) => warn!("Failed listing {product} while probing {timestamp}: {error:#}"),
+        }
+    }
+    domain_state.missing_scan_products(timestamp).len()
+}
+
+#[instrument(skip(state, domain, policy), fields(domain = domain.id(), policy = policy.as_str()))]
+pub async fn ingest_timestamp(
+    state: &AppState,
+    domain: MrmsDomain,
+    timestamp: &str,
+    policy: IngestPolicy,
+    missing_scan_products: usize,
+) -> Result<Arc<ScanSnapshot>> {
+    let domain_state = state
+        .domain_state(domain)
+        .ok_or_else(|| anyhow!("MRMS domain {} is not enabled", domain.id()))?;
+    let level_tags = domain_state.level_tags();
+    let availability = &domain_state.availability;
     let date_part = timestamp
         .split('-')
         .next()
         .ok_or_else(|| anyhow!("Invalid timestamp format: {timestamp}"))?;
 
     let mut futures = FuturesUnordered::new();
-    for (level_idx, level_tag) in LEVEL_TAGS.iter().enumerate() {
-        let http = state.http.clone();
+    for (level_idx, level_tag) in level_tags.iter().enumerate() {
+        let source = state.source.as_ref();
         let level_tag = level_tag.to_string();
         let timestamp = timestamp.to_string();
         let date_part = date_part.to_string();
         futures.push(async move {
-            let reflectivity_key =
-                build_level_key(MRMS_PRODUCT_PREFIX, &level_tag, &date_part, &timestamp);
-            let reflectivity_zipped =
-                fetch_bytes(&http, &format!("{MRMS_BUCKET_URL}/{reflectivity_key}")).await?;
-            let reflectivity = tokio::task::spawn_blocking(move || {
-                parse_reflectivity_grib_gzipped(&reflectivity_zipped)
-            })
-            .await
-            .context("Join error while parsing level GRIB")??;
-            Ok::<_, anyhow::Error>((level_idx, level_tag, reflectivity))
+            let reflectivity_key = build_level_key(
+                domain,
+                MRMS_PRODUCT_PREFIX,
+                &level_tag,
+                &date_part,
+                &timestamp,
+            );
+            let reflectivity = async {
+                let fetch_started = Instant::now();
+                let reflectivity_zipped = source.fetch_object(&reflectivity_key).await?;
+                METRICS.observe_stage(domain, IngestStage::Fetch, fetch_started);
+                let decode_started = Instant::now();
+                let span = Span::current();
+                let parsed = tokio::task::spawn_blocking(move || {
+                    span.in_scope(|| parse_reflectivity_grib_gzipped(&reflectivity_zipped))
+                })
+                .await
+                .context("Join error while parsing level GRIB")?;
+                METRICS.observe_stage(domain, IngestStage::GribDecode, decode_started);
+                parsed
+            }
+            .await;
+            (level_idx, level_tag, reflectivity)
         });
     }
 
     let mut parsed_levels: Vec<Option<(String, ParsedReflectivityField)>> =
-        vec![None; LEVEL_TAGS.len()];
-    while let Some(result) = futures.next().await {
-        let (level_idx, level_tag, reflectivity) = result?;
-        parsed_levels[level_idx] = Some((level_tag, reflectivity));
+        vec![None; level_tags.len()];
+    while let Some((level_idx, level_tag, reflectivity)) = futures.next().await {
+        match reflectivity {
+            Ok(reflectivity) => parsed_levels[level_idx] = Some((level_tag, reflectivity)),
+            // The base level defines the grid, so without it there is no scan.
+            Err(error) if level_idx == 0 => {
+                return Err(error.context(format!("Base level {level_tag} unavailable")));
+            }
+            Err(error) => warn!(
+                "MRMS {} level {level_tag} unavailable at {timestamp}: {error:#}; ingesting partial scan",
+                domain.id()
+            ),
+        }
     }
 
     let mut levels = Vec::with_capacity(parsed_levels.len());
+    let mut missing_levels = Vec::new();
     for (idx, item) in parsed_levels.into_iter().enumerate() {
-        let (level_tag, reflectivity) =
-            item.ok_or_else(|| anyhow!("Missing parsed level {}", LEVEL_TAGS[idx]))?;
-        levels.push((idx as u8, level_tag, reflectivity));
+        match item {
+            Some((level_tag, reflectivity)) => levels.push((idx as u8, level_tag, reflectivity)),
+            None => missing_levels.push(idx as u8),
+        }
     }
 
     levels.sort_by_key(|(idx, _, _)| *idx);
@@ -379,20 +685,34 @@ async fn ingest_timestamp(state: &AppState, timestamp: &str) -> Result<Arc<ScanS
         }
     }
 
-    let mut zdr_bundle =
-        fetch_dual_pol_bundle(&state.http, MRMS_ZDR_PRODUCT_PREFIX, timestamp).await;
-    let mut rhohv_bundle =
-        fetch_dual_pol_bundle(&state.http, MRMS_RHOHV_PRODUCT_PREFIX, timestamp).await;
+    let mut zdr_bundle = fetch_dual_pol_bundle(
+        state.source.as_ref(),
+        availability,
+        domain,
+        &level_tags,
+        MRMS_ZDR_PRODUCT_PREFIX,
+        timestamp,
+    )
+    .await;
+    let mut rhohv_bundle = fetch_dual_pol_bundle(
+        state.source.as_ref(),
+        availability,
+        domain,
+        &level_tags,
+        MRMS_RHOHV_PRODUCT_PREFIX,
+        timestamp,
+    )
+    .await;
 
-    if zdr_bundle.fields_by_level.len() != LEVEL_TAGS.len() {
+    if zdr_bundle.fields_by_level.len() != level_tags.len() {
         zdr_bundle
             .fields_by_level
-            .resize_with(LEVEL_TAGS.len(), || None);
+            .resize_with(level_tags.len(), || None);
     }
-    if rhohv_bundle.fields_by_level.len() != LEVEL_TAGS.len() {
+    if rhohv_bundle.fields_by_level.len() != level_tags.len() {
         rhohv_bundle
             .fields_by_level
-            .resize_with(LEVEL_TAGS.len(), || None);
+            .resize_with(level_tags.len(), || None);
     }
 
     let dual_pol_stale = zdr_bundle
@@ -401,14 +721,16 @@ async fn ingest_timestamp(state: &AppState, timestamp: &str) -> Result<Arc<ScanS
         || rhohv_bundle
             .age_seconds
             .is_some_and(|age| age > DUAL_POL_STALE_THRESHOLD_SECONDS);
-    let dual_pol_incomplete = zdr_bundle.available_level_count() < LEVEL_TAGS.len()
-        || rhohv_bundle.available_level_count() < LEVEL_TAGS.len();
+    let dual_pol_incomplete = zdr_bundle.available_level_count() < level_tags.len()
+        || rhohv_bundle.available_level_count() < level_tags.len();
     let use_aux_fallback = dual_pol_stale || dual_pol_incomplete;
 
-    let thermo_aux_bundle = fetch_thermo_aux_bundle(&state.http, timestamp).await;
-    let echo_top_bundle = fetch_echo_top_bundle(&state.http, timestamp).await;
+    let thermo_aux_bundle =
+        fetch_thermo_aux_bundle(state.source.as_ref(), availability, domain, timestamp).await;
+    let echo_top_bundle =
+        fetch_echo_top_bundle(state.source.as_ref(), availability, domain, timestamp).await;
 
-    let level_km: Vec<f64> = LEVEL_TAGS
+    let level_km: Vec<f64> = level_tags
         .iter()
         .map(|tag| tag.parse::<f64>().unwrap_or(0.0))
         .collect();
@@ -520,9 +842,9 @@ async fn ingest_timestamp(state: &AppState, timestamp: &str) -> Result<Arc<ScanS
         }
     }
 
-    let tile_size = state.cfg.tile_size.max(16);
-    let tile_cols = ((base_grid.nx + tile_size as u32 - 1) / tile_size as u32) as u16;
-    let tile_rows = ((base_grid.ny + tile_size as u32 - 1) / tile_size as u32) as u16;
+    let tile_size = state.cfg.tile_size;
+    let tile_cols = base_grid.nx.div_ceil(tile_size as u32) as u16;
+    let tile_rows = base_grid.ny.div_ceil(tile_size as u32) as u16;
     let tile_count = tile_cols as usize * tile_rows as usize;
 
     let mut buckets: Vec<Vec<StoredVoxel>> = (0..tile_count).map(|_| Vec::new()).collect();
@@ -569,6 +891,8 @@ async fn ingest_timestamp(state: &AppState, timestamp: &str) -> Result<Arc<ScanS
         || (bright_band_top_field.is_some() && bright_band_bottom_field.is_some())
         || rqi_field.is_some();
 
+    let phase_started = Instant::now();
+    let phase_span = info_span!("resolve_phase", levels = levels.len()).entered();
     let mut dual_missing_voxel_count: u64 = 0;
     let mut thermo_signal_voxel_count: u64 = 0;
     let mut thermo_no_signal_voxel_count: u64 = 0;
@@ -602,18 +926,16 @@ async fn ingest_timestamp(state: &AppState, timestamp: &str) -> Result<Arc<ScanS
             timestamp,
         );
 
-        for row in 0..parsed.grid.ny as usize {
-            let lat_deg = row_lats[row];
+        for (row, &lat_deg) in row_lats.iter().enumerate() {
             let row_offset = row * parsed.grid.nx as usize;
 
-            for col in 0..parsed.grid.nx as usize {
+            for (col, &lon_deg360) in col_lons360.iter().enumerate() {
                 let value_idx = row_offset + col;
                 let dbz_tenths = parsed.dbz_tenths[value_idx];
                 if dbz_tenths < STORE_MIN_DBZ_TENTHS {
                     continue;
                 }
 
-                let lon_deg360 = col_lons360[col];
                 let dual_evidence = resolve_dual_pol_evidence(
                     zdr_values.and_then(|values| values.get(value_idx).copied()),
                     rhohv_values.and_then(|values| values.get(value_idx).copied()),
@@ -731,9 +1053,9 @@ async fn ingest_timestamp(state: &AppState, timestamp: &str) -> Result<Arc<ScanS
         bool_label(use_aux_fallback),
         bool_label(aux_context_available),
         zdr_bundle.available_level_count(),
-        LEVEL_TAGS.len(),
+        level_tags.len(),
         rhohv_bundle.available_level_count(),
-        LEVEL_TAGS.len(),
+        level_tags.len(),
         format_optional_i64(zdr_bundle.age_seconds),
         format_optional_i64(rhohv_bundle.age_seconds),
         bool_label(precip_field.is_some()),
@@ -752,6 +1074,28 @@ async fn ingest_timestamp(state: &AppState, timestamp: &str) -> Result<Arc<ScanS
         mixed_edge_promoted_voxel_count,
         precip_snow_forced_voxel_count,
     );
+    METRICS.set_phase_voxels(
+        domain,
+        &[
+            ("thermo_signal_voxels", thermo_signal_voxel_count),
+            ("thermo_no_signal_voxels", thermo_no_signal_voxel_count),
+            ("dual_missing_voxels", dual_missing_voxel_count),
+            ("dual_adjusted_voxels", dual_adjusted_voxel_count),
+            ("dual_suppressed_voxels", dual_suppressed_voxel_count),
+            (
+                "stale_dual_adjusted_voxels",
+                stale_dual_adjusted_voxel_count,
+            ),
+            ("mixed_suppressed_voxels", mixed_suppressed_voxel_count),
+            (
+                "mixed_edge_promoted_voxels",
+                mixed_edge_promoted_voxel_count,
+            ),
+            ("precip_snow_forced_voxels", precip_snow_forced_voxel_count),
+        ],
+    );
+    METRICS.observe_stage(domain, IngestStage::Phase, phase_started);
+    drop(phase_span);
 
     Ok(Arc::new(ScanSnapshot {
         timestamp: timestamp.to_string(),
@@ -787,6 +1131,7 @@ async fn ingest_timestamp(state: &AppState, timestamp: &str) -> Result<Arc<ScanS
             max_top50_feet,
             max_top60_feet,
         },
+        missing_levels,
         phase_debug: PhaseDebugMetadata {
             mode: mode.to_string(),
             detail,
@@ -802,6 +1147,8 @@ async fn ingest_timestamp(state: &AppState, timestamp: &str) -> Result<Arc<ScanS
                 .map(|(ts, _field)| ts.clone()),
             zdr_age_seconds: zdr_bundle.age_seconds,
             rhohv_age_seconds: rhohv_bundle.age_seconds,
+            ingest_policy: policy.as_str().to_string(),
+            missing_scan_products: missing_scan_products as u32,
         },
     }))
 }
@@ -897,7 +1244,10 @@ impl DualPolBundle {
 }
 
 async fn fetch_dual_pol_bundle(
-    http: &Client,
+    source: &dyn IngestSource,
+    availability: &AvailabilityIndex,
+    domain: MrmsDomain,
+    level_tags: &[String],
     product_prefix: &'static str,
     target_timestamp: &str,
 ) -> DualPolBundle {
@@ -908,14 +1258,15 @@ async fn fetch_dual_pol_bundle(
             return DualPolBundle {
                 selected_timestamp: None,
                 age_seconds: None,
-                fields_by_level: vec![None; LEVEL_TAGS.len()],
+                fields_by_level: vec![None; level_tags.len()],
             };
         }
     };
 
     let mut selected_timestamp = Some(target_timestamp.to_string());
     let mut base_level_field: Option<ParsedAuxField> = match fetch_level_aux_field_at_timestamp(
-        http,
+        source,
+        domain,
         product_prefix,
         MRMS_BASE_LEVEL_TAG,
         target_date_part,
@@ -934,7 +1285,9 @@ async fn fetch_dual_pol_bundle(
 
     if base_level_field.is_none() {
         selected_timestamp = find_latest_level_timestamp_at_or_before(
-            http,
+            source,
+            availability,
+            domain,
             product_prefix,
             MRMS_BASE_LEVEL_TAG,
             target_timestamp,
@@ -950,12 +1303,13 @@ async fn fetch_dual_pol_bundle(
                     return DualPolBundle {
                         selected_timestamp: None,
                         age_seconds: None,
-                        fields_by_level: vec![None; LEVEL_TAGS.len()],
+                        fields_by_level: vec![None; level_tags.len()],
                     };
                 }
             };
             base_level_field = fetch_level_aux_field_at_timestamp(
-                http,
+                source,
+                domain,
                 product_prefix,
                 MRMS_BASE_LEVEL_TAG,
                 date_part,
@@ -976,7 +1330,7 @@ async fn fetch_dual_pol_bundle(
         return DualPolBundle {
             selected_timestamp: None,
             age_seconds: None,
-            fields_by_level: vec![None; LEVEL_TAGS.len()],
+            fields_by_level: vec![None; level_tags.len()],
         };
     };
 
@@ -989,21 +1343,20 @@ async fn fetch_dual_pol_bundle(
             return DualPolBundle {
                 selected_timestamp: None,
                 age_seconds: None,
-                fields_by_level: vec![None; LEVEL_TAGS.len()],
+                fields_by_level: vec![None; level_tags.len()],
             };
         }
     };
 
-    let mut fields_by_level = vec![None; LEVEL_TAGS.len()];
+    let mut fields_by_level = vec![None; level_tags.len()];
     let mut futures = FuturesUnordered::new();
 
-    for (level_idx, level_tag) in LEVEL_TAGS.iter().enumerate() {
+    for (level_idx, level_tag) in level_tags.iter().enumerate() {
         if level_idx == 0 {
             fields_by_level[level_idx] = base_level_field.clone();
             continue;
         }
 
-        let http = http.clone();
         let level_tag = level_tag.to_string();
         let product_prefix = product_prefix.to_string();
         let date_part = selected_date_part.clone();
@@ -1011,7 +1364,8 @@ async fn fetch_dual_pol_bundle(
 
         futures.push(async move {
             let field = fetch_level_aux_field_at_timestamp(
-                &http,
+                source,
+                domain,
                 &product_prefix,
                 &level_tag,
                 &date_part,
@@ -1040,38 +1394,68 @@ async fn fetch_dual_pol_bundle(
     }
 }
 
-async fn fetch_thermo_aux_bundle(http: &Client, target_timestamp: &str) -> ThermoAuxBundle {
-    let precip_flag =
-        fetch_latest_aux_field_at_or_before(http, MRMS_PRECIP_FLAG_PRODUCT, target_timestamp).await;
+async fn fetch_thermo_aux_bundle(
+    source: &dyn IngestSource,
+    availability: &AvailabilityIndex,
+    domain: MrmsDomain,
+    target_timestamp: &str,
+) -> ThermoAuxBundle {
+    let precip_flag = fetch_latest_aux_field_at_or_before(
+        source,
+        availability,
+        domain,
+        MRMS_PRECIP_FLAG_PRODUCT,
+        target_timestamp,
+    )
+    .await;
     let freezing_level = fetch_latest_aux_field_at_or_before(
-        http,
+        source,
+        availability,
+        domain,
         MRMS_MODEL_FREEZING_HEIGHT_PRODUCT,
         target_timestamp,
     )
     .await;
     let wet_bulb_temp = fetch_latest_aux_field_at_or_before(
-        http,
+        source,
+        availability,
+        domain,
         MRMS_MODEL_WET_BULB_TEMP_PRODUCT,
         target_timestamp,
     )
     .await;
     let surface_temp = fetch_latest_aux_field_at_or_before(
-        http,
+        source,
+        availability,
+        domain,
         MRMS_MODEL_SURFACE_TEMP_PRODUCT,
         target_timestamp,
     )
     .await;
-    let bright_band_top =
-        fetch_latest_aux_field_at_or_before(http, MRMS_BRIGHT_BAND_TOP_PRODUCT, target_timestamp)
-            .await;
+    let bright_band_top = fetch_latest_aux_field_at_or_before(
+        source,
+        availability,
+        domain,
+        MRMS_BRIGHT_BAND_TOP_PRODUCT,
+        target_timestamp,
+    )
+    .await;
     let bright_band_bottom = fetch_latest_aux_field_at_or_before(
-        http,
+        source,
+        availability,
+        domain,
         MRMS_BRIGHT_BAND_BOTTOM_PRODUCT,
         target_timestamp,
     )
     .await;
-    let radar_quality_index =
-        fetch_latest_aux_field_at_or_before(http, MRMS_RQI_PRODUCT, target_timestamp).await;
+    let radar_quality_index = fetch_latest_aux_field_at_or_before(
+        source,
+        availability,
+        domain,
+        MRMS_RQI_PRODUCT,
+        target_timestamp,
+    )
+    .await;
 
     ThermoAuxBundle {
         precip_flag,
@@ -1084,15 +1468,44 @@ async fn fetch_thermo_aux_bundle(http: &Client, target_timestamp: &str) -> Therm
     }
 }
 
-async fn fetch_echo_top_bundle(http: &Client, target_timestamp: &str) -> EchoTopBundle {
-    let top18 =
-        fetch_latest_aux_field_at_or_before(http, MRMS_ECHO_TOP_18_PRODUCT, target_timestamp).await;
-    let top30 =
-        fetch_latest_aux_field_at_or_before(http, MRMS_ECHO_TOP_30_PRODUCT, target_timestamp).await;
-    let top50 =
-        fetch_latest_aux_field_at_or_before(http, MRMS_ECHO_TOP_50_PRODUCT, target_timestamp).await;
-    let top60 =
-        fetch_latest_aux_field_at_or_before(http, MRMS_ECHO_TOP_60_PRODUCT, target_timestamp).await;
+async fn fetch_echo_top_bundle(
+    source: &dyn IngestSource,
+    availability: &AvailabilityIndex,
+    domain: MrmsDomain,
+    target_timestamp: &str,
+) -> EchoTopBundle {
+    let top18 = fetch_latest_aux_field_at_or_before(
+        source,
+        availability,
+        domain,
+        MRMS_ECHO_TOP_18_PRODUCT,
+        target_timestamp,
+    )
+    .await;
+    let top30 = fetch_latest_aux_field_at_or_before(
+        source,
+        availability,
+        domain,
+        MRMS_ECHO_TOP_30_PRODUCT,
+        target_timestamp,
+    )
+    .await;
+    let top50 = fetch_latest_aux_field_at_or_before(
+        source,
+        availability,
+        domain,
+        MRMS_ECHO_TOP_50_PRODUCT,
+        target_timestamp,
+    )
+    .await;
+    let top60 = fetch_latest_aux_field_at_or_before(
+        source,
+        availability,
+        domain,
+        MRMS_ECHO_TOP_60_PRODUCT,
+        target_timestamp,
+    )
+    .await;
 
     EchoTopBundle {
         top18,
@@ -1103,13 +1516,22 @@ async fn fetch_echo_top_bundle(http: &Client, target_timestamp: &str) -> EchoTop
 }
 
 async fn fetch_latest_aux_field_at_or_before(
-    http: &Client,
+    source: &dyn IngestSource,
+    availability: &AvailabilityIndex,
+    domain: MrmsDomain,
     product: &'static str,
     target_timestamp: &str,
 ) -> Option<(String, ParsedAuxField)> {
-    let timestamp = find_latest_aux_timestamp_at_or_before(http, product, target_timestamp).await?;
+    let timestamp = find_latest_aux_timestamp_at_or_before(
+        source,
+        availability,
+        domain,
+        product,
+        target_timestamp,
+    )
+    .await?;
     let date_part = timestamp.split('-').next()?;
-    match fetch_aux_field_at_timestamp(http, product, date_part, &timestamp).await {
+    match fetch_aux_field_at_timestamp(source, domain, product, date_part, &timestamp).await {
         Ok(field) => Some((timestamp, field)),
         Err(error) => {
             warn!(
@@ -1515,11 +1937,12 @@ fn resolve_phase_from_evidence(
     }
 
     let mut forced_precip_snow = false;
-    if thermo.precip_flag_phase == Some(PHASE_SNOW) && phase != PHASE_SNOW {
-        if thermo.phase == PHASE_SNOW || thermo.near_transition {
-            phase = PHASE_SNOW;
-            forced_precip_snow = true;
-        }
+    if thermo.precip_flag_phase == Some(PHASE_SNOW)
+        && phase != PHASE_SNOW
+        && (thermo.phase == PHASE_SNOW || thermo.near_transition)
+    {
+        phase = PHASE_SNOW;
+        forced_precip_snow = true;
     }
 
     PhaseResolution {
@@ -1674,80 +2097,107 @@ fn echo_top_km_to_feet(value: f32) -> Option<u16> {
 }
 
 fn build_level_key(
+    domain: MrmsDomain,
     product_prefix: &str,
     level_tag: &str,
     date_part: &str,
     timestamp: &str,
 ) -> String {
     format!(
-        "{MRMS_CONUS_PREFIX}/{product_prefix}_{level_tag}/{date_part}/MRMS_{product_prefix}_{level_tag}_{timestamp}.grib2.gz"
+        "{}/{product_prefix}_{level_tag}/{date_part}/MRMS_{product_prefix}_{level_tag}_{timestamp}.grib2.gz",
+        domain.s3_prefix()
     )
 }
 
 async fn fetch_level_aux_field_at_timestamp(
-    http: &Client,
+    source: &dyn IngestSource,
+    domain: MrmsDomain,
     product_prefix: &str,
     level_tag: &str,
     date_part: &str,
     timestamp: &str,
 ) -> Result<ParsedAuxField> {
-    let key = build_level_key(product_prefix, level_tag, date_part, timestamp);
-    let url = format!("{MRMS_BUCKET_URL}/{key}");
-    let zipped = fetch_bytes(http, &url).await?;
-    let parsed = tokio::task::spawn_blocking(move || parse_aux_grib_gzipped(&zipped))
-        .await
-        .context("Join error while parsing aux GRIB")??;
+    let key = build_level_key(domain, product_prefix, level_tag, date_part, timestamp);
+    fetch_and_parse_aux_object(source, domain, &key).await
+}
+
+/// Reads and decodes one aux GRIB2 object, timing both stages.
+async fn fetch_and_parse_aux_object(
+    source: &dyn IngestSource,
+    domain: MrmsDomain,
+    key: &str,
+) -> Result<ParsedAuxField> {
+    let fetch_started = Instant::now();
+    let zipped = source.fetch_object(key).await?;
+    METRICS.observe_stage(domain, IngestStage::Fetch, fetch_started);
+    let decode_started = Instant::now();
+    let span = Span::current();
+    let parsed =
+        tokio::task::spawn_blocking(move || span.in_scope(|| parse_aux_grib_gzipped(&zipped)))
+            .await
+            .context("Join error while parsing aux GRIB")??;
+    METRICS.observe_stage(domain, IngestStage::GribDecode, decode_started);
     Ok(parsed)
 }
 
-fn build_aux_key(product: &str, date_part: &str, timestamp: &str) -> String {
-    format!("{MRMS_CONUS_PREFIX}/{product}/{date_part}/MRMS_{product}_{timestamp}.grib2.gz")
+fn build_aux_key(domain: MrmsDomain, product: &str, date_part: &str, timestamp: &str) -> String {
+    format!(
+        "{}/{product}/{date_part}/MRMS_{product}_{timestamp}.grib2.gz",
+        domain.s3_prefix()
+    )
 }
 
 async fn fetch_aux_field_at_timestamp(
-    http: &Client,
+    source: &dyn IngestSource,
+    domain: MrmsDomain,
     product: &str,
     date_part: &str,
     timestamp: &str,
 ) -> Result<ParsedAuxField> {
-    let key = build_aux_key(product, date_part, timestamp);
-    let url = format!("{MRMS_BUCKET_URL}/{key}");
-    let zipped = fetch_bytes(http, &url).await?;
-    let parsed = tokio::task::spawn_blocking(move || parse_aux_grib_gzipped(&zipped))
-        .await
-        .context("Join error while parsing aux context GRIB")??;
-    Ok(parsed)
+    let key = build_aux_key(domain, product, date_part, timestamp);
+    fetch_and_parse_aux_object(source, domain, &key).await
 }
 
 async fn find_latest_level_timestamp_at_or_before(
-    http: &Client,
+    source: &dyn IngestSource,
+    availability: &AvailabilityIndex,
+    domain: MrmsDomain,
     product_prefix: &str,
     level_tag: &str,
     target_timestamp: &str,
 ) -> Option<String> {
+    let product = format!("{product_prefix}_{level_tag}");
+    if let Some(timestamp) = availability.latest_at_or_before(&product, target_timestamp) {
+        return Some(timestamp);
+    }
     find_latest_timestamp_at_or_before(
-        http,
-        |day| format!("{MRMS_CONUS_PREFIX}/{product_prefix}_{level_tag}/{day}/"),
+        source,
+        |day| format!("{}/{product}/{day}/", domain.s3_prefix()),
         target_timestamp,
     )
     .await
 }
 
 async fn find_latest_aux_timestamp_at_or_before(
-    http: &Client,
+    source: &dyn IngestSource,
+    availability: &AvailabilityIndex,
+    domain: MrmsDomain,
     product: &str,
     target_timestamp: &str,
 ) -> Option<String> {
+    if let Some(timestamp) = availability.latest_at_or_before(product, target_timestamp) {
+        return Some(timestamp);
+    }
     find_latest_timestamp_at_or_before(
-        http,
-        |day| format!("{MRMS_CONUS_PREFIX}/{product}/{day}/"),
+        source,
+        |day| format!("{}/{product}/{day}/", domain.s3_prefix()),
         target_timestamp,
     )
     .await
 }
 
 async fn find_latest_timestamp_at_or_before<F>(
-    http: &Client,
+    source: &dyn IngestSource,
     prefix_builder: F,
     target_timestamp: &str,
 ) -> Option<String>
@@ -1769,7 +2219,7 @@ where
             .format("%Y%m%d")
             .to_string();
         let prefix = prefix_builder(&day);
-        let keys = match list_keys_for_prefix(http, &prefix).await {
+        let keys = match source.list_keys(&prefix).await {
             Ok(value) => value,
             Err(error) => {
                 warn!("Failed listing MRMS keys for prefix {prefix}: {error:#}");
@@ -1794,52 +2244,6 @@ where
     best
 }
 
-async fn list_keys_for_prefix(http: &Client, prefix: &str) -> Result<Vec<String>> {
-    let mut keys = Vec::new();
-    let mut continuation_token: Option<String> = None;
-
-    for _ in 0..4 {
-        let mut url = format!(
-            "{MRMS_BUCKET_URL}/?list-type=2&prefix={}&max-keys=1000",
-            urlencoding::encode(prefix)
-        );
-        if let Some(token) = continuation_token.as_ref() {
-            url.push_str("&continuation-token=");
-            url.push_str(&urlencoding::encode(token));
-        }
-
-        let xml = crate::http_client::fetch_text(http, &url).await?;
-        keys.extend(parse_xml_tag_values(&xml, "Key"));
-
-        let is_truncated = parse_xml_tag_value(&xml, "IsTruncated")
-            .map(|value| value == "true")
-            .unwrap_or(false);
-        if !is_truncated {
-            break;
-        }
-
-        continuation_token = parse_xml_tag_value(&xml, "NextContinuationToken");
-        if continuation_token.is_none() {
-            break;
-        }
-    }
-
-    Ok(keys)
-}
-
-fn parse_xml_tag_values(xml: &str, tag_name: &str) -> Vec<String> {
-    let regex = Regex::new(&format!(r"<{0}>([^<]+)</{0}>", regex::escape(tag_name)))
-        .unwrap_or_else(|_| Regex::new(r"$^").unwrap());
-    regex
-        .captures_iter(xml)
-        .filter_map(|captures| captures.get(1).map(|value| value.as_str().to_string()))
-        .collect()
-}
-
-fn parse_xml_tag_value(xml: &str, tag_name: &str) -> Option<String> {
-    parse_xml_tag_values(xml, tag_name).into_iter().next()
-}
-
 fn timestamp_age_seconds(newer_timestamp: &str, older_timestamp: &str) -> Option<i64> {
     let newer = parse_timestamp_utc(newer_timestamp)?;
     let older = parse_timestamp_utc(older_timestamp)?;
@@ -2172,12 +2576,61 @@ mod tests {
         let level = "03.00";
         let suffix = format!("_{level}_{timestamp}.grib2.gz");
 
-        let reflectivity = build_level_key(MRMS_PRODUCT_PREFIX, level, date, timestamp);
-        let zdr = build_level_key(MRMS_ZDR_PRODUCT_PREFIX, level, date, timestamp);
-        let rhohv = build_level_key(MRMS_RHOHV_PRODUCT_PREFIX, level, date, timestamp);
+        let reflectivity = build_level_key(
+            MrmsDomain::Conus,
+            MRMS_PRODUCT_PREFIX,
+            level,
+            date,
+            timestamp,
+        );
+        let zdr = build_level_key(
+            MrmsDomain::Conus,
+            MRMS_ZDR_PRODUCT_PREFIX,
+            level,
+            date,
+            timestamp,
+        );
+        let rhohv = build_level_key(
+            MrmsDomain::Conus,
+            MRMS_RHOHV_PRODUCT_PREFIX,
+            level,
+            date,
+            timestamp,
+        );
 
         assert!(reflectivity.ends_with(&suffix));
         assert!(zdr.ends_with(&suffix));
         assert!(rhohv.ends_with(&suffix));
     }
+
+    #[test]
+    fn sqs_body_objects_are_tagged_with_domain_and_product() {
+        let regex = Regex::new(SQS_OBJECT_KEY_PATTERN).unwrap();
+        let body = r#"{"Message":"{\"Records\":[{\"s3\":{\"object\":{\"key\":\"ALASKA/MergedReflectivityQC_00.50/20260212/MRMS_MergedReflectivityQC_00.50_20260212-120000.grib2.gz\"}}}]}","Extra":"MRMS_MergedReflectivityQC_00.50_20260212-120200.grib2.gz"}"#;
+
+        let extracted = extract_objects_from_sqs_body(body, &regex);
+        let base = "MergedReflectivityQC_00.50".to_string();
+        assert!(extracted.contains(&(
+            MrmsDomain::Alaska,
+            base.clone(),
+            "20260212-120000".to_string()
+        )));
+        assert!(extracted.contains(&(MrmsDomain::Conus, base, "20260212-120200".to_string())));
+
+        let aux_body = "CONUS%2FMergedZdr_03.00%2F20260212%2FMRMS_MergedZdr_03.00_20260212-120000.grib2.gz PrecipFlag_00.00/20260212/MRMS_PrecipFlag_00.00_20260212-120000.grib2.gz";
+        let extracted =
+            extract_objects_from_sqs_body(&format!("{{\"key\":\"{aux_body}\"}}"), &regex);
+        assert!(extracted.contains(&(
+            MrmsDomain::Conus,
+            "MergedZdr_03.00".to_string(),
+            "20260212-120000".to_string()
+        )));
+        assert!(extracted.contains(&(
+            MrmsDomain::Conus,
+            "PrecipFlag_00.00".to_string(),
+            "20260212-120000".to_string()
+        )));
+        assert!(is_scan_product("MergedZdr_03.00"));
+        assert!(!is_scan_product("MergedZdrFoo_03.00"));
+    }
 }
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+/// Why a scan was ingested when it was; recorded in `PhaseDebugMetadata`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IngestPolicy {
+    /// Every reflectivity and dual-pol level was available.
+    Complete,
+    /// The deadline passed first; missing inputs used at-or-before fallbacks.
+    Deadline,
+    /// Re-ingest of a deadline scan once its missing levels arrived.
+    Upgrade,
+    /// Offline backfill run; ingested with whatever is available.
+    Backfill,
+}
+
+impl IngestPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IngestPolicy::Complete => "complete",
+            IngestPolicy::Deadline => "deadline",
+            IngestPolicy::Upgrade => "upgrade",
+            IngestPolicy::Backfill => "backfill",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IngestDecision {
+    Ingest(IngestPolicy),
+    /// Check again later; nothing new to ingest yet.
+    Wait,
+    /// Give up on an upgrade whose missing levels never arrived.
+    Drop,
+}
+
+/// Decides what to do with a due pending timestamp. `waited` is measured from
+/// when the timestamp was first enqueued (or, for upgrades, first ingested).
+pub fn decide_ingest(
+    missing_scan_products: usize,
+    waited: Duration,
+    upgrade: bool,
+    deadline: Duration,
+    upgrade_window: Duration,
+) -> IngestDecision {
+    match (upgrade, missing_scan_products == 0) {
+        (false, true) => IngestDecision::Ingest(IngestPolicy::Complete),
+        (false, false) if waited >= deadline => IngestDecision::Ingest(IngestPolicy::Deadline),
+        (true, true) => IngestDecision::Ingest(IngestPolicy::Upgrade),
+        (true, false) if waited >= upgrade_window => IngestDecision::Drop,
+        _ => IngestDecision::Wait,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decide_ingest_waits_for_completeness_until_deadline() {
+        let deadline = Duration::from_secs(240);
+        let window = Duration::from_secs(900);
+        let at = Duration::from_secs;
+
+        assert_eq!(
+            decide_ingest(0, at(5), false, deadline, window),
+            IngestDecision::Ingest(IngestPolicy::Complete)
+        );
+        assert_eq!(
+            decide_ingest(12, at(60), false, deadline, window),
+            IngestDecision::Wait
+        );
+        assert_eq!(
+            decide_ingest(12, at(240), false, deadline, window),
+            IngestDecision::Ingest(IngestPolicy::Deadline)
+        );
+        assert_eq!(
+            decide_ingest(0, at(300), true, deadline, window),
+            IngestDecision::Ingest(IngestPolicy::Upgrade)
+        );
+        assert_eq!(
+            decide_ingest(3, at(300), true, deadline, window),
+            IngestDecision::Wait
+        );
+        assert_eq!(
+            decide_ingest(3, at(900), true, deadline, window),
+            IngestDecision::Drop
+        );
+    }
+}
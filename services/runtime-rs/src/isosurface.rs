@@ -0,0 +1,629 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::api::{project_grid_position_nm, QueryWindow};
+use crate::constants::{
+    MAX_ISOSURFACE_SAMPLES, MAX_ISOSURFACE_TRIANGLES, MESH_HEADER_BYTES, MESH_MAGIC,
+    MESH_V1_VERSION, MESH_VERTEX_BYTES,
+};
+use crate::types::ScanSnapshot;
+use crate::utils::{round_i16, round_u16};
+
+// Grid samples without a stored voxel are treated as "no echo". Stored voxels
+// are always >= STORE_MIN_DBZ_TENTHS, so this sits below every valid threshold.
+const EMPTY_DBZ_TENTHS: i16 = 0;
+
+/// Corner offsets of a marching cube as (col, row, level) deltas.
+const CORNERS: [[i32; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [1, 1, 0],
+    [0, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [1, 1, 1],
+    [0, 1, 1],
+];
+
+/// Corner pairs spanned by each of the 12 cube edges.
+const EDGE_CORNERS: [[usize; 2]; 12] = [
+    [0, 1],
+    [1, 2],
+    [2, 3],
+    [3, 0],
+    [4, 5],
+    [5, 6],
+    [6, 7],
+    [7, 4],
+    [0, 4],
+    [1, 5],
+    [2, 6],
+    [3, 7],
+];
+
+#[derive(Debug, Default)]
+pub(crate) struct IsosurfaceMesh {
+    pub(crate) source_voxel_count: u32,
+    /// (x_nm, z_nm, altitude_feet) in the same frame as brick records.
+    pub(crate) vertices: Vec<(f64, f64, f64)>,
+    pub(crate) indices: Vec<u32>,
+}
+
+/// The window holds more lattice samples or yields more triangles than the
+/// mesh builder accepts.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum MeshTooLarge {
+    Samples(usize),
+    Triangles(usize),
+}
+
+impl std::fmt::Display for MeshTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Samples(limit) => write!(f, "query window holds more than {limit} voxels"),
+            Self::Triangles(limit) => write!(f, "isosurface has more than {limit} triangles"),
+        }
+    }
+}
+
+/// Sample lattice key. Row/col/level are shifted by one so the padding ring
+/// around the window (index -1) still packs into unsigned bits.
+fn sample_key(row: i32, col: i32, level: i32) -> u64 {
+    (((row + 1) as u64) << 40) | (((col + 1) as u64) << 16) | (level + 1) as u64
+}
+
+pub(crate) fn build_isosurface_mesh(
+    scan: &ScanSnapshot,
+    window: &QueryWindow,
+) -> Result<IsosurfaceMesh, MeshTooLarge> {
+    build_bounded_mesh(
+        scan,
+        window,
+        MAX_ISOSURFACE_SAMPLES,
+        MAX_ISOSURFACE_TRIANGLES,
+    )
+}
+
+fn build_bounded_mesh(
+    scan: &ScanSnapshot,
+    window: &QueryWindow,
+    max_samples: usize,
+    max_triangles: usize,
+) -> Result<IsosurfaceMesh, MeshTooLarge> {
+    let level_count = scan.level_bounds.len() as i32;
+    let mut samples: HashMap<u64, i16> = HashMap::new();
+    let mut cube_origins: HashSet<(i32, i32, i32)> = HashSet::new();
+    let mut source_voxel_count: u32 = 0;
+
+    for tile_row in window.tile_row_start..=window.tile_row_end {
+        for tile_col in window.tile_col_start..=window.tile_col_end {
+            let tile_idx = (tile_row * scan.tile_cols as u32 + tile_col) as usize;
+            if tile_idx + 1 >= scan.tile_offsets.len() {
+                continue;
+            }
+            let start = scan.tile_offsets[tile_idx] as usize;
+            let end = scan.tile_offsets[tile_idx + 1] as usize;
+            for record in &scan.voxels[start..end] {
+                let row = record.row as u32;
+                let col = record.col as u32;
+                if row < window.row_start || row > window.row_end {
+                    continue;
+                }
+                if !window.lon_wrapped && (col < window.col_start || col > window.col_end) {
+                    continue;
+                }
+                if record.level_idx as i32 >= level_count {
+                    continue;
+                }
+
+                let (x_nm, z_nm) = project_grid_position_nm(scan, window, row as f64, col as f64);
+                if !window.contains_nm(x_nm, z_nm) {
+                    continue;
+                }
+
+                let (row, col, level) = (row as i32, col as i32, record.level_idx as i32);
+                samples.insert(sample_key(row, col, level), record.dbz_tenths);
+                if samples.len() > max_samples {
+                    return Err(MeshTooLarge::Samples(max_samples));
+                }
+                if record.dbz_tenths < window.min_dbz_tenths {
+                    continue;
+                }
+
+                source_voxel_count = source_voxel_count.saturating_add(1);
+                for d_row in -1..=0 {
+                    for d_col in -1..=0 {
+                        for d_level in -1..=0 {
+                            cube_origins.insert((row + d_row, col + d_col, level + d_level));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut cubes: Vec<(i32, i32, i32)> = cube_origins.into_iter().collect();
+    cubes.sort_unstable();
+
+    let threshold = window.min_dbz_tenths;
+    let mut mesh = IsosurfaceMesh {
+        source_voxel_count,
+        ..IsosurfaceMesh::default()
+    };
+    let mut vertex_by_edge: HashMap<(u64, u64), u32> = HashMap::new();
+
+    for (row, col, level) in cubes {
+        let mut corners = [(0_i32, 0_i32, 0_i32); 8];
+        let mut values = [EMPTY_DBZ_TENTHS; 8];
+        let mut cube_index = 0_usize;
+        for (idx, offset) in CORNERS.iter().enumerate() {
+            let corner = (row + offset[1], col + offset[0], level + offset[2]);
+            corners[idx] = corner;
+            values[idx] = samples
+                .get(&sample_key(corner.0, corner.1, corner.2))
+                .copied()
+                .unwrap_or(EMPTY_DBZ_TENTHS);
+            if values[idx] < threshold {
+                cube_index |= 1 << idx;
+            }
+        }
+
+        let triangles = &TRIANGLE_TABLE[cube_index];
+        for edge in triangles.iter().take_while(|edge| **edge >= 0) {
+            let [a, b] = EDGE_CORNERS[*edge as usize];
+            let key_a = sample_key(corners[a].0, corners[a].1, corners[a].2);
+            let key_b = sample_key(corners[b].0, corners[b].1, corners[b].2);
+            let edge_key = if key_a < key_b {
+                (key_a, key_b)
+            } else {
+                (key_b, key_a)
+            };
+
+            let vertex_idx = match vertex_by_edge.get(&edge_key) {
+                Some(existing) => *existing,
+                None => {
+                    let vertex = interpolate_edge_vertex(
+                        scan, window, corners[a], values[a], corners[b], values[b], threshold,
+                    );
+                    let new_idx = mesh.vertices.len() as u32;
+                    mesh.vertices.push(vertex);
+                    vertex_by_edge.insert(edge_key, new_idx);
+                    new_idx
+                }
+            };
+            mesh.indices.push(vertex_idx);
+        }
+        if mesh.indices.len() / 3 > max_triangles {
+            return Err(MeshTooLarge::Triangles(max_triangles));
+        }
+    }
+
+    Ok(mesh)
+}
+
+fn interpolate_edge_vertex(
+    scan: &ScanSnapshot,
+    window: &QueryWindow,
+    corner_a: (i32, i32, i32),
+    value_a: i16,
+    corner_b: (i32, i32, i32),
+    value_b: i16,
+    threshold: i16,
+) -> (f64, f64, f64) {
+    let delta = value_b as f64 - value_a as f64;
+    let t = if delta.abs() < f64::EPSILON {
+        0.5
+    } else {
+        ((threshold as f64 - value_a as f64) / delta).clamp(0.0, 1.0)
+    };
+
+    let row = corner_a.0 as f64 + t * (corner_b.0 - corner_a.0) as f64;
+    let col = corner_a.1 as f64 + t * (corner_b.1 - corner_a.1) as f64;
+    let (x_nm, z_nm) = project_grid_position_nm(scan, window, row, col);
+
+    let feet_a = level_sample_feet(scan, corner_a.2);
+    let feet_b = level_sample_feet(scan, corner_b.2);
+    (x_nm, z_nm, feet_a + t * (feet_b - feet_a))
+}
+
+/// Altitude of a lattice sample: level mid-point, with the padding levels
+/// pinned to the bottom of the lowest and top of the highest level.
+fn level_sample_feet(scan: &ScanSnapshot, level: i32) -> f64 {
+    let Some(last) = scan.level_bounds.last() else {
+        return 0.0;
+    };
+    if level < 0 {
+        return scan.level_bounds[0].bottom_feet as f64;
+    }
+    match scan.level_bounds.get(level as usize) {
+        Some(bounds) => (bounds.bottom_feet as f64 + bounds.top_feet as f64) / 2.0,
+        None => last.top_feet as f64,
+    }
+}
+
+pub(crate) fn encode_isosurface_wire(
+    scan: &ScanSnapshot,
+    window: &QueryWindow,
+    mesh: &IsosurfaceMesh,
+) -> Vec<u8> {
+    let vertex_count = mesh.vertices.len();
+    let triangle_count = mesh.indices.len() / 3;
+    let index_bytes: u16 = if vertex_count <= u16::MAX as usize {
+        2
+    } else {
+        4
+    };
+
+    let mut body = vec![0_u8; MESH_HEADER_BYTES];
+    body.reserve(vertex_count * MESH_VERTEX_BYTES + mesh.indices.len() * index_bytes as usize);
+    body[0..4].copy_from_slice(&MESH_MAGIC);
+    body[4..6].copy_from_slice(&MESH_V1_VERSION.to_le_bytes());
+    body[6..8].copy_from_slice(&(MESH_HEADER_BYTES as u16).to_le_bytes());
+    body[8..12].copy_from_slice(&(vertex_count as u32).to_le_bytes());
+    body[12..16].copy_from_slice(&(triangle_count as u32).to_le_bytes());
+    body[16..18].copy_from_slice(&(MESH_VERTEX_BYTES as u16).to_le_bytes());
+    body[18..20].copy_from_slice(&index_bytes.to_le_bytes());
+    body[20..28].copy_from_slice(&scan.generated_at_ms.to_le_bytes());
+    body[28..36].copy_from_slice(&scan.scan_time_ms.to_le_bytes());
+    body[36..38].copy_from_slice(&window.footprint_x_milli.to_le_bytes());
+    body[38..40].copy_from_slice(&window.footprint_y_milli.to_le_bytes());
+    body[40..42].copy_from_slice(&window.min_dbz_tenths.to_le_bytes());
+    body[42..44].copy_from_slice(&round_u16(window.max_range_nm * 10.0).to_le_bytes());
+    body[44..48].copy_from_slice(&mesh.source_voxel_count.to_le_bytes());
+    body[48..52].copy_from_slice(&((window.origin_lat * 1_000_000.0).round() as i32).to_le_bytes());
+    body[52..56].copy_from_slice(&((window.origin_lon * 1_000_000.0).round() as i32).to_le_bytes());
+
+    for (x_nm, z_nm, altitude_feet) in &mesh.vertices {
+        body.extend_from_slice(&round_i16(x_nm * 100.0).to_le_bytes());
+        body.extend_from_slice(&round_i16(z_nm * 100.0).to_le_bytes());
+        body.extend_from_slice(&round_u16(*altitude_feet).to_le_bytes());
+    }
+    for index in mesh.indices.iter().take(triangle_count * 3) {
+        if index_bytes == 2 {
+            body.extend_from_slice(&(*index as u16).to_le_bytes());
+        } else {
+            body.extend_from_slice(&index.to_le_bytes());
+        }
+    }
+
+    body
+}
+
+/// Marching-cubes triangulation per corner mask (bit set when the corner is
+/// below threshold). Standard Lorensen/Bourke table; -1 terminates each row.
+const TRIANGLE_TABLE: [[i8; 16]; 256] = [
+    [
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+    ],
+    [0, 8, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 8, 3, 9, 8, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 2, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 8, 3, 1, 2, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 2, 10, 0, 2, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 8, 3, 2, 10, 8, 10, 9, 8, -1, -1, -1, -1, -1, -1, -1],
+    [3, 11, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 11, 2, 8, 11, 0, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 9, 0, 2, 3, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 11, 2, 1, 9, 11, 9, 8, 11, -1, -1, -1, -1, -1, -1, -1],
+    [3, 10, 1, 11, 10, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 10, 1, 0, 8, 10, 8, 11, 10, -1, -1, -1, -1, -1, -1, -1],
+    [3, 9, 0, 3, 11, 9, 11, 10, 9, -1, -1, -1, -1, -1, -1, -1],
+    [9, 8, 10, 10, 8, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 7, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 3, 0, 7, 3, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 9, 8, 4, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 1, 9, 4, 7, 1, 7, 3, 1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 2, 10, 8, 4, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 4, 7, 3, 0, 4, 1, 2, 10, -1, -1, -1, -1, -1, -1, -1],
+    [9, 2, 10, 9, 0, 2, 8, 4, 7, -1, -1, -1, -1, -1, -1, -1],
+    [2, 10, 9, 2, 9, 7, 2, 7, 3, 7, 9, 4, -1, -1, -1, -1],
+    [8, 4, 7, 3, 11, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [11, 4, 7, 11, 2, 4, 2, 0, 4, -1, -1, -1, -1, -1, -1, -1],
+    [9, 0, 1, 8, 4, 7, 2, 3, 11, -1, -1, -1, -1, -1, -1, -1],
+    [4, 7, 11, 9, 4, 11, 9, 11, 2, 9, 2, 1, -1, -1, -1, -1],
+    [3, 10, 1, 3, 11, 10, 7, 8, 4, -1, -1, -1, -1, -1, -1, -1],
+    [1, 11, 10, 1, 4, 11, 1, 0, 4, 7, 11, 4, -1, -1, -1, -1],
+    [4, 7, 8, 9, 0, 11, 9, 11, 10, 11, 0, 3, -1, -1, -1, -1],
+    [4, 7, 11, 4, 11, 9, 9, 11, 10, -1, -1, -1, -1, -1, -1, -1],
+    [9, 5, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 5, 4, 0, 8, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 5, 4, 1, 5, 0, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 5, 4, 8, 3, 5, 3, 1, 5, -1, -1, -1, -1, -1, -1, -1],
+    [1, 2, 10, 9, 5, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 0, 8, 1, 2, 10, 4, 9, 5, -1, -1, -1, -1, -1, -1, -1],
+    [5, 2, 10, 5, 4, 2, 4, 0, 2, -1, -1, -1, -1, -1, -1, -1],
+    [2, 10, 5, 3, 2, 5, 3, 5, 4, 3, 4, 8, -1, -1, -1, -1],
+    [9, 5, 4, 2, 3, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 11, 2, 0, 8, 11, 4, 9, 5, -1, -1, -1, -1, -1, -1, -1],
+    [0, 5, 4, 0, 1, 5, 2, 3, 11, -1, -1, -1, -1, -1, -1, -1],
+    [2, 1, 5, 2, 5, 8, 2, 8, 11, 4, 8, 5, -1, -1, -1, -1],
+    [10, 3, 11, 10, 1, 3, 9, 5, 4, -1, -1, -1, -1, -1, -1, -1],
+    [4, 9, 5, 0, 8, 1, 8, 10, 1, 8, 11, 10, -1, -1, -1, -1],
+    [5, 4, 0, 5, 0, 11, 5, 11, 10, 11, 0, 3, -1, -1, -1, -1],
+    [5, 4, 8, 5, 8, 10, 10, 8, 11, -1, -1, -1, -1, -1, -1, -1],
+    [9, 7, 8, 5, 7, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 3, 0, 9, 5, 3, 5, 7, 3, -1, -1, -1, -1, -1, -1, -1],
+    [0, 7, 8, 0, 1, 7, 1, 5, 7, -1, -1, -1, -1, -1, -1, -1],
+    [1, 5, 3, 3, 5, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 7, 8, 9, 5, 7, 10, 1, 2, -1, -1, -1, -1, -1, -1, -1],
+    [10, 1, 2, 9, 5, 0, 5, 3, 0, 5, 7, 3, -1, -1, -1, -1],
+    [8, 0, 2, 8, 2, 5, 8, 5, 7, 10, 5, 2, -1, -1, -1, -1],
+    [2, 10, 5, 2, 5, 3, 3, 5, 7, -1, -1, -1, -1, -1, -1, -1],
+    [7, 9, 5, 7, 8, 9, 3, 11, 2, -1, -1, -1, -1, -1, -1, -1],
+    [9, 5, 7, 9, 7, 2, 9, 2, 0, 2, 7, 11, -1, -1, -1, -1],
+    [2, 3, 11, 0, 1, 8, 1, 7, 8, 1, 5, 7, -1, -1, -1, -1],
+    [11, 2, 1, 11, 1, 7, 7, 1, 5, -1, -1, -1, -1, -1, -1, -1],
+    [9, 5, 8, 8, 5, 7, 10, 1, 3, 10, 3, 11, -1, -1, -1, -1],
+    [5, 7, 0, 5, 0, 9, 7, 11, 0, 1, 0, 10, 11, 10, 0, -1],
+    [11, 10, 0, 11, 0, 3, 10, 5, 0, 8, 0, 7, 5, 7, 0, -1],
+    [11, 10, 5, 7, 11, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 6, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 8, 3, 5, 10, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 0, 1, 5, 10, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 8, 3, 1, 9, 8, 5, 10, 6, -1, -1, -1, -1, -1, -1, -1],
+    [1, 6, 5, 2, 6, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 6, 5, 1, 2, 6, 3, 0, 8, -1, -1, -1, -1, -1, -1, -1],
+    [9, 6, 5, 9, 0, 6, 0, 2, 6, -1, -1, -1, -1, -1, -1, -1],
+    [5, 9, 8, 5, 8, 2, 5, 2, 6, 3, 2, 8, -1, -1, -1, -1],
+    [2, 3, 11, 10, 6, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [11, 0, 8, 11, 2, 0, 10, 6, 5, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 9, 2, 3, 11, 5, 10, 6, -1, -1, -1, -1, -1, -1, -1],
+    [5, 10, 6, 1, 9, 2, 9, 11, 2, 9, 8, 11, -1, -1, -1, -1],
+    [6, 3, 11, 6, 5, 3, 5, 1, 3, -1, -1, -1, -1, -1, -1, -1],
+    [0, 8, 11, 0, 11, 5, 0, 5, 1, 5, 11, 6, -1, -1, -1, -1],
+    [3, 11, 6, 0, 3, 6, 0, 6, 5, 0, 5, 9, -1, -1, -1, -1],
+    [6, 5, 9, 6, 9, 11, 11, 9, 8, -1, -1, -1, -1, -1, -1, -1],
+    [5, 10, 6, 4, 7, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 3, 0, 4, 7, 3, 6, 5, 10, -1, -1, -1, -1, -1, -1, -1],
+    [1, 9, 0, 5, 10, 6, 8, 4, 7, -1, -1, -1, -1, -1, -1, -1],
+    [10, 6, 5, 1, 9, 7, 1, 7, 3, 7, 9, 4, -1, -1, -1, -1],
+    [6, 1, 2, 6, 5, 1, 4, 7, 8, -1, -1, -1, -1, -1, -1, -1],
+    [1, 2, 5, 5, 2, 6, 3, 0, 4, 3, 4, 7, -1, -1, -1, -1],
+    [8, 4, 7, 9, 0, 5, 0, 6, 5, 0, 2, 6, -1, -1, -1, -1],
+    [7, 3, 9, 7, 9, 4, 3, 2, 9, 5, 9, 6, 2, 6, 9, -1],
+    [3, 11, 2, 7, 8, 4, 10, 6, 5, -1, -1, -1, -1, -1, -1, -1],
+    [5, 10, 6, 4, 7, 2, 4, 2, 0, 2, 7, 11, -1, -1, -1, -1],
+    [0, 1, 9, 4, 7, 8, 2, 3, 11, 5, 10, 6, -1, -1, -1, -1],
+    [9, 2, 1, 9, 11, 2, 9, 4, 11, 7, 11, 4, 5, 10, 6, -1],
+    [8, 4, 7, 3, 11, 5, 3, 5, 1, 5, 11, 6, -1, -1, -1, -1],
+    [5, 1, 11, 5, 11, 6, 1, 0, 11, 7, 11, 4, 0, 4, 11, -1],
+    [0, 5, 9, 0, 6, 5, 0, 3, 6, 11, 6, 3, 8, 4, 7, -1],
+    [6, 5, 9, 6, 9, 11, 4, 7, 9, 7, 11, 9, -1, -1, -1, -1],
+    [10, 4, 9, 6, 4, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 10, 6, 4, 9, 10, 0, 8, 3, -1, -1, -1, -1, -1, -1, -1],
+    [10, 0, 1, 10, 6, 0, 6, 4, 0, -1, -1, -1, -1, -1, -1, -1],
+    [8, 3, 1, 8, 1, 6, 8, 6, 4, 6, 1, 10, -1, -1, -1, -1],
+    [1, 4, 9, 1, 2, 4, 2, 6, 4, -1, -1, -1, -1, -1, -1, -1],
+    [3, 0, 8, 1, 2, 9, 2, 4, 9, 2, 6, 4, -1, -1, -1, -1],
+    [0, 2, 4, 4, 2, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 3, 2, 8, 2, 4, 4, 2, 6, -1, -1, -1, -1, -1, -1, -1],
+    [10, 4, 9, 10, 6, 4, 11, 2, 3, -1, -1, -1, -1, -1, -1, -1],
+    [0, 8, 2, 2, 8, 11, 4, 9, 10, 4, 10, 6, -1, -1, -1, -1],
+    [3, 11, 2, 0, 1, 6, 0, 6, 4, 6, 1, 10, -1, -1, -1, -1],
+    [6, 4, 1, 6, 1, 10, 4, 8, 1, 2, 1, 11, 8, 11, 1, -1],
+    [9, 6, 4, 9, 3, 6, 9, 1, 3, 11, 6, 3, -1, -1, -1, -1],
+    [8, 11, 1, 8, 1, 0, 11, 6, 1, 9, 1, 4, 6, 4, 1, -1],
+    [3, 11, 6, 3, 6, 0, 0, 6, 4, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 8, 11, 6, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [7, 10, 6, 7, 8, 10, 8, 9, 10, -1, -1, -1, -1, -1, -1, -1],
+    [0, 7, 3, 0, 10, 7, 0, 9, 10, 6, 7, 10, -1, -1, -1, -1],
+    [10, 6, 7, 1, 10, 7, 1, 7, 8, 1, 8, 0, -1, -1, -1, -1],
+    [10, 6, 7, 10, 7, 1, 1, 7, 3, -1, -1, -1, -1, -1, -1, -1],
+    [1, 2, 6, 1, 6, 8, 1, 8, 9, 8, 6, 7, -1, -1, -1, -1],
+    [2, 6, 9, 2, 9, 1, 6, 7, 9, 0, 9, 3, 7, 3, 9, -1],
+    [7, 8, 0, 7, 0, 6, 6, 0, 2, -1, -1, -1, -1, -1, -1, -1],
+    [7, 3, 2, 6, 7, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 3, 11, 10, 6, 8, 10, 8, 9, 8, 6, 7, -1, -1, -1, -1],
+    [2, 0, 7, 2, 7, 11, 0, 9, 7, 6, 7, 10, 9, 10, 7, -1],
+    [1, 8, 0, 1, 7, 8, 1, 10, 7, 6, 7, 10, 2, 3, 11, -1],
+    [11, 2, 1, 11, 1, 7, 10, 6, 1, 6, 7, 1, -1, -1, -1, -1],
+    [8, 9, 6, 8, 6, 7, 9, 1, 6, 11, 6, 3, 1, 3, 6, -1],
+    [0, 9, 1, 11, 6, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [7, 8, 0, 7, 0, 6, 3, 11, 0, 11, 6, 0, -1, -1, -1, -1],
+    [7, 11, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [7, 6, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 0, 8, 11, 7, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 9, 11, 7, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 1, 9, 8, 3, 1, 11, 7, 6, -1, -1, -1, -1, -1, -1, -1],
+    [10, 1, 2, 6, 11, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 2, 10, 3, 0, 8, 6, 11, 7, -1, -1, -1, -1, -1, -1, -1],
+    [2, 9, 0, 2, 10, 9, 6, 11, 7, -1, -1, -1, -1, -1, -1, -1],
+    [6, 11, 7, 2, 10, 3, 10, 8, 3, 10, 9, 8, -1, -1, -1, -1],
+    [7, 2, 3, 6, 2, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [7, 0, 8, 7, 6, 0, 6, 2, 0, -1, -1, -1, -1, -1, -1, -1],
+    [2, 7, 6, 2, 3, 7, 0, 1, 9, -1, -1, -1, -1, -1, -1, -1],
+    [1, 6, 2, 1, 8, 6, 1, 9, 8, 8, 7, 6, -1, -1, -1, -1],
+    [10, 7, 6, 10, 1, 7, 1, 3, 7, -1, -1, -1, -1, -1, -1, -1],
+    [10, 7, 6, 1, 7, 10, 1, 8, 7, 1, 0, 8, -1, -1, -1, -1],
+    [0, 3, 7, 0, 7, 10, 0, 10, 9, 6, 10, 7, -1, -1, -1, -1],
+    [7, 6, 10, 7, 10, 8, 8, 10, 9, -1, -1, -1, -1, -1, -1, -1],
+    [6, 8, 4, 11, 8, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 6, 11, 3, 0, 6, 0, 4, 6, -1, -1, -1, -1, -1, -1, -1],
+    [8, 6, 11, 8, 4, 6, 9, 0, 1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 4, 6, 9, 6, 3, 9, 3, 1, 11, 3, 6, -1, -1, -1, -1],
+    [6, 8, 4, 6, 11, 8, 2, 10, 1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 2, 10, 3, 0, 11, 0, 6, 11, 0, 4, 6, -1, -1, -1, -1],
+    [4, 11, 8, 4, 6, 11, 0, 2, 9, 2, 10, 9, -1, -1, -1, -1],
+    [10, 9, 3, 10, 3, 2, 9, 4, 3, 11, 3, 6, 4, 6, 3, -1],
+    [8, 2, 3, 8, 4, 2, 4, 6, 2, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 2, 4, 6, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 9, 0, 2, 3, 4, 2, 4, 6, 4, 3, 8, -1, -1, -1, -1],
+    [1, 9, 4, 1, 4, 2, 2, 4, 6, -1, -1, -1, -1, -1, -1, -1],
+    [8, 1, 3, 8, 6, 1, 8, 4, 6, 6, 10, 1, -1, -1, -1, -1],
+    [10, 1, 0, 10, 0, 6, 6, 0, 4, -1, -1, -1, -1, -1, -1, -1],
+    [4, 6, 3, 4, 3, 8, 6, 10, 3, 0, 3, 9, 10, 9, 3, -1],
+    [10, 9, 4, 6, 10, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 9, 5, 7, 6, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 8, 3, 4, 9, 5, 11, 7, 6, -1, -1, -1, -1, -1, -1, -1],
+    [5, 0, 1, 5, 4, 0, 7, 6, 11, -1, -1, -1, -1, -1, -1, -1],
+    [11, 7, 6, 8, 3, 4, 3, 5, 4, 3, 1, 5, -1, -1, -1, -1],
+    [9, 5, 4, 10, 1, 2, 7, 6, 11, -1, -1, -1, -1, -1, -1, -1],
+    [6, 11, 7, 1, 2, 10, 0, 8, 3, 4, 9, 5, -1, -1, -1, -1],
+    [7, 6, 11, 5, 4, 10, 4, 2, 10, 4, 0, 2, -1, -1, -1, -1],
+    [3, 4, 8, 3, 5, 4, 3, 2, 5, 10, 5, 2, 11, 7, 6, -1],
+    [7, 2, 3, 7, 6, 2, 5, 4, 9, -1, -1, -1, -1, -1, -1, -1],
+    [9, 5, 4, 0, 8, 6, 0, 6, 2, 6, 8, 7, -1, -1, -1, -1],
+    [3, 6, 2, 3, 7, 6, 1, 5, 0, 5, 4, 0, -1, -1, -1, -1],
+    [6, 2, 8, 6, 8, 7, 2, 1, 8, 4, 8, 5, 1, 5, 8, -1],
+    [9, 5, 4, 10, 1, 6, 1, 7, 6, 1, 3, 7, -1, -1, -1, -1],
+    [1, 6, 10, 1, 7, 6, 1, 0, 7, 8, 7, 0, 9, 5, 4, -1],
+    [4, 0, 10, 4, 10, 5, 0, 3, 10, 6, 10, 7, 3, 7, 10, -1],
+    [7, 6, 10, 7, 10, 8, 5, 4, 10, 4, 8, 10, -1, -1, -1, -1],
+    [6, 9, 5, 6, 11, 9, 11, 8, 9, -1, -1, -1, -1, -1, -1, -1],
+    [3, 6, 11, 0, 6, 3, 0, 5, 6, 0, 9, 5, -1, -1, -1, -1],
+    [0, 11, 8, 0, 5, 11, 0, 1, 5, 5, 6, 11, -1, -1, -1, -1],
+    [6, 11, 3, 6, 3, 5, 5, 3, 1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 2, 10, 9, 5, 11, 9, 11, 8, 11, 5, 6, -1, -1, -1, -1],
+    [0, 11, 3, 0, 6, 11, 0, 9, 6, 5, 6, 9, 1, 2, 10, -1],
+    [11, 8, 5, 11, 5, 6, 8, 0, 5, 10, 5, 2, 0, 2, 5, -1],
+    [6, 11, 3, 6, 3, 5, 2, 10, 3, 10, 5, 3, -1, -1, -1, -1],
+    [5, 8, 9, 5, 2, 8, 5, 6, 2, 3, 8, 2, -1, -1, -1, -1],
+    [9, 5, 6, 9, 6, 0, 0, 6, 2, -1, -1, -1, -1, -1, -1, -1],
+    [1, 5, 8, 1, 8, 0, 5, 6, 8, 3, 8, 2, 6, 2, 8, -1],
+    [1, 5, 6, 2, 1, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 3, 6, 1, 6, 10, 3, 8, 6, 5, 6, 9, 8, 9, 6, -1],
+    [10, 1, 0, 10, 0, 6, 9, 5, 0, 5, 6, 0, -1, -1, -1, -1],
+    [0, 3, 8, 5, 6, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [11, 5, 10, 7, 5, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [11, 5, 10, 11, 7, 5, 8, 3, 0, -1, -1, -1, -1, -1, -1, -1],
+    [5, 11, 7, 5, 10, 11, 1, 9, 0, -1, -1, -1, -1, -1, -1, -1],
+    [10, 7, 5, 10, 11, 7, 9, 8, 1, 8, 3, 1, -1, -1, -1, -1],
+    [11, 1, 2, 11, 7, 1, 7, 5, 1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 8, 3, 1, 2, 7, 1, 7, 5, 7, 2, 11, -1, -1, -1, -1],
+    [9, 7, 5, 9, 2, 7, 9, 0, 2, 2, 11, 7, -1, -1, -1, -1],
+    [7, 5, 2, 7, 2, 11, 5, 9, 2, 3, 2, 8, 9, 8, 2, -1],
+    [2, 5, 10, 2, 3, 5, 3, 7, 5, -1, -1, -1, -1, -1, -1, -1],
+    [8, 2, 0, 8, 5, 2, 8, 7, 5, 10, 2, 5, -1, -1, -1, -1],
+    [9, 0, 1, 5, 10, 3, 5, 3, 7, 3, 10, 2, -1, -1, -1, -1],
+    [9, 8, 2, 9, 2, 1, 8, 7, 2, 10, 2, 5, 7, 5, 2, -1],
+    [1, 3, 5, 3, 7, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 8, 7, 0, 7, 1, 1, 7, 5, -1, -1, -1, -1, -1, -1, -1],
+    [9, 0, 3, 9, 3, 5, 5, 3, 7, -1, -1, -1, -1, -1, -1, -1],
+    [9, 8, 7, 5, 9, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [5, 8, 4, 5, 10, 8, 10, 11, 8, -1, -1, -1, -1, -1, -1, -1],
+    [5, 0, 4, 5, 11, 0, 5, 10, 11, 11, 3, 0, -1, -1, -1, -1],
+    [0, 1, 9, 8, 4, 10, 8, 10, 11, 10, 4, 5, -1, -1, -1, -1],
+    [10, 11, 4, 10, 4, 5, 11, 3, 4, 9, 4, 1, 3, 1, 4, -1],
+    [2, 5, 1, 2, 8, 5, 2, 11, 8, 4, 5, 8, -1, -1, -1, -1],
+    [0, 4, 11, 0, 11, 3, 4, 5, 11, 2, 11, 1, 5, 1, 11, -1],
+    [0, 2, 5, 0, 5, 9, 2, 11, 5, 4, 5, 8, 11, 8, 5, -1],
+    [9, 4, 5, 2, 11, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 5, 10, 3, 5, 2, 3, 4, 5, 3, 8, 4, -1, -1, -1, -1],
+    [5, 10, 2, 5, 2, 4, 4, 2, 0, -1, -1, -1, -1, -1, -1, -1],
+    [3, 10, 2, 3, 5, 10, 3, 8, 5, 4, 5, 8, 0, 1, 9, -1],
+    [5, 10, 2, 5, 2, 4, 1, 9, 2, 9, 4, 2, -1, -1, -1, -1],
+    [8, 4, 5, 8, 5, 3, 3, 5, 1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 5, 1, 0, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 4, 5, 8, 5, 3, 9, 0, 5, 0, 3, 5, -1, -1, -1, -1],
+    [9, 4, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 11, 7, 4, 9, 11, 9, 10, 11, -1, -1, -1, -1, -1, -1, -1],
+    [0, 8, 3, 4, 9, 7, 9, 11, 7, 9, 10, 11, -1, -1, -1, -1],
+    [1, 10, 11, 1, 11, 4, 1, 4, 0, 7, 4, 11, -1, -1, -1, -1],
+    [3, 1, 4, 3, 4, 8, 1, 10, 4, 7, 4, 11, 10, 11, 4, -1],
+    [4, 11, 7, 9, 11, 4, 9, 2, 11, 9, 1, 2, -1, -1, -1, -1],
+    [9, 7, 4, 9, 11, 7, 9, 1, 11, 2, 11, 1, 0, 8, 3, -1],
+    [11, 7, 4, 11, 4, 2, 2, 4, 0, -1, -1, -1, -1, -1, -1, -1],
+    [11, 7, 4, 11, 4, 2, 8, 3, 4, 3, 2, 4, -1, -1, -1, -1],
+    [2, 9, 10, 2, 7, 9, 2, 3, 7, 7, 4, 9, -1, -1, -1, -1],
+    [9, 10, 7, 9, 7, 4, 10, 2, 7, 8, 7, 0, 2, 0, 7, -1],
+    [3, 7, 10, 3, 10, 2, 7, 4, 10, 1, 10, 0, 4, 0, 10, -1],
+    [1, 10, 2, 8, 7, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 9, 1, 4, 1, 7, 7, 1, 3, -1, -1, -1, -1, -1, -1, -1],
+    [4, 9, 1, 4, 1, 7, 0, 8, 1, 8, 7, 1, -1, -1, -1, -1],
+    [4, 0, 3, 7, 4, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 8, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 10, 8, 10, 11, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 0, 9, 3, 9, 11, 11, 9, 10, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 10, 0, 10, 8, 8, 10, 11, -1, -1, -1, -1, -1, -1, -1],
+    [3, 1, 10, 11, 3, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 2, 11, 1, 11, 9, 9, 11, 8, -1, -1, -1, -1, -1, -1, -1],
+    [3, 0, 9, 3, 9, 11, 1, 2, 9, 2, 11, 9, -1, -1, -1, -1],
+    [0, 2, 11, 8, 0, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 2, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 3, 8, 2, 8, 10, 10, 8, 9, -1, -1, -1, -1, -1, -1, -1],
+    [9, 10, 2, 0, 9, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 3, 8, 2, 8, 10, 0, 1, 8, 1, 10, 8, -1, -1, -1, -1],
+    [1, 10, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 3, 8, 9, 1, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 9, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 3, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+    ],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::build_query_window;
+    use crate::types::{test_scan, StoredVoxel};
+
+    fn voxel(row: u16, col: u16, level_idx: u8, dbz_tenths: i16) -> StoredVoxel {
+        StoredVoxel {
+            row,
+            col,
+            level_idx,
+            phase: 0,
+            surface_phase: 0,
+            dbz_tenths,
+        }
+    }
+
+    #[test]
+    fn single_voxel_above_threshold_forms_closed_octahedron() {
+        let scan = test_scan(10, 10, 3, vec![voxel(5, 5, 1, 400)]);
+        let window = build_query_window(&scan, 39.95, -104.95, 30.0, 60.0);
+        let mesh = build_isosurface_mesh(&scan, &window).expect("mesh within limits");
+        assert_eq!(mesh.source_voxel_count, 1);
+        assert_eq!(mesh.indices.len(), 8 * 3);
+        assert_eq!(mesh.vertices.len(), 6);
+        for (_, _, altitude_feet) in &mesh.vertices {
+            assert!((2000.0..=4000.0).contains(altitude_feet));
+        }
+    }
+
+    #[test]
+    fn voxels_below_threshold_produce_empty_mesh() {
+        let scan = test_scan(10, 10, 3, vec![voxel(5, 5, 1, 200), voxel(5, 6, 1, 250)]);
+        let window = build_query_window(&scan, 39.95, -104.95, 30.0, 60.0);
+        let mesh = build_isosurface_mesh(&scan, &window).expect("mesh within limits");
+        assert_eq!(mesh.source_voxel_count, 0);
+        assert!(mesh.vertices.is_empty());
+        assert!(mesh.indices.is_empty());
+    }
+
+    #[test]
+    fn oversized_windows_are_rejected() {
+        let scan = test_scan(10, 10, 3, vec![voxel(5, 5, 1, 400), voxel(5, 6, 1, 200)]);
+        let window = build_query_window(&scan, 39.95, -104.95, 30.0, 60.0);
+        assert_eq!(
+            build_bounded_mesh(&scan, &window, 1, 100).unwrap_err(),
+            MeshTooLarge::Samples(1)
+        );
+        assert_eq!(
+            build_bounded_mesh(&scan, &window, 2, 7).unwrap_err(),
+            MeshTooLarge::Triangles(7)
+        );
+        assert!(build_bounded_mesh(&scan, &window, 2, 8).is_ok());
+    }
+
+    #[test]
+    fn encoded_mesh_length_matches_header_counts() {
+        let scan = test_scan(10, 10, 3, vec![voxel(5, 5, 0, 400), voxel(5, 6, 0, 450)]);
+        let window = build_query_window(&scan, 39.95, -104.95, 30.0, 60.0);
+        let mesh = build_isosurface_mesh(&scan, &window).expect("mesh within limits");
+        let body = encode_isosurface_wire(&scan, &window, &mesh);
+
+        assert_eq!(&body[0..4], &MESH_MAGIC);
+        let vertex_count = u32::from_le_bytes(body[8..12].try_into().unwrap()) as usize;
+        let triangle_count = u32::from_le_bytes(body[12..16].try_into().unwrap()) as usize;
+        let index_bytes = u16::from_le_bytes(body[18..20].try_into().unwrap()) as usize;
+        assert_eq!(vertex_count, mesh.vertices.len());
+        assert_eq!(index_bytes, 2);
+        assert_eq!(
+            body.len(),
+            MESH_HEADER_BYTES + vertex_count * MESH_VERTEX_BYTES + triangle_count * 3 * index_bytes
+        );
+    }
+}
@@ -1,94 +1,280 @@
+mod access;
+mod admin_api;
 mod api;
+mod availability;
+mod backfill;
+mod coalesce;
 mod config;
 mod constants;
+mod dead_letter;
 mod discovery;
+mod domain;
 mod grib;
 mod http_client;
 mod ingest;
+mod ingest_policy;
+mod isosurface;
+mod metrics;
+mod query_shape;
+mod readiness;
+mod s3_listing;
+mod shutdown;
+mod source;
 mod storage;
+mod telemetry;
 mod traffic_api;
+mod traffic_poller;
+mod traffic_stream;
 mod types;
 mod utils;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use axum::routing::get;
-use axum::Router;
+use anyhow::{bail, Context, Result};
+use axum::http::HeaderValue;
+use axum::middleware;
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::{Extension, Router};
+use futures::future::join_all;
 use reqwest::Client;
 use tokio::fs;
-use tokio::sync::{Mutex, RwLock};
 use tower_http::compression::CompressionLayer;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::trace::TraceLayer;
-use tracing::{info, warn};
+use tracing::{error, info, warn, Span};
 
-use crate::api::{echo_tops, healthz, meta, volume};
+use crate::access::{enforce_access, AccessControl};
+use crate::admin_api::{admin_config, admin_ingest, admin_ingest_retry};
+use crate::api::{echo_tops, healthz, isosurface, meta, volume};
+use crate::backfill::{parse_backfill_args, run_backfill};
 use crate::config::Config;
+use crate::discovery::discover_level_tags;
 use crate::ingest::{enqueue_latest_from_s3, spawn_background_workers};
-use crate::storage::load_latest_snapshot;
-use crate::traffic_api::traffic_adsbx;
-use crate::types::AppState;
-use crate::utils::init_tracing;
+use crate::metrics::{metrics, track_http_metrics};
+use crate::readiness::{readyz, RuntimeHealth};
+use crate::shutdown::{wait_for_os_signal, Shutdown};
+use crate::source::{IngestSource, LocalDirSource, S3HttpSource};
+use crate::storage::{load_latest_snapshot, persist_pending_queue, take_pending_queue};
+use crate::telemetry::{http_request_span, init_tracing};
+use crate::traffic_api::{traffic_adsbx, TrafficCache};
+use crate::traffic_poller::{spawn_traffic_poller, TrafficStore};
+use crate::traffic_stream::traffic_stream;
+use crate::types::{AppState, DomainState};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    init_tracing();
-
-    let cfg = Arc::new(Config::from_env()?);
-    fs::create_dir_all(cfg.scans_dir())
-        .await
-        .with_context(|| format!("Failed to create {}", cfg.scans_dir().display()))?;
+    let telemetry = init_tracing()?;
+    let result = run().await;
+    telemetry.shutdown();
+    result
+}
 
+async fn run() -> Result<()> {
+    let cfg = Config::from_env()?;
     let http = Client::builder()
         .timeout(cfg.request_timeout)
         .user_agent("approach-viz-runtime-rs/1.0")
         .build()
         .context("Failed to build reqwest client")?;
 
-    let latest = Arc::new(RwLock::new(load_latest_snapshot(&cfg).await?));
+    let source: Arc<dyn IngestSource> = match cfg.source_dir.clone() {
+        Some(root) => Arc::new(LocalDirSource::new(root)),
+        None => Arc::new(S3HttpSource::new(
+            http.clone(),
+            &cfg.mrms_bucket_url,
+            cfg.mrms_list_style,
+            cfg.listing_cache_ttl,
+        )),
+    };
+    info!("Reading MRMS objects from {}", source.describe());
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => {}
+        Some("backfill") => {
+            return run_backfill(cfg, http, source, parse_backfill_args(&args[1..])?).await;
+        }
+        Some(other) => bail!("Unknown subcommand '{other}' (expected: backfill)"),
+    }
+
+    let cfg = Arc::new(cfg);
+    fs::create_dir_all(cfg.scans_dir())
+        .await
+        .with_context(|| format!("Failed to create {}", cfg.scans_dir().display()))?;
+
+    let mut domains = BTreeMap::new();
+    for domain in &cfg.mrms_domains {
+        let latest = load_latest_snapshot(&cfg, *domain).await?;
+        let domain_state = DomainState::new(*domain, latest);
+        discover_level_tags(source.as_ref(), &domain_state).await;
+        restore_pending_queue(&cfg, &domain_state).await;
+        domains.insert(*domain, Arc::new(domain_state));
+    }
     let state = AppState {
         cfg: cfg.clone(),
         http: http.clone(),
-        latest,
-        pending: Arc::new(Mutex::new(HashMap::new())),
-        recent_timestamps: Arc::new(Mutex::new(HashSet::new())),
+        source,
+        domains: Arc::new(domains),
+        health: Arc::new(RuntimeHealth::default()),
+        traffic_cache: Arc::new(TrafficCache::default()),
+        traffic_store: Arc::new(TrafficStore::default()),
     };
 
-    if state.latest.read().await.is_none() {
-        if let Err(error) = enqueue_latest_from_s3(&state).await {
-            warn!("Initial S3 bootstrap enqueue failed: {error:#}");
+    for domain_state in state.domains.values() {
+        if domain_state.latest.read().await.is_none() {
+            if let Err(error) = enqueue_latest_from_s3(&state, domain_state).await {
+                warn!(
+                    "Initial S3 bootstrap enqueue failed for {}: {error:#}",
+                    domain_state.domain.id()
+                );
+            }
         }
     }
 
-    spawn_background_workers(state.clone()).await?;
+    let shutdown = Shutdown::new(cfg.shutdown_grace);
+    let mut workers = spawn_background_workers(state.clone(), shutdown.signal()).await?;
+    workers.extend(spawn_traffic_poller(state.clone(), shutdown.signal()));
 
     let app = Router::new()
         .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics))
         .route("/v1/meta", get(meta))
         .route("/v1/weather/volume", get(volume))
         .route("/v1/weather/echo-tops", get(echo_tops))
+        .route("/v1/weather/isosurface", get(isosurface))
         .route("/v1/volume", get(volume))
         .route("/v1/echo-tops", get(echo_tops))
         .route("/v1/traffic/adsbx", get(traffic_adsbx))
+        .route(
+            "/v1/traffic/stream",
+            get(traffic_stream).layer(Extension(shutdown.signal())),
+        )
+        .route("/v1/admin/config", get(admin_config))
+        .route("/v1/admin/ingest", get(admin_ingest))
+        .route("/v1/admin/ingest/retry", post(admin_ingest_retry))
+        .layer(middleware::from_fn_with_state(
+            AccessControl::new(&cfg),
+            enforce_access,
+        ))
+        .layer(middleware::from_fn(track_http_metrics))
         .layer(CompressionLayer::new())
-        .layer(TraceLayer::new_for_http())
         .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
+            TraceLayer::new_for_http()
+                .make_span_with(http_request_span)
+                .on_response(|response: &Response, _latency: Duration, span: &Span| {
+                    span.record("http.response.status_code", response.status().as_u16());
+                }),
         )
-        .with_state(state);
+        .layer(cors_layer(&cfg)?)
+        .with_state(state.clone());
 
     let listener = tokio::net::TcpListener::bind(&cfg.listen_addr)
         .await
         .with_context(|| format!("Failed to bind {}", cfg.listen_addr))?;
 
     info!("Runtime rust service listening on {}", cfg.listen_addr);
-    axum::serve(listener, app)
+    let mut drain_signal = shutdown.signal();
+    let mut server = tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move { drain_signal.triggered().await })
         .await
-        .context("HTTP server failed")?;
+    });
+
+    tokio::select! {
+        result = &mut server => {
+            result.context("HTTP server task panicked")?.context("HTTP server failed")?;
+            bail!("HTTP server stopped unexpectedly");
+        }
+        () = wait_for_os_signal() => {}
+    }
+
+    info!(
+        "Shutting down; waiting up to {}s for in-flight work",
+        cfg.shutdown_grace.as_secs()
+    );
+    shutdown.trigger();
+    match tokio::time::timeout(cfg.shutdown_grace, &mut server).await {
+        Ok(Ok(Err(error))) => warn!("HTTP server failed while draining: {error}"),
+        Ok(_) => {}
+        Err(_) => {
+            warn!("HTTP connections still open after the grace period; closing them");
+            server.abort();
+        }
+    }
+    // Schedulers return within the grace period, requeueing an unfinished
+    // ingest, so the queue is complete once they have all stopped.
+    join_all(workers).await;
+
+    for domain_state in state.domains.values() {
+        let pending = domain_state.pending.lock().await;
+        match persist_pending_queue(&cfg, domain_state.domain, &pending).await {
+            Ok(()) => info!(
+                "Saved {} pending {} ingests",
+                pending.len(),
+                domain_state.domain.id()
+            ),
+            Err(error) => error!(
+                "Failed to save pending {} ingests: {error:#}",
+                domain_state.domain.id()
+            ),
+        }
+    }
     Ok(())
 }
+
+/// Outermost layer, so `401`/`429` responses carry CORS headers too.
+fn cors_layer(cfg: &Config) -> Result<CorsLayer> {
+    let layer = CorsLayer::new().allow_methods(Any).allow_headers(Any);
+    if cfg.cors_allowed_origins.iter().any(|origin| origin == "*") {
+        return Ok(layer.allow_origin(Any));
+    }
+    let origins = cfg
+        .cors_allowed_origins
+        .iter()
+        .map(|origin| {
+            HeaderValue::from_str(origin).with_context(|| format!("Invalid CORS origin '{origin}'"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(layer.allow_origin(AllowOrigin::list(origins)))
+}
+
+/// Reloads the queue saved at the last shutdown, dropping timestamps the
+/// loaded snapshot already covers (upgrades excepted).
+async fn restore_pending_queue(cfg: &Config, domain_state: &DomainState) {
+    let domain = domain_state.domain;
+    let restored = match take_pending_queue(cfg, domain).await {
+        Ok(restored) => restored,
+        Err(error) => {
+            warn!(
+                "Discarding saved pending {} ingests: {error:#}",
+                domain.id()
+            );
+            return;
+        }
+    };
+    if restored.is_empty() {
+        return;
+    }
+
+    let latest = domain_state
+        .latest
+        .read()
+        .await
+        .as_ref()
+        .map(|scan| scan.timestamp.clone());
+    let mut pending = domain_state.pending.lock().await;
+    for (timestamp, entry) in restored {
+        if entry.upgrade || latest.as_ref().is_none_or(|latest| &timestamp > latest) {
+            pending.insert(timestamp, entry);
+        }
+    }
+    info!("Restored {} pending {} ingests", pending.len(), domain.id());
+}
@@ -0,0 +1,332 @@
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use axum::body::HttpBody;
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use prometheus::{
+    exponential_buckets, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use tracing::warn;
+
+use crate::coalesce::CacheOutcome;
+use crate::domain::MrmsDomain;
+use crate::types::AppState;
+
+const METRIC_PREFIX: &str = "approach_viz_runtime";
+
+/// Process-wide metrics; ingest, storage and traffic code record into it
+/// directly instead of threading a handle through every call.
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Ingest stages timed by `approach_viz_runtime_ingest_stage_seconds`.
+#[derive(Clone, Copy, Debug)]
+pub enum IngestStage {
+    /// One object read from the ingest source.
+    Fetch,
+    /// One GRIB2 object decoded.
+    GribDecode,
+    /// Phase resolution and voxel build for a whole scan.
+    Phase,
+    /// Snapshot encode, write and retention.
+    Persist,
+}
+
+impl IngestStage {
+    fn as_str(self) -> &'static str {
+        match self {
+            IngestStage::Fetch => "fetch",
+            IngestStage::GribDecode => "grib_decode",
+            IngestStage::Phase => "phase",
+            IngestStage::Persist => "persist",
+        }
+    }
+}
+
+pub struct Metrics {
+    registry: Registry,
+    ingest_stage_seconds: HistogramVec,
+    ingests: IntCounterVec,
+    pending_queue: IntGaugeVec,
+    dead_letters: IntGaugeVec,
+    pub sqs_messages_received: IntCounter,
+    pub sqs_messages_deleted: IntCounter,
+    snapshot_bytes: IntGaugeVec,
+    retention_prunes: IntCounterVec,
+    http_request_seconds: HistogramVec,
+    http_response_bytes: HistogramVec,
+    traffic_upstream_failures: IntCounterVec,
+    traffic_cache_requests: IntCounterVec,
+    pub traffic_tracked_aircraft: IntGauge,
+    pub traffic_stream_connections: IntGauge,
+    phase_voxels: IntGaugeVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let name = |suffix: &str| format!("{METRIC_PREFIX}_{suffix}");
+
+        let ingest_stage_seconds = HistogramVec::new(
+            HistogramOpts::new(name("ingest_stage_seconds"), "Ingest stage duration")
+                .buckets(exponential_buckets(0.01, 2.0, 14).expect("valid buckets")),
+            &["domain", "stage"],
+        )
+        .expect("valid metric");
+        let ingests = IntCounterVec::new(
+            Opts::new(name("ingests_total"), "Ingest attempts by result"),
+            &["domain", "result"],
+        )
+        .expect("valid metric");
+        let pending_queue = IntGaugeVec::new(
+            Opts::new(
+                name("pending_queue_size"),
+                "Timestamps waiting to be ingested",
+            ),
+            &["domain"],
+        )
+        .expect("valid metric");
+        let dead_letters = IntGaugeVec::new(
+            Opts::new(name("dead_letters"), "Dead-lettered ingest timestamps"),
+            &["domain"],
+        )
+        .expect("valid metric");
+        let sqs_messages_received =
+            IntCounter::new(name("sqs_messages_received_total"), "SQS messages received")
+                .expect("valid metric");
+        let sqs_messages_deleted =
+            IntCounter::new(name("sqs_messages_deleted_total"), "SQS messages deleted")
+                .expect("valid metric");
+        let snapshot_bytes = IntGaugeVec::new(
+            Opts::new(
+                name("snapshot_bytes"),
+                "Snapshot bytes on disk after retention",
+            ),
+            &["domain"],
+        )
+        .expect("valid metric");
+        let retention_prunes = IntCounterVec::new(
+            Opts::new(
+                name("retention_prunes_total"),
+                "Snapshots removed by retention",
+            ),
+            &["domain"],
+        )
+        .expect("valid metric");
+        let http_request_seconds = HistogramVec::new(
+            HistogramOpts::new(name("http_request_seconds"), "HTTP request latency")
+                .buckets(exponential_buckets(0.001, 2.0, 14).expect("valid buckets")),
+            &["endpoint", "method", "status"],
+        )
+        .expect("valid metric");
+        let http_response_bytes = HistogramVec::new(
+            HistogramOpts::new(
+                name("http_response_bytes"),
+                "HTTP response body bytes before compression",
+            )
+            .buckets(exponential_buckets(256.0, 4.0, 10).expect("valid buckets")),
+            &["endpoint"],
+        )
+        .expect("valid metric");
+        let traffic_upstream_failures = IntCounterVec::new(
+            Opts::new(
+                name("traffic_upstream_failures_total"),
+                "Failed tar1090 upstream requests",
+            ),
+            &["base_url", "kind"],
+        )
+        .expect("valid metric");
+        let traffic_cache_requests = IntCounterVec::new(
+            Opts::new(
+                name("traffic_cache_requests_total"),
+                "Traffic upstream lookups by cache outcome",
+            ),
+            &["kind", "outcome"],
+        )
+        .expect("valid metric");
+        let traffic_tracked_aircraft = IntGauge::new(
+            name("traffic_tracked_aircraft"),
+            "Aircraft held by the background traffic poller",
+        )
+        .expect("valid metric");
+        let traffic_stream_connections = IntGauge::new(
+            name("traffic_stream_connections"),
+            "Open /v1/traffic/stream WebSocket connections",
+        )
+        .expect("valid metric");
+        let phase_voxels = IntGaugeVec::new(
+            Opts::new(
+                name("phase_voxels"),
+                "phaseDetail voxel counters of the last ingested scan",
+            ),
+            &["domain", "counter"],
+        )
+        .expect("valid metric");
+
+        for collector in [
+            Box::new(ingest_stage_seconds.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(ingests.clone()),
+            Box::new(pending_queue.clone()),
+            Box::new(dead_letters.clone()),
+            Box::new(sqs_messages_received.clone()),
+            Box::new(sqs_messages_deleted.clone()),
+            Box::new(snapshot_bytes.clone()),
+            Box::new(retention_prunes.clone()),
+            Box::new(http_request_seconds.clone()),
+            Box::new(http_response_bytes.clone()),
+            Box::new(traffic_upstream_failures.clone()),
+            Box::new(traffic_cache_requests.clone()),
+            Box::new(traffic_tracked_aircraft.clone()),
+            Box::new(traffic_stream_connections.clone()),
+            Box::new(phase_voxels.clone()),
+        ] {
+            registry.register(collector).expect("unique metric names");
+        }
+
+        Self {
+            registry,
+            ingest_stage_seconds,
+            ingests,
+            pending_queue,
+            dead_letters,
+            sqs_messages_received,
+            sqs_messages_deleted,
+            snapshot_bytes,
+            retention_prunes,
+            http_request_seconds,
+            http_response_bytes,
+            traffic_upstream_failures,
+            traffic_cache_requests,
+            traffic_tracked_aircraft,
+            traffic_stream_connections,
+            phase_voxels,
+        }
+    }
+
+    pub fn observe_stage(&self, domain: MrmsDomain, stage: IngestStage, started: Instant) {
+        self.ingest_stage_seconds
+            .with_label_values(&[domain.id(), stage.as_str()])
+            .observe(started.elapsed().as_secs_f64());
+    }
+
+    pub fn record_ingest(&self, domain: MrmsDomain, ok: bool) {
+        self.ingests
+            .with_label_values(&[domain.id(), if ok { "ok" } else { "error" }])
+            .inc();
+    }
+
+    pub fn set_snapshot_bytes(&self, domain: MrmsDomain, bytes: u64) {
+        self.snapshot_bytes
+            .with_label_values(&[domain.id()])
+            .set(bytes.min(i64::MAX as u64) as i64);
+    }
+
+    pub fn record_retention_prune(&self, domain: MrmsDomain) {
+        self.retention_prunes
+            .with_label_values(&[domain.id()])
+            .inc();
+    }
+
+    /// `kind` is `bincraft` or `trace`.
+    pub fn record_traffic_upstream_failure(&self, base_url: &str, kind: &str) {
+        self.traffic_upstream_failures
+            .with_label_values(&[base_url, kind])
+            .inc();
+    }
+
+    /// `kind` is `bincraft` or `trace`.
+    pub fn record_traffic_cache(&self, kind: &str, outcome: CacheOutcome) {
+        self.traffic_cache_requests
+            .with_label_values(&[kind, outcome.as_str()])
+            .inc();
+    }
+
+    pub fn set_phase_voxels(&self, domain: MrmsDomain, counters: &[(&str, u64)]) {
+        for (counter, value) in counters {
+            self.phase_voxels
+                .with_label_values(&[domain.id(), *counter])
+                .set((*value).min(i64::MAX as u64) as i64);
+        }
+    }
+
+    fn encode(&self) -> prometheus::Result<String> {
+        TextEncoder::new().encode_to_string(&self.registry.gather())
+    }
+}
+
+/// `GET /metrics` in the Prometheus text format. Queue gauges are sampled at
+/// scrape time.
+pub async fn metrics(State(state): State<AppState>) -> Response {
+    for domain_state in state.domains.values() {
+        let domain = domain_state.domain.id();
+        let pending = domain_state.pending.lock().await.len();
+        METRICS
+            .pending_queue
+            .with_label_values(&[domain])
+            .set(pending as i64);
+        METRICS
+            .dead_letters
+            .with_label_values(&[domain])
+            .set(domain_state.dead_letters.len() as i64);
+    }
+
+    match METRICS.encode() {
+        Ok(body) => ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body).into_response(),
+        Err(error) => {
+            warn!("Failed to encode metrics: {error}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Records latency and body size per matched route; unmatched paths share
+/// one label so scanners cannot blow up label cardinality.
+pub async fn track_http_metrics(request: Request, next: Next) -> Response {
+    let endpoint = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().clone();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    METRICS
+        .http_request_seconds
+        .with_label_values(&[
+            endpoint.as_str(),
+            method.as_str(),
+            response.status().as_str(),
+        ])
+        .observe(started.elapsed().as_secs_f64());
+    if let Some(bytes) = response.body().size_hint().exact() {
+        METRICS
+            .http_response_bytes
+            .with_label_values(&[endpoint.as_str()])
+            .observe(bytes as f64);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_metrics_include_recorded_series() {
+        METRICS.record_ingest(MrmsDomain::Conus, true);
+        METRICS.set_phase_voxels(MrmsDomain::Conus, &[("thermo_signal_voxels", 42)]);
+        METRICS.record_traffic_upstream_failure("https://example.test", "bincraft");
+
+        let body = METRICS.encode().expect("encodes");
+        assert!(body.contains("approach_viz_runtime_ingests_total{domain=\"conus\",result=\"ok\"}"));
+        assert!(body.contains(
+            "approach_viz_runtime_phase_voxels{counter=\"thermo_signal_voxels\",domain=\"conus\"} 42"
+        ));
+        assert!(body.contains("approach_viz_runtime_traffic_upstream_failures_total{base_url=\"https://example.test\",kind=\"bincraft\"} 1"));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::constants::{
+    DEFAULT_CORRIDOR_HALF_WIDTH_NM, MAX_CORRIDOR_HALF_WIDTH_NM, MAX_CORRIDOR_POINTS,
+    MIN_CORRIDOR_HALF_WIDTH_NM,
+};
+use crate::utils::{clamp, projection_scales_nm_per_degree, shortest_lon_delta_degrees, to_lon360};
+
+/// Geographic query shape as requested by the client, before projection.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum QueryShape {
+    Radius {
+        max_range_nm: f64,
+    },
+    BoundingBox {
+        south_lat: f64,
+        west_lon: f64,
+        north_lat: f64,
+        east_lon: f64,
+    },
+    /// Polyline of `(lat, lon)` vertices buffered by `half_width_nm` on each side.
+    Corridor {
+        points: Vec<(f64, f64)>,
+        half_width_nm: f64,
+    },
+}
+
+/// Query shape in the x/z NM frame around the query origin (x east, z south).
+#[derive(Clone, Debug)]
+pub(crate) enum QueryRegion {
+    Radius {
+        max_range_squared_nm: f64,
+    },
+    Rect {
+        x_min_nm: f64,
+        x_max_nm: f64,
+        z_min_nm: f64,
+        z_max_nm: f64,
+    },
+    Corridor {
+        vertices_nm: Vec<(f64, f64)>,
+        half_width_squared_nm: f64,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ProjectedShape {
+    pub(crate) origin_lat: f64,
+    pub(crate) origin_lon: f64,
+    pub(crate) origin_lon360: f64,
+    pub(crate) east_nm_per_lon_deg_safe: f64,
+    pub(crate) north_nm_per_lat_deg_safe: f64,
+    pub(crate) region: QueryRegion,
+    /// Axis-aligned x/z NM bounds used to derive the tile/row/col window.
+    pub(crate) x_min_nm: f64,
+    pub(crate) x_max_nm: f64,
+    pub(crate) z_min_nm: f64,
+    pub(crate) z_max_nm: f64,
+    /// Farthest extent from the origin; reported as the wire header range.
+    pub(crate) bounding_radius_nm: f64,
+}
+
+impl QueryShape {
+    pub(crate) fn project(&self, origin_lat: f64, origin_lon: f64) -> ProjectedShape {
+        let origin_lon360 = to_lon360(origin_lon);
+        let (east_nm_per_lon_deg, north_nm_per_lat_deg) =
+            projection_scales_nm_per_degree(origin_lat);
+        let east_nm_per_lon_deg_safe = east_nm_per_lon_deg.abs().max(1e-6);
+        let north_nm_per_lat_deg_safe = north_nm_per_lat_deg.abs().max(1e-6);
+        let project_point = |lat: f64, lon: f64| {
+            let delta_lon_deg = shortest_lon_delta_degrees(to_lon360(lon), origin_lon360);
+            (
+                delta_lon_deg * east_nm_per_lon_deg_safe,
+                -(lat - origin_lat) * north_nm_per_lat_deg_safe,
+            )
+        };
+
+        let (region, x_min_nm, x_max_nm, z_min_nm, z_max_nm) = match self {
+            QueryShape::Radius { max_range_nm } => (
+                QueryRegion::Radius {
+                    max_range_squared_nm: max_range_nm * max_range_nm,
+                },
+                -max_range_nm,
+                *max_range_nm,
+                -max_range_nm,
+                *max_range_nm,
+            ),
+            QueryShape::BoundingBox {
+                south_lat,
+                west_lon,
+                north_lat,
+                east_lon,
+            } => {
+                // East is measured from west so boxes may cross the antimeridian.
+                let (x_west_nm, z_north_nm) = project_point(*north_lat, *west_lon);
+                let span_lon_deg = (east_lon - west_lon).rem_euclid(360.0);
+                let x_east_nm = x_west_nm + span_lon_deg * east_nm_per_lon_deg_safe;
+                let z_south_nm = -(south_lat - origin_lat) * north_nm_per_lat_deg_safe;
+                (
+                    QueryRegion::Rect {
+                        x_min_nm: x_west_nm,
+                        x_max_nm: x_east_nm,
+                        z_min_nm: z_north_nm,
+                        z_max_nm: z_south_nm,
+                    },
+                    x_west_nm,
+                    x_east_nm,
+                    z_north_nm,
+                    z_south_nm,
+                )
+            }
+            QueryShape::Corridor {
+                points,
+                half_width_nm,
+            } => {
+                let vertices_nm: Vec<(f64, f64)> = points
+                    .iter()
+                    .map(|(lat, lon)| project_point(*lat, *lon))
+                    .collect();
+                let mut x_min_nm = f64::INFINITY;
+                let mut x_max_nm = f64::NEG_INFINITY;
+                let mut z_min_nm = f64::INFINITY;
+                let mut z_max_nm = f64::NEG_INFINITY;
+                for (x_nm, z_nm) in &vertices_nm {
+                    x_min_nm = x_min_nm.min(*x_nm);
+                    x_max_nm = x_max_nm.max(*x_nm);
+                    z_min_nm = z_min_nm.min(*z_nm);
+                    z_max_nm = z_max_nm.max(*z_nm);
+                }
+                (
+                    QueryRegion::Corridor {
+                        vertices_nm,
+                        half_width_squared_nm: half_width_nm * half_width_nm,
+                    },
+                    x_min_nm - half_width_nm,
+                    x_max_nm + half_width_nm,
+                    z_min_nm - half_width_nm,
+                    z_max_nm + half_width_nm,
+                )
+            }
+        };
+
+        let bounding_radius_nm = match self {
+            QueryShape::Radius { max_range_nm } => *max_range_nm,
+            _ => {
+                let far_x_nm = x_min_nm.abs().max(x_max_nm.abs());
+                let far_z_nm = z_min_nm.abs().max(z_max_nm.abs());
+                far_x_nm.hypot(far_z_nm)
+            }
+        };
+
+        ProjectedShape {
+            origin_lat,
+            origin_lon,
+            origin_lon360,
+            east_nm_per_lon_deg_safe,
+            north_nm_per_lat_deg_safe,
+            region,
+            x_min_nm,
+            x_max_nm,
+            z_min_nm,
+            z_max_nm,
+            bounding_radius_nm,
+        }
+    }
+}
+
+impl QueryRegion {
+    pub(crate) fn contains_nm(&self, x_nm: f64, z_nm: f64) -> bool {
+        match self {
+            QueryRegion::Radius {
+                max_range_squared_nm,
+            } => x_nm * x_nm + z_nm * z_nm <= *max_range_squared_nm,
+            QueryRegion::Rect {
+                x_min_nm,
+                x_max_nm,
+                z_min_nm,
+                z_max_nm,
+            } => x_nm >= *x_min_nm && x_nm <= *x_max_nm && z_nm >= *z_min_nm && z_nm <= *z_max_nm,
+            QueryRegion::Corridor {
+                vertices_nm,
+                half_width_squared_nm,
+            } => vertices_nm.windows(2).any(|segment| {
+                distance_squared_to_segment_nm((x_nm, z_nm), segment[0], segment[1])
+                    <= *half_width_squared_nm
+            }),
+        }
+    }
+}
+
+fn distance_squared_to_segment_nm(point: (f64, f64), start: (f64, f64), end: (f64, f64)) -> f64 {
+    let (dx, dz) = (end.0 - start.0, end.1 - start.1);
+    let length_squared = dx * dx + dz * dz;
+    let t = if length_squared <= f64::EPSILON {
+        0.0
+    } else {
+        clamp(
+            ((point.0 - start.0) * dx + (point.1 - start.1) * dz) / length_squared,
+            0.0,
+            1.0,
+        )
+    };
+    let (nearest_x, nearest_z) = (start.0 + t * dx, start.1 + t * dz);
+    let (offset_x, offset_z) = (point.0 - nearest_x, point.1 - nearest_z);
+    offset_x * offset_x + offset_z * offset_z
+}
+
+fn parse_lat_lon(lat: &str, lon: &str) -> Option<(f64, f64)> {
+    let lat = lat.trim().parse::<f64>().ok()?;
+    let lon = lon.trim().parse::<f64>().ok()?;
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return None;
+    }
+    Some((lat, lon))
+}
+
+/// Resolves `bbox=south,west,north,east` or `corridor=lat,lon;lat,lon;...`
+/// (buffered by `corridorNm`), falling back to the radius around the origin.
+pub(crate) fn parse_query_shape(
+    bbox: Option<&str>,
+    corridor: Option<&str>,
+    corridor_nm: Option<f64>,
+    max_range_nm: f64,
+) -> Result<QueryShape, String> {
+    match (bbox, corridor) {
+        (Some(_), Some(_)) => Err("Specify either bbox or corridor, not both.".to_string()),
+        (Some(raw), None) => {
+            let parts: Vec<&str> = raw.split(',').collect();
+            let [south, west, north, east] = parts.as_slice() else {
+                return Err("bbox must be south,west,north,east in decimal degrees.".to_string());
+            };
+            let (Some((south_lat, west_lon)), Some((north_lat, east_lon))) =
+                (parse_lat_lon(south, west), parse_lat_lon(north, east))
+            else {
+                return Err("bbox must be south,west,north,east in decimal degrees.".to_string());
+            };
+            if south_lat >= north_lat || west_lon == east_lon {
+                return Err("bbox must have south < north and west != east.".to_string());
+            }
+            Ok(QueryShape::BoundingBox {
+                south_lat,
+                west_lon,
+                north_lat,
+                east_lon,
+            })
+        }
+        (None, Some(raw)) => {
+            let mut points = Vec::new();
+            for vertex in raw.split(';').filter(|vertex| !vertex.trim().is_empty()) {
+                let point = vertex
+                    .split_once(',')
+                    .and_then(|(lat, lon)| parse_lat_lon(lat, lon))
+                    .ok_or_else(|| format!("Invalid corridor vertex '{}'.", vertex.trim()))?;
+                points.push(point);
+            }
+            if points.len() < 2 || points.len() > MAX_CORRIDOR_POINTS {
+                return Err(format!(
+                    "corridor must list 2..={MAX_CORRIDOR_POINTS} lat,lon vertices separated by ';'."
+                ));
+            }
+            Ok(QueryShape::Corridor {
+                points,
+                half_width_nm: clamp(
+                    corridor_nm.unwrap_or(DEFAULT_CORRIDOR_HALF_WIDTH_NM),
+                    MIN_CORRIDOR_HALF_WIDTH_NM,
+                    MAX_CORRIDOR_HALF_WIDTH_NM,
+                ),
+            })
+        }
+        (None, None) => Ok(QueryShape::Radius { max_range_nm }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bbox_projects_to_rect_around_origin() {
+        let shape = parse_query_shape(Some("39.5,-75.5,40.5,-74.5"), None, None, 120.0)
+            .expect("valid bbox");
+        let projected = shape.project(40.0, -75.0);
+
+        assert!(projected.region.contains_nm(0.0, 0.0));
+        assert!(projected.region.contains_nm(20.0, -25.0));
+        assert!(!projected.region.contains_nm(0.0, 40.0));
+        assert!((projected.z_min_nm + 30.0).abs() < 0.5);
+        assert!((projected.z_max_nm - 30.0).abs() < 0.5);
+        assert!(projected.x_max_nm > 22.0 && projected.x_max_nm < 24.0);
+    }
+
+    #[test]
+    fn corridor_buffers_each_segment() {
+        let shape = parse_query_shape(None, Some("40,-75;40,-73"), Some(5.0), 120.0)
+            .expect("valid corridor");
+        let projected = shape.project(40.0, -75.0);
+
+        assert!(projected.region.contains_nm(45.0, 4.0));
+        assert!(!projected.region.contains_nm(45.0, 6.0));
+        assert!(!projected.region.contains_nm(-6.0, 0.0));
+        assert!(projected.bounding_radius_nm > 90.0);
+    }
+
+    #[test]
+    fn parse_query_shape_rejects_bad_input() {
+        assert!(parse_query_shape(Some("1,2,3"), None, None, 120.0).is_err());
+        assert!(parse_query_shape(Some("41,-75,40,-74"), None, None, 120.0).is_err());
+        assert!(parse_query_shape(None, Some("40,-75"), None, 120.0).is_err());
+        assert!(
+            parse_query_shape(Some("39,-75,40,-74"), Some("40,-75;41,-75"), None, 120.0).is_err()
+        );
+        assert_eq!(
+            parse_query_shape(None, None, None, 80.0),
+            Ok(QueryShape::Radius { max_range_nm: 80.0 })
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::types::AppState;
+
+/// Process-wide liveness signals that are not tied to one domain.
+#[derive(Default)]
+pub struct RuntimeHealth {
+    /// Unix ms when the SQS loop started; `0` when SQS is not in use.
+    sqs_started_at_ms: AtomicI64,
+    /// Unix ms of the last non-empty SQS receive; `0` before the first one.
+    last_sqs_message_at_ms: AtomicI64,
+}
+
+impl RuntimeHealth {
+    pub fn mark_sqs_started(&self) {
+        self.sqs_started_at_ms
+            .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    pub fn mark_sqs_message(&self) {
+        self.last_sqs_message_at_ms
+            .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    /// Whether SQS was already listening when a scan taken at `scan_time_ms`
+    /// began publishing, so the availability index saw all of its products.
+    pub fn sqs_covers(&self, scan_time_ms: i64) -> bool {
+        let started = self.sqs_started_at_ms.load(Ordering::Relaxed);
+        started != 0 && scan_time_ms >= started
+    }
+
+    /// Time since the last SQS message (or loop start, if none arrived yet);
+    /// `None` when SQS is not in use.
+    fn sqs_silence_ms(&self, now_ms: i64) -> Option<i64> {
+        let started = self.sqs_started_at_ms.load(Ordering::Relaxed);
+        if started == 0 {
+            return None;
+        }
+        let last_message = self.last_sqs_message_at_ms.load(Ordering::Relaxed);
+        Some(now_ms - started.max(last_message))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ReadinessLimits {
+    pub max_scan_age: Duration,
+    pub max_sqs_silence: Duration,
+    pub max_consecutive_failures: u32,
+}
+
+/// Readiness inputs for one domain.
+#[derive(Clone, Copy, Debug)]
+struct DomainReadiness {
+    domain: &'static str,
+    scan_time_ms: Option<i64>,
+    consecutive_failures: u32,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct NotReadyReason {
+    /// `no_scan`, `stale_scan`, `sqs_silent` or `ingest_failing`.
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    domain: Option<&'static str>,
+    detail: String,
+}
+
+/// `GET /readyz`: `200` when every domain has fresh data, SQS (if used) is
+/// delivering, and ingests are not failing back to back; `503` otherwise.
+pub async fn readyz(State(state): State<AppState>) -> Response {
+    let now_ms = Utc::now().timestamp_millis();
+    let mut domains = Vec::with_capacity(state.domains.len());
+    for domain_state in state.domains.values() {
+        domains.push(DomainReadiness {
+            domain: domain_state.domain.id(),
+            scan_time_ms: domain_state
+                .latest
+                .read()
+                .await
+                .as_ref()
+                .map(|scan| scan.scan_time_ms),
+            consecutive_failures: domain_state.consecutive_ingest_failures(),
+        });
+    }
+
+    let reasons = evaluate_readiness(
+        &state.cfg.readiness,
+        now_ms,
+        &domains,
+        state.health.sqs_silence_ms(now_ms),
+    );
+    let status = if reasons.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "ready": reasons.is_empty(),
+            "reasons": reasons,
+        })),
+    )
+        .into_response()
+}
+
+fn evaluate_readiness(
+    limits: &ReadinessLimits,
+    now_ms: i64,
+    domains: &[DomainReadiness],
+    sqs_silence_ms: Option<i64>,
+) -> Vec<NotReadyReason> {
+    let max_scan_age_ms = limits.max_scan_age.as_millis() as i64;
+    let mut reasons = Vec::new();
+    for status in domains {
+        match status.scan_time_ms {
+            None => reasons.push(NotReadyReason {
+                code: "no_scan",
+                domain: Some(status.domain),
+                detail: "No scan has been ingested yet.".to_string(),
+            }),
+            Some(scan_time_ms) if now_ms - scan_time_ms > max_scan_age_ms => {
+                reasons.push(NotReadyReason {
+                    code: "stale_scan",
+                    domain: Some(status.domain),
+                    detail: format!(
+                        "Latest scan is {}s old (limit {}s).",
+                        (now_ms - scan_time_ms) / 1000,
+                        limits.max_scan_age.as_secs()
+                    ),
+                })
+            }
+            Some(_) => {}
+        }
+        if status.consecutive_failures >= limits.max_consecutive_failures {
+            reasons.push(NotReadyReason {
+                code: "ingest_failing",
+                domain: Some(status.domain),
+                detail: format!(
+                    "{} consecutive ingest failures (limit {}).",
+                    status.consecutive_failures, limits.max_consecutive_failures
+                ),
+            });
+        }
+    }
+
+    if let Some(silence_ms) = sqs_silence_ms {
+        if silence_ms > limits.max_sqs_silence.as_millis() as i64 {
+            reasons.push(NotReadyReason {
+                code: "sqs_silent",
+                domain: None,
+                detail: format!(
+                    "No SQS message for {}s (limit {}s).",
+                    silence_ms / 1000,
+                    limits.max_sqs_silence.as_secs()
+                ),
+            });
+        }
+    }
+    reasons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluate_readiness_reports_each_failed_check() {
+        let limits = ReadinessLimits {
+            max_scan_age: Duration::from_secs(900),
+            max_sqs_silence: Duration::from_secs(600),
+            max_consecutive_failures: 5,
+        };
+        let now_ms = 10_000_000;
+        let fresh = DomainReadiness {
+            domain: "conus",
+            scan_time_ms: Some(now_ms - 120_000),
+            consecutive_failures: 0,
+        };
+        assert!(evaluate_readiness(&limits, now_ms, &[fresh], Some(30_000)).is_empty());
+        assert!(evaluate_readiness(&limits, now_ms, &[fresh], None).is_empty());
+
+        let stale = DomainReadiness {
+            domain: "alaska",
+            scan_time_ms: Some(now_ms - 1_000_000),
+            consecutive_failures: 5,
+        };
+        let empty = DomainReadiness {
+            domain: "hawaii",
+            scan_time_ms: None,
+            consecutive_failures: 0,
+        };
+        let codes = evaluate_readiness(&limits, now_ms, &[fresh, stale, empty], Some(700_000))
+            .into_iter()
+            .map(|reason| (reason.code, reason.domain))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            vec![
+                ("stale_scan", Some("alaska")),
+                ("ingest_failing", Some("alaska")),
+                ("no_scan", Some("hawaii")),
+                ("sqs_silent", None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use reqwest::Client;
+use tokio::sync::Mutex;
+use tracing::{instrument, warn};
+
+use crate::constants::{LISTING_CACHE_MAX_ENTRIES, MAX_LIST_PAGES};
+use crate::http_client::fetch_text;
+
+/// Bucket listing API flavour. `V2` is `ListObjectsV2` (`list-type=2` with
+/// continuation tokens); `V1` is the legacy marker-based `ListObjects` that some
+/// caching proxies and older S3-compatible servers only understand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum S3ListStyle {
+    V2,
+    V1,
+}
+
+impl S3ListStyle {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "v2" | "list-objects-v2" => Some(Self::V2),
+            "v1" | "list-objects" => Some(Self::V1),
+            _ => None,
+        }
+    }
+}
+
+struct CachedListing {
+    fetched_at: Instant,
+    keys: Arc<Vec<String>>,
+}
+
+/// Lists bucket prefixes and caches each prefix's full key list for `ttl`, so
+/// the ~15 aux-product lookups made per ingest do not re-list the same day.
+pub struct S3ListingClient {
+    http: Client,
+    bucket_url: String,
+    list_style: S3ListStyle,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, CachedListing>>,
+}
+
+impl S3ListingClient {
+    pub fn new(http: Client, bucket_url: &str, list_style: S3ListStyle, ttl: Duration) -> Self {
+        Self {
+            http,
+            bucket_url: bucket_url.trim_end_matches('/').to_string(),
+            list_style,
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn bucket_url(&self) -> &str {
+        &self.bucket_url
+    }
+
+    pub async fn list_keys(&self, prefix: &str) -> Result<Vec<String>> {
+        if let Some(cached) = self.cache.lock().await.get(prefix) {
+            if cached.fetched_at.elapsed() < self.ttl {
+                return Ok(cached.keys.as_ref().clone());
+            }
+        }
+
+        let keys = Arc::new(self.list_uncached(prefix, false).await?.keys);
+        if !self.ttl.is_zero() {
+            let mut cache = self.cache.lock().await;
+            cache.retain(|_, entry| entry.fetched_at.elapsed() < self.ttl);
+            if cache.len() >= LISTING_CACHE_MAX_ENTRIES {
+                cache.clear();
+            }
+            cache.insert(
+                prefix.to_string(),
+                CachedListing {
+                    fetched_at: Instant::now(),
+                    keys: keys.clone(),
+                },
+            );
+        }
+        Ok(keys.as_ref().clone())
+    }
+
+    /// Immediate sub-prefixes of `prefix` (`/`-delimited), uncached.
+    pub async fn list_common_prefixes(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self.list_uncached(prefix, true).await?.common_prefixes)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_uncached(&self, prefix: &str, delimited: bool) -> Result<ListPage> {
+        let mut listing = ListPage::default();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..MAX_LIST_PAGES {
+            let url = build_list_url(
+                &self.bucket_url,
+                self.list_style,
+                prefix,
+                delimited,
+                cursor.as_deref(),
+            );
+            let xml = fetch_text(&self.http, &url).await?;
+            let page = parse_list_page(&xml)
+                .with_context(|| format!("Failed to parse bucket listing for {prefix}"))?;
+
+            cursor = match self.list_style {
+                S3ListStyle::V2 => page.next_continuation_token,
+                // NextMarker is only returned when a delimiter is set; otherwise
+                // the last key of the page is the marker.
+                S3ListStyle::V1 => page.next_marker.or_else(|| page.keys.last().cloned()),
+            };
+            listing.keys.extend(page.keys);
+            listing.common_prefixes.extend(page.common_prefixes);
+
+            if !page.is_truncated {
+                return Ok(listing);
+            }
+            if cursor.is_none() {
+                bail!("Truncated bucket listing for {prefix} has no continuation cursor");
+            }
+        }
+
+        warn!("Bucket listing for {prefix} stopped after {MAX_LIST_PAGES} pages");
+        Ok(listing)
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ListPage {
+    keys: Vec<String>,
+    common_prefixes: Vec<String>,
+    is_truncated: bool,
+    next_continuation_token: Option<String>,
+    next_marker: Option<String>,
+}
+
+fn parse_list_page(xml: &str) -> Result<ListPage> {
+    let document = roxmltree::Document::parse(xml).context("Invalid listing XML")?;
+    let root = document.root_element();
+    if root.tag_name().name() != "ListBucketResult" {
+        bail!(
+            "Unexpected listing root element <{}>",
+            root.tag_name().name()
+        );
+    }
+
+    let mut page = ListPage::default();
+    for node in root.children().filter(|node| node.is_element()) {
+        match node.tag_name().name() {
+            "Contents" => {
+                if let Some(key) = node
+                    .children()
+                    .find(|child| child.has_tag_name("Key"))
+                    .and_then(|child| child.text())
+                {
+                    page.keys.push(key.to_string());
+                }
+            }
+            "CommonPrefixes" => {
+                if let Some(prefix) = node
+                    .children()
+                    .find(|child| child.has_tag_name("Prefix"))
+                    .and_then(|child| child.text())
+                {
+                    page.common_prefixes.push(prefix.to_string());
+                }
+            }
+            "IsTruncated" => page.is_truncated = node.text() == Some("true"),
+            "NextContinuationToken" => {
+                page.next_continuation_token = node.text().map(str::to_string)
+            }
+            "NextMarker" => page.next_marker = node.text().map(str::to_string),
+            _ => {}
+        }
+    }
+    Ok(page)
+}
+
+fn build_list_url(
+    bucket_url: &str,
+    list_style: S3ListStyle,
+    prefix: &str,
+    delimited: bool,
+    cursor: Option<&str>,
+) -> String {
+    let mut url = match list_style {
+        S3ListStyle::V2 => format!(
+            "{bucket_url}/?list-type=2&prefix={}&max-keys=1000",
+            urlencoding::encode(prefix)
+        ),
+        S3ListStyle::V1 => format!(
+            "{bucket_url}/?prefix={}&max-keys=1000",
+            urlencoding::encode(prefix)
+        ),
+    };
+    if delimited {
+        url.push_str("&delimiter=%2F");
+    }
+    if let Some(cursor) = cursor {
+        url.push_str(match list_style {
+            S3ListStyle::V2 => "&continuation-token=",
+            S3ListStyle::V1 => "&marker=",
+        });
+        url.push_str(&urlencoding::encode(cursor));
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_urls_follow_the_configured_style() {
+        let prefix = "CONUS/MergedReflectivityQC_00.50/20260212/";
+        assert_eq!(
+            build_list_url("http://minio:9000/mrms", S3ListStyle::V2, prefix, false, Some("a b")),
+            "http://minio:9000/mrms/?list-type=2&prefix=CONUS%2FMergedReflectivityQC_00.50%2F20260212%2F&max-keys=1000&continuation-token=a%20b"
+        );
+        assert_eq!(
+            build_list_url("http://proxy", S3ListStyle::V1, prefix, false, Some("CONUS/x")),
+            "http://proxy/?prefix=CONUS%2FMergedReflectivityQC_00.50%2F20260212%2F&max-keys=1000&marker=CONUS%2Fx"
+        );
+        assert_eq!(
+            build_list_url("http://proxy", S3ListStyle::V2, "ALASKA/", true, None),
+            "http://proxy/?list-type=2&prefix=ALASKA%2F&max-keys=1000&delimiter=%2F"
+        );
+        assert_eq!(S3ListStyle::parse("V1"), Some(S3ListStyle::V1));
+        assert_eq!(S3ListStyle::parse("v3"), None);
+    }
+
+    #[test]
+    fn parse_list_page_reads_namespaced_listing() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>noaa-mrms-pds</Name>
+  <Prefix>CONUS/</Prefix>
+  <IsTruncated>true</IsTruncated>
+  <NextContinuationToken>1a+b/c==</NextContinuationToken>
+  <Contents><Key>CONUS/a_20260212-120000.grib2.gz</Key><Size>10</Size></Contents>
+  <Contents><Key>CONUS/b&amp;c_20260212-120200.grib2.gz</Key></Contents>
+  <CommonPrefixes><Prefix>CONUS/MergedReflectivityQC_00.50/</Prefix></CommonPrefixes>
+</ListBucketResult>"#;
+
+        let page = parse_list_page(xml).expect("valid listing");
+        assert_eq!(
+            page.keys,
+            vec![
+                "CONUS/a_20260212-120000.grib2.gz",
+                "CONUS/b&c_20260212-120200.grib2.gz"
+            ]
+        );
+        assert_eq!(
+            page.common_prefixes,
+            vec!["CONUS/MergedReflectivityQC_00.50/"]
+        );
+        assert!(page.is_truncated);
+        assert_eq!(page.next_continuation_token.as_deref(), Some("1a+b/c=="));
+        assert!(parse_list_page("<Error><Code>NoSuchBucket</Code></Error>").is_err());
+    }
+}
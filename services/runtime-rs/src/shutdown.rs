@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::time::sleep;
+use tracing::warn;
+
+/// Owner side of the shutdown signal; `trigger` tells every background loop
+/// and the HTTP server to wind down.
+pub struct Shutdown {
+    sender: watch::Sender<bool>,
+    grace: Duration,
+}
+
+impl Shutdown {
+    pub fn new(grace: Duration) -> Self {
+        let (sender, _) = watch::channel(false);
+        Self { sender, grace }
+    }
+
+    pub fn signal(&self) -> ShutdownSignal {
+        ShutdownSignal {
+            receiver: self.sender.subscribe(),
+            grace: self.grace,
+        }
+    }
+
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+}
+
+#[derive(Clone)]
+pub struct ShutdownSignal {
+    receiver: watch::Receiver<bool>,
+    grace: Duration,
+}
+
+impl ShutdownSignal {
+    pub fn is_triggered(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// Resolves once shutdown starts (or the `Shutdown` owner is dropped).
+    pub async fn triggered(&mut self) {
+        let _ = self.receiver.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Resolves when in-flight work should be abandoned: the grace period
+    /// after shutdown started.
+    pub async fn grace_elapsed(&mut self) {
+        self.triggered().await;
+        sleep(self.grace).await;
+    }
+}
+
+/// Waits for SIGTERM (systemd stop) or Ctrl-C.
+pub async fn wait_for_os_signal() {
+    let ctrl_c = async {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl-C: {error}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(error) => {
+                warn!("Failed to listen for SIGTERM: {error}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
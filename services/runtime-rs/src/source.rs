@@ -0,0 +1,218 @@
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use reqwest::Client;
+use tokio::fs;
+use tracing::{info_span, Instrument};
+
+use crate::http_client::fetch_bytes;
+use crate::s3_listing::{S3ListStyle, S3ListingClient};
+
+/// Where ingest reads MRMS objects from. Keys always use the bucket layout
+/// (`CONUS/MergedReflectivityQC_00.50/20260212/MRMS_..._20260212-120000.grib2.gz`).
+pub trait IngestSource: Send + Sync {
+    fn describe(&self) -> String;
+
+    fn fetch_object<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<u8>>>;
+
+    /// Keys under `prefix`, in no particular order.
+    fn list_keys<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<Vec<String>>>;
+
+    /// Immediate sub-prefixes of `prefix`, each ending in `/`, e.g.
+    /// `ALASKA/MergedReflectivityQC_00.50/` under `ALASKA/`.
+    fn list_subprefixes<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<Vec<String>>>;
+}
+
+pub struct S3HttpSource {
+    http: Client,
+    listing: S3ListingClient,
+}
+
+impl S3HttpSource {
+    pub fn new(
+        http: Client,
+        bucket_url: &str,
+        list_style: S3ListStyle,
+        listing_cache_ttl: Duration,
+    ) -> Self {
+        Self {
+            listing: S3ListingClient::new(http.clone(), bucket_url, list_style, listing_cache_ttl),
+            http,
+        }
+    }
+}
+
+impl IngestSource for S3HttpSource {
+    fn describe(&self) -> String {
+        self.listing.bucket_url().to_string()
+    }
+
+    fn fetch_object<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<u8>>> {
+        async move { fetch_bytes(&self.http, &format!("{}/{key}", self.listing.bucket_url())).await }
+            .instrument(info_span!("fetch_object", key))
+            .boxed()
+    }
+
+    fn list_keys<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        async move { self.listing.list_keys(prefix).await }.boxed()
+    }
+
+    fn list_subprefixes<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        async move { self.listing.list_common_prefixes(prefix).await }.boxed()
+    }
+}
+
+/// Reads `.grib2.gz` files from a directory tree that mirrors the bucket key
+/// layout, for offline demos, replays and network-free tests.
+pub struct LocalDirSource {
+    root: PathBuf,
+}
+
+impl LocalDirSource {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn resolve(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        if relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            bail!("Refusing to read non-relative key {key}");
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+impl IngestSource for LocalDirSource {
+    fn describe(&self) -> String {
+        format!("file://{}", self.root.display())
+    }
+
+    fn fetch_object<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<u8>>> {
+        async move {
+            let path = self.resolve(key)?;
+            fs::read(&path)
+                .await
+                .with_context(|| format!("Failed to read {}", path.display()))
+        }
+        .instrument(info_span!("fetch_object", key))
+        .boxed()
+    }
+
+    fn list_keys<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        async move {
+            // Walk from the deepest directory named by the prefix, then filter
+            // by the full prefix to mimic S3 prefix semantics.
+            let dir_part = match prefix.rfind('/') {
+                Some(idx) => &prefix[..idx],
+                None => "",
+            };
+            let start_dir = if dir_part.is_empty() {
+                self.root.clone()
+            } else {
+                self.resolve(dir_part)?
+            };
+
+            let mut keys = Vec::new();
+            let mut stack = vec![(start_dir, dir_part.to_string())];
+            while let Some((dir, key_prefix)) = stack.pop() {
+                let mut entries = match fs::read_dir(&dir).await {
+                    Ok(entries) => entries,
+                    Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(error) => {
+                        return Err(error)
+                            .with_context(|| format!("Failed to read {}", dir.display()))
+                    }
+                };
+                while let Some(entry) = entries.next_entry().await? {
+                    let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                        continue;
+                    };
+                    let key = if key_prefix.is_empty() {
+                        name
+                    } else {
+                        format!("{key_prefix}/{name}")
+                    };
+                    if entry.file_type().await?.is_dir() {
+                        stack.push((entry.path(), key));
+                    } else if key.starts_with(prefix) {
+                        keys.push(key);
+                    }
+                }
+            }
+            Ok(keys)
+        }
+        .boxed()
+    }
+
+    fn list_subprefixes<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        async move {
+            let dir = self.resolve(prefix.trim_end_matches('/'))?;
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(error) => {
+                    return Err(error).with_context(|| format!("Failed to read {}", dir.display()))
+                }
+            };
+            let mut prefixes = Vec::new();
+            while let Some(entry) = entries.next_entry().await? {
+                if !entry.file_type().await?.is_dir() {
+                    continue;
+                }
+                if let Some(name) = entry.file_name().to_str() {
+                    prefixes.push(format!("{prefix}{name}/"));
+                }
+            }
+            Ok(prefixes)
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_dir_source_lists_and_reads_bucket_layout() {
+        let root =
+            std::env::temp_dir().join(format!("approach-viz-local-source-{}", std::process::id()));
+        let day_dir = root.join("CONUS/MergedReflectivityQC_00.50/20260212");
+        std::fs::create_dir_all(&day_dir).unwrap();
+        std::fs::write(
+            day_dir.join("MRMS_MergedReflectivityQC_00.50_20260212-120000.grib2.gz"),
+            b"grib",
+        )
+        .unwrap();
+
+        let source = LocalDirSource::new(root.clone());
+        let mut keys = source
+            .list_keys("CONUS/MergedReflectivityQC_00.50/20260212/")
+            .await
+            .unwrap();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["CONUS/MergedReflectivityQC_00.50/20260212/MRMS_MergedReflectivityQC_00.50_20260212-120000.grib2.gz"]
+        );
+        assert_eq!(source.fetch_object(&keys[0]).await.unwrap(), b"grib");
+        assert_eq!(
+            source.list_subprefixes("CONUS/").await.unwrap(),
+            vec!["CONUS/MergedReflectivityQC_00.50/"]
+        );
+        assert!(source
+            .list_keys("ALASKA/MergedReflectivityQC_00.50/20260212/")
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(source.fetch_object("../etc/passwd").await.is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
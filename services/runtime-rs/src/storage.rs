@@ -1,17 +1,23 @@
-use std::io::Cursor;
+use std::collections::HashMap;
+use std::io::{Cursor, ErrorKind};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{bail, Context, Result};
 use bincode::config::standard as bincode_config;
 use bincode::serde::{decode_from_slice, encode_to_vec};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tokio::fs;
 use tracing::{info, warn};
 
 use crate::config::Config;
-use crate::constants::{SNAPSHOT_MAGIC, SNAPSHOT_VERSION};
-use crate::types::ScanSnapshot;
+use crate::constants::{PENDING_QUEUE_FILE_VERSION, SNAPSHOT_MAGIC, SNAPSHOT_VERSION};
+use crate::dead_letter::AttemptError;
+use crate::domain::MrmsDomain;
+use crate::metrics::{IngestStage, METRICS};
+use crate::types::{PendingIngest, ScanSnapshot};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct SnapshotFile {
@@ -20,8 +26,11 @@ struct SnapshotFile {
     payload: ScanSnapshot,
 }
 
-pub async fn load_latest_snapshot(cfg: &Config) -> Result<Option<Arc<ScanSnapshot>>> {
-    let scans_dir = cfg.scans_dir();
+pub async fn load_latest_snapshot(
+    cfg: &Config,
+    domain: MrmsDomain,
+) -> Result<Option<Arc<ScanSnapshot>>> {
+    let scans_dir = cfg.domain_scans_dir(domain);
     if !Path::new(&scans_dir).exists() {
         return Ok(None);
     }
@@ -75,7 +84,12 @@ async fn load_snapshot_file(path: &Path) -> Result<ScanSnapshot> {
     Ok(snapshot_file.payload)
 }
 
-pub async fn persist_snapshot(cfg: &Config, snapshot: Arc<ScanSnapshot>) -> Result<()> {
+pub async fn persist_snapshot(
+    cfg: &Config,
+    domain: MrmsDomain,
+    snapshot: Arc<ScanSnapshot>,
+) -> Result<()> {
+    let started = Instant::now();
     let file = SnapshotFile {
         magic: SNAPSHOT_MAGIC,
         version: SNAPSHOT_VERSION,
@@ -86,7 +100,7 @@ pub async fn persist_snapshot(cfg: &Config, snapshot: Arc<ScanSnapshot>) -> Resu
     let compressed = zstd::stream::encode_all(Cursor::new(encoded), 6)
         .context("Failed to zstd-compress snapshot")?;
 
-    let scans_dir = cfg.scans_dir();
+    let scans_dir = cfg.domain_scans_dir(domain);
     fs::create_dir_all(&scans_dir)
         .await
         .with_context(|| format!("Failed to create {}", scans_dir.display()))?;
@@ -105,12 +119,14 @@ pub async fn persist_snapshot(cfg: &Config, snapshot: Arc<ScanSnapshot>) -> Resu
         )
     })?;
 
-    apply_retention(cfg).await?;
+    apply_retention(cfg, domain).await?;
+    METRICS.observe_stage(domain, IngestStage::Persist, started);
     Ok(())
 }
 
-async fn apply_retention(cfg: &Config) -> Result<()> {
-    let scans_dir = cfg.scans_dir();
+async fn apply_retention(cfg: &Config, domain: MrmsDomain) -> Result<()> {
+    let scans_dir = cfg.domain_scans_dir(domain);
+    let retention_bytes = cfg.retention_bytes_for(domain);
     let mut dir = fs::read_dir(&scans_dir)
         .await
         .with_context(|| format!("Failed to read {}", scans_dir.display()))?;
@@ -129,13 +145,14 @@ async fn apply_retention(cfg: &Config) -> Result<()> {
         files.push((path, len));
     }
 
-    if total_bytes <= cfg.retention_bytes {
+    if total_bytes <= retention_bytes {
+        METRICS.set_snapshot_bytes(domain, total_bytes);
         return Ok(());
     }
 
     files.sort_by(|left, right| left.0.cmp(&right.0));
     for (path, len) in files {
-        if total_bytes <= cfg.retention_bytes {
+        if total_bytes <= retention_bytes {
             break;
         }
         if let Err(error) = fs::remove_file(&path).await {
@@ -143,8 +160,180 @@ async fn apply_retention(cfg: &Config) -> Result<()> {
             continue;
         }
         total_bytes = total_bytes.saturating_sub(len);
+        METRICS.record_retention_prune(domain);
         info!("Pruned {} ({} bytes)", path.display(), len);
     }
+    METRICS.set_snapshot_bytes(domain, total_bytes);
 
     Ok(())
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingQueueFile {
+    version: u16,
+    saved_at: DateTime<Utc>,
+    entries: Vec<PersistedPendingIngest>,
+}
+
+/// `PendingIngest` with its monotonic instants replaced by wall-clock time.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedPendingIngest {
+    timestamp: String,
+    attempts: u32,
+    upgrade: bool,
+    first_seen_at: DateTime<Utc>,
+    errors: Vec<AttemptError>,
+}
+
+/// Writes the pending queue so a restart can pick it up; an empty queue
+/// removes any earlier file.
+pub async fn persist_pending_queue(
+    cfg: &Config,
+    domain: MrmsDomain,
+    pending: &HashMap<String, PendingIngest>,
+) -> Result<()> {
+    let path = cfg.pending_queue_path(domain);
+    if pending.is_empty() {
+        return match fs::remove_file(&path).await {
+            Err(error) if error.kind() != ErrorKind::NotFound => {
+                Err(error).with_context(|| format!("Failed removing {}", path.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+
+    let file = encode_pending_queue(pending, Instant::now(), Utc::now());
+    let encoded = serde_json::to_vec_pretty(&file).context("Failed to encode pending queue")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, encoded)
+        .await
+        .with_context(|| format!("Failed writing {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, &path).await.with_context(|| {
+        format!(
+            "Failed renaming {} -> {}",
+            tmp_path.display(),
+            path.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Loads and deletes the queue saved by `persist_pending_queue`. Entries are
+/// due immediately and keep their attempt count, errors and deadline start.
+pub async fn take_pending_queue(
+    cfg: &Config,
+    domain: MrmsDomain,
+) -> Result<HashMap<String, PendingIngest>> {
+    let path = cfg.pending_queue_path(domain);
+    let encoded = match fs::read(&path).await {
+        Ok(encoded) => encoded,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(error) => {
+            return Err(error).with_context(|| format!("Failed to read {}", path.display()))
+        }
+    };
+    fs::remove_file(&path)
+        .await
+        .with_context(|| format!("Failed removing {}", path.display()))?;
+
+    let file: PendingQueueFile = serde_json::from_slice(&encoded)
+        .with_context(|| format!("Failed to decode {}", path.display()))?;
+    if file.version != PENDING_QUEUE_FILE_VERSION {
+        bail!(
+            "Unsupported pending queue version {} in {}",
+            file.version,
+            path.display()
+        );
+    }
+    Ok(decode_pending_queue(file, Instant::now(), Utc::now()))
+}
+
+fn encode_pending_queue(
+    pending: &HashMap<String, PendingIngest>,
+    now: Instant,
+    now_utc: DateTime<Utc>,
+) -> PendingQueueFile {
+    let mut entries = pending
+        .iter()
+        .map(|(timestamp, entry)| PersistedPendingIngest {
+            timestamp: timestamp.clone(),
+            attempts: entry.attempts,
+            upgrade: entry.upgrade,
+            first_seen_at: now_utc
+                - chrono::Duration::from_std(now.saturating_duration_since(entry.first_seen_at))
+                    .unwrap_or_default(),
+            errors: entry.errors.clone(),
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    PendingQueueFile {
+        version: PENDING_QUEUE_FILE_VERSION,
+        saved_at: now_utc,
+        entries,
+    }
+}
+
+fn decode_pending_queue(
+    file: PendingQueueFile,
+    now: Instant,
+    now_utc: DateTime<Utc>,
+) -> HashMap<String, PendingIngest> {
+    file.entries
+        .into_iter()
+        .map(|entry| {
+            let waited = (now_utc - entry.first_seen_at).to_std().unwrap_or_default();
+            let pending = PendingIngest {
+                attempts: entry.attempts,
+                next_attempt_at: now,
+                first_seen_at: now.checked_sub(waited).unwrap_or(now),
+                upgrade: entry.upgrade,
+                errors: entry.errors,
+            };
+            (entry.timestamp, pending)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn pending_queue_round_trip_keeps_attempts_and_deadline_start() {
+        let now = Instant::now();
+        let now_utc = Utc::now();
+        let mut pending = HashMap::new();
+        let mut entry = PendingIngest::new(true, now + Duration::from_secs(30));
+        entry.first_seen_at = now - Duration::from_secs(90);
+        entry.attempts = 2;
+        entry.errors.push(AttemptError {
+            attempt: 2,
+            failed_at: now_utc,
+            error: "boom".to_string(),
+        });
+        pending.insert("20260212-000000".to_string(), entry);
+
+        let file = encode_pending_queue(&pending, now, now_utc);
+        let json = serde_json::to_vec(&file).expect("encodes");
+        let file: PendingQueueFile = serde_json::from_slice(&json).expect("decodes");
+
+        // Restored 10 s later: the entry is due now and has waited 100 s.
+        let later = now + Duration::from_secs(10);
+        let restored = decode_pending_queue(file, later, now_utc + chrono::Duration::seconds(10));
+        let entry = &restored["20260212-000000"];
+        assert_eq!(entry.attempts, 2);
+        assert!(entry.upgrade);
+        assert_eq!(entry.errors.len(), 1);
+        assert_eq!(entry.next_attempt_at, later);
+        let waited = later.duration_since(entry.first_seen_at).as_secs_f64();
+        assert!((waited - 100.0).abs() < 0.01, "waited {waited}");
+    }
+}
@@ -1,6 +1,7 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::io::Cursor;
+use std::sync::Arc;
 use std::time::Duration;
 
 use axum::extract::{Query, State};
@@ -11,11 +12,13 @@ use futures::future::join_all;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::coalesce::{CacheOutcome, CoalescingCache};
+use crate::metrics::METRICS;
 use crate::types::AppState;
 
 const DEFAULT_RADIUS_NM: f64 = 80.0;
-const MIN_RADIUS_NM: f64 = 5.0;
-const MAX_RADIUS_NM: f64 = 220.0;
+pub(crate) const MIN_RADIUS_NM: f64 = 5.0;
+pub(crate) const MAX_RADIUS_NM: f64 = 220.0;
 const DEFAULT_LIMIT: usize = 250;
 const MAX_LIMIT: usize = 800;
 const REQUEST_TIMEOUT_MS: u64 = 5500;
@@ -29,6 +32,10 @@ const BINCRAFT_MAX_STRIDE_BYTES: usize = 256;
 const BINCRAFT_S32_SEEN_VERSION: u32 = 20240218;
 const DEFAULT_HIDE_GROUND_TRAFFIC: bool = false;
 const EARTH_RADIUS_NM: f64 = 3440.065;
+/// Upstream boxes are widened to this grid so nearby viewers share a fetch.
+const TRAFFIC_BOX_ROUNDING_DEG: f64 = 0.25;
+const BINCRAFT_CACHE_TTL_MS: u64 = 2000;
+const TRACE_CACHE_TTL_MS: u64 = 10_000;
 
 const USER_AGENT: &str =
     "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36";
@@ -46,27 +53,60 @@ pub(crate) struct TrafficQuery {
     hide_ground: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct TrafficAircraft {
-    hex: String,
-    flight: Option<String>,
-    lat: f64,
-    lon: f64,
-    is_on_ground: bool,
-    altitude_feet: Option<f64>,
-    ground_speed_kt: Option<f64>,
-    track_deg: Option<f64>,
-    last_seen_seconds: Option<f64>,
+pub(crate) struct TrafficAircraft {
+    pub(crate) hex: String,
+    pub(crate) flight: Option<String>,
+    pub(crate) lat: f64,
+    pub(crate) lon: f64,
+    pub(crate) is_on_ground: bool,
+    pub(crate) altitude_feet: Option<f64>,
+    pub(crate) ground_speed_kt: Option<f64>,
+    pub(crate) track_deg: Option<f64>,
+    pub(crate) last_seen_seconds: Option<f64>,
+    /// Four octal digits, e.g. `7700`.
+    pub(crate) squawk: Option<String>,
+    pub(crate) baro_rate_fpm: Option<f64>,
+    pub(crate) geom_rate_fpm: Option<f64>,
+    /// ADS-B emitter category, e.g. `A3`.
+    pub(crate) category: Option<String>,
+    /// MCP/FCU selected altitude.
+    pub(crate) selected_altitude_feet: Option<f64>,
+    /// FMS target altitude.
+    pub(crate) nav_altitude_feet: Option<f64>,
+    pub(crate) selected_heading_deg: Option<f64>,
+    pub(crate) mag_heading_deg: Option<f64>,
+    pub(crate) true_heading_deg: Option<f64>,
+    pub(crate) ias_kt: Option<f64>,
+    pub(crate) tas_kt: Option<f64>,
+    /// Navigation integrity category (0-11).
+    pub(crate) nic: Option<u8>,
+    /// Navigation accuracy category for position (0-11).
+    pub(crate) nac_p: Option<u8>,
+}
+
+/// Airborne fixture at 3000 ft on the -122.4 meridian.
+#[cfg(test)]
+pub(crate) fn test_aircraft(hex: &str, lat: f64, last_seen_seconds: f64) -> TrafficAircraft {
+    TrafficAircraft {
+        hex: hex.to_string(),
+        lat,
+        lon: -122.4,
+        is_on_ground: false,
+        altitude_feet: Some(3000.0),
+        last_seen_seconds: Some(last_seen_seconds),
+        ..TrafficAircraft::default()
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct TrafficHistoryPoint {
-    lat: f64,
-    lon: f64,
-    altitude_feet: f64,
-    timestamp_ms: i64,
+pub(crate) struct TrafficHistoryPoint {
+    pub(crate) lat: f64,
+    pub(crate) lon: f64,
+    pub(crate) altitude_feet: f64,
+    pub(crate) timestamp_ms: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -88,17 +128,89 @@ struct TrafficErrorPayload {
 }
 
 #[derive(Debug, Clone, Copy)]
-struct BoundingBox {
+pub(crate) struct BoundingBox {
     south: f64,
     north: f64,
     west: f64,
     east: f64,
 }
 
+/// A query circle with its filters, clamped to the supported ranges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TrafficArea {
+    pub(crate) lat: f64,
+    pub(crate) lon: f64,
+    pub(crate) radius_nm: f64,
+    pub(crate) limit: usize,
+    pub(crate) hide_ground: bool,
+}
+
+impl TrafficArea {
+    pub(crate) fn new(
+        lat: f64,
+        lon: f64,
+        radius_nm: Option<f64>,
+        limit: Option<f64>,
+        hide_ground: bool,
+    ) -> Self {
+        Self {
+            lat,
+            lon,
+            radius_nm: clamp(
+                radius_nm.unwrap_or(DEFAULT_RADIUS_NM),
+                MIN_RADIUS_NM,
+                MAX_RADIUS_NM,
+            ),
+            limit: clamp_usize(
+                limit
+                    .map(|value| value.floor() as i64)
+                    .unwrap_or(DEFAULT_LIMIT as i64),
+                1,
+                MAX_LIMIT,
+            ),
+            hide_ground,
+        }
+    }
+}
+
+pub(crate) struct NearbyTraffic {
+    pub(crate) source: String,
+    /// Upstream the aircraft came from, for trace backfill; `None` when the
+    /// background poller answered and trails come from its own table.
+    pub(crate) upstream_base_url: Option<String>,
+    /// Whether this call fetched upstream itself; cache hits, coalesced
+    /// waits and poller answers cost upstream nothing.
+    pub(crate) upstream_fetched: bool,
+    pub(crate) aircraft: Vec<TrafficAircraft>,
+}
+
+/// Rounded box in `TRAFFIC_BOX_ROUNDING_DEG` steps: south, north, west, east.
+type BoxKey = (i32, i32, i32, i32);
+/// `(base_url, trace hex)`.
+type TraceKey = (String, String);
+
 #[derive(Debug)]
-struct TraceFetchResult {
-    hex: String,
-    points: Vec<TrafficHistoryPoint>,
+pub(crate) struct TrafficSnapshot {
+    pub(crate) source: String,
+    pub(crate) base_url: String,
+    pub(crate) aircraft: Vec<TrafficAircraft>,
+}
+
+/// Recently fetched binCraft boxes and traces, shared across requests so
+/// viewers of the same area do not multiply upstream calls.
+pub struct TrafficCache {
+    snapshots: Arc<CoalescingCache<BoxKey, Arc<TrafficSnapshot>>>,
+    /// Points are unfiltered by age; each request applies its own cutoff.
+    traces: Arc<CoalescingCache<TraceKey, Arc<Vec<TrafficHistoryPoint>>>>,
+}
+
+impl Default for TrafficCache {
+    fn default() -> Self {
+        Self {
+            snapshots: CoalescingCache::new(Duration::from_millis(BINCRAFT_CACHE_TTL_MS)),
+            traces: CoalescingCache::new(Duration::from_millis(TRACE_CACHE_TTL_MS)),
+        }
+    }
 }
 
 pub async fn traffic_adsbx(
@@ -121,58 +233,40 @@ pub async fn traffic_adsbx(
     let lat = lat.unwrap_or_default();
     let lon = lon.unwrap_or_default();
 
-    let radius_nm = clamp(
-        to_finite_number(query.radius_nm.as_deref()).unwrap_or(DEFAULT_RADIUS_NM),
-        MIN_RADIUS_NM,
-        MAX_RADIUS_NM,
-    );
-    let limit = clamp_usize(
-        to_finite_number(query.limit.as_deref())
-            .map(|value| value.floor() as i64)
-            .unwrap_or(DEFAULT_LIMIT as i64),
-        1,
-        MAX_LIMIT,
+    let area = TrafficArea::new(
+        lat,
+        lon,
+        to_finite_number(query.radius_nm.as_deref()),
+        to_finite_number(query.limit.as_deref()),
+        parse_boolean_query_param(query.hide_ground.as_deref(), DEFAULT_HIDE_GROUND_TRAFFIC),
     );
     let history_minutes = clamp(
         to_finite_number(query.history_minutes.as_deref()).unwrap_or(0.0),
         0.0,
         MAX_HISTORY_MINUTES,
     );
-    let hide_ground_traffic =
-        parse_boolean_query_param(query.hide_ground.as_deref(), DEFAULT_HIDE_GROUND_TRAFFIC);
-
-    let bounds = build_bounding_box(lat, lon, radius_nm);
-
-    let fetch_result = fetch_adsbx_traffic(&state, bounds).await;
-    match fetch_result {
-        Ok((source, base_url, mut aircraft)) => {
-            aircraft.retain(|candidate| {
-                distance_nm(lat, lon, candidate.lat, candidate.lon) <= radius_nm
-                    && (!hide_ground_traffic || !candidate.is_on_ground)
-            });
 
-            aircraft.sort_by(|left, right| {
-                let left_seen = left.last_seen_seconds.unwrap_or(f64::INFINITY);
-                let right_seen = right.last_seen_seconds.unwrap_or(f64::INFINITY);
-                left_seen
-                    .partial_cmp(&right_seen)
-                    .unwrap_or(Ordering::Equal)
-            });
-            aircraft.truncate(limit);
-
-            let history_by_hex = if history_minutes > 0.0 {
-                fetch_recent_trace_history(&state, &base_url, &aircraft, history_minutes).await
-            } else {
+    match nearby_traffic(&state, area).await {
+        Ok(nearby) => {
+            let history_by_hex = if history_minutes <= 0.0 {
                 HashMap::new()
+            } else if let Some(base_url) = &nearby.upstream_base_url {
+                fetch_recent_trace_history(&state, base_url, &nearby.aircraft, history_minutes)
+                    .await
+            } else {
+                let history_cutoff_ms = now_ms() - (history_minutes * 60_000.0) as i64;
+                state
+                    .traffic_store
+                    .history(&nearby.aircraft, history_cutoff_ms)
             };
 
             (
                 StatusCode::OK,
                 no_store_headers(),
                 Json(TrafficSuccessPayload {
-                    source,
+                    source: nearby.source,
                     fetched_at_ms: now_ms(),
-                    aircraft,
+                    aircraft: nearby.aircraft,
                     history_by_hex,
                 }),
             )
@@ -192,22 +286,89 @@ pub async fn traffic_adsbx(
     }
 }
 
-async fn fetch_adsbx_traffic(
+/// Aircraft in `area`, from the background poller when one of its regions
+/// covers the area, else from a (cached, coalesced) binCraft fetch.
+pub(crate) async fn nearby_traffic(
+    state: &AppState,
+    area: TrafficArea,
+) -> Result<NearbyTraffic, String> {
+    if let Some((source, mut aircraft)) = state.traffic_store.covering_snapshot(
+        &state.cfg.traffic_poll,
+        area.lat,
+        area.lon,
+        area.radius_nm,
+        now_ms(),
+    ) {
+        select_aircraft(&mut aircraft, area);
+        return Ok(NearbyTraffic {
+            source,
+            upstream_base_url: None,
+            upstream_fetched: false,
+            aircraft,
+        });
+    }
+
+    let (box_key, bounds) =
+        round_bounding_box(build_bounding_box(area.lat, area.lon, area.radius_nm));
+    let fetch_state = state.clone();
+    let (fetch_result, outcome) = state
+        .traffic_cache
+        .snapshots
+        .get_or_fetch(box_key, async move {
+            fetch_adsbx_traffic(&fetch_state, bounds)
+                .await
+                .map(Arc::new)
+        })
+        .await;
+    METRICS.record_traffic_cache("bincraft", outcome);
+    let snapshot = fetch_result?;
+    let mut aircraft = snapshot.aircraft.clone();
+    select_aircraft(&mut aircraft, area);
+    Ok(NearbyTraffic {
+        source: snapshot.source.clone(),
+        upstream_base_url: Some(snapshot.base_url.clone()),
+        upstream_fetched: outcome == CacheOutcome::Miss,
+        aircraft,
+    })
+}
+
+/// Keeps aircraft inside the area (optionally airborne only), freshest
+/// first, up to its limit.
+fn select_aircraft(aircraft: &mut Vec<TrafficAircraft>, area: TrafficArea) {
+    aircraft.retain(|candidate| {
+        distance_nm(area.lat, area.lon, candidate.lat, candidate.lon) <= area.radius_nm
+            && (!area.hide_ground || !candidate.is_on_ground)
+    });
+
+    aircraft.sort_by(|left, right| {
+        let left_seen = left.last_seen_seconds.unwrap_or(f64::INFINITY);
+        let right_seen = right.last_seen_seconds.unwrap_or(f64::INFINITY);
+        left_seen
+            .partial_cmp(&right_seen)
+            .unwrap_or(Ordering::Equal)
+    });
+    aircraft.truncate(area.limit);
+}
+
+pub(crate) async fn fetch_adsbx_traffic(
     state: &AppState,
     bounds: BoundingBox,
-) -> Result<(String, String, Vec<TrafficAircraft>), String> {
+) -> Result<TrafficSnapshot, String> {
     let mut errors = Vec::new();
     for base_url in state.cfg.traffic_base_urls() {
         let request_url = format!("{base_url}/re-api/?binCraft&zstd&box={}", box_param(bounds));
-        match fetch_bincraft(&state, &request_url, &base_url).await {
+        match fetch_bincraft(state, &request_url, &base_url).await {
             Ok(aircraft) => {
-                return Ok((
-                    format!("{base_url} (/re-api binCraft+zstd)"),
+                return Ok(TrafficSnapshot {
+                    source: format!("{base_url} (/re-api binCraft+zstd)"),
                     base_url,
                     aircraft,
-                ))
+                })
+            }
+            Err(error) => {
+                METRICS.record_traffic_upstream_failure(&base_url, "bincraft");
+                errors.push(format!("{base_url}: {error}"));
             }
-            Err(error) => errors.push(format!("{base_url}: {error}")),
         }
     }
 
@@ -266,7 +427,9 @@ fn decode_bincraft_aircraft(payload: &[u8]) -> Result<Vec<TrafficAircraft>, Stri
     }
 
     let stride = read_u32_le(&decoded, 8).unwrap_or(0) as usize;
-    if stride < BINCRAFT_MIN_STRIDE_BYTES || stride > BINCRAFT_MAX_STRIDE_BYTES || stride % 4 != 0 {
+    if !(BINCRAFT_MIN_STRIDE_BYTES..=BINCRAFT_MAX_STRIDE_BYTES).contains(&stride)
+        || !stride.is_multiple_of(4)
+    {
         return Err(format!("Unexpected binCraft stride: {stride}"));
     }
 
@@ -284,12 +447,10 @@ fn decode_bincraft_aircraft(payload: &[u8]) -> Result<Vec<TrafficAircraft>, Stri
             continue;
         }
 
-        let lat = read_i32_le(u8, 12)
-            .map(|value| normalize_lat_value(value as f64 / 1_000_000.0))
-            .flatten();
-        let lon = read_i32_le(u8, 8)
-            .map(|value| normalize_lon_value(value as f64 / 1_000_000.0))
-            .flatten();
+        let lat =
+            read_i32_le(u8, 12).and_then(|value| normalize_lat_value(value as f64 / 1_000_000.0));
+        let lon =
+            read_i32_le(u8, 8).and_then(|value| normalize_lon_value(value as f64 / 1_000_000.0));
         let (lat, lon) = match (lat, lon) {
             (Some(lat), Some(lon)) => (lat, lon),
             _ => {
@@ -349,6 +510,45 @@ fn decode_bincraft_aircraft(payload: &[u8]) -> Result<Vec<TrafficAircraft>, Stri
         let airground = u8[68] & 15;
         let is_on_ground = airground == 1;
 
+        let (validity74, validity75, validity76, validity77) = (u8[74], u8[75], u8[76], u8[77]);
+        let squawk = when_valid(validity76, 4, || {
+            read_u16_le(u8, 32).map(|value| format!("{value:04x}"))
+        });
+        let baro_rate_fpm = when_valid(validity75, 1, || {
+            read_i16_le(u8, 16).map(|value| f64::from(value) * 8.0)
+        });
+        let geom_rate_fpm = when_valid(validity75, 2, || {
+            read_i16_le(u8, 18).map(|value| f64::from(value) * 8.0)
+        });
+        let category = Some(u8[64])
+            .filter(|value| *value != 0)
+            .map(|value| format!("{value:X}"));
+        let selected_altitude_feet = when_valid(validity76, 64, || {
+            read_u16_le(u8, 24)
+                .and_then(|value| normalize_altitude_feet_value(f64::from(value) * 4.0))
+        });
+        let nav_altitude_feet = when_valid(validity76, 128, || {
+            read_u16_le(u8, 26)
+                .and_then(|value| normalize_altitude_feet_value(f64::from(value) * 4.0))
+        });
+        let selected_heading_deg = when_valid(validity77, 2, || {
+            read_i16_le(u8, 30).and_then(|value| normalize_heading_value(f64::from(value) / 90.0))
+        });
+        let mag_heading_deg = when_valid(validity74, 64, || {
+            read_i16_le(u8, 44).and_then(|value| normalize_heading_value(f64::from(value) / 90.0))
+        });
+        let true_heading_deg = when_valid(validity74, 128, || {
+            read_i16_le(u8, 46).and_then(|value| normalize_heading_value(f64::from(value) / 90.0))
+        });
+        let ias_kt = when_valid(validity74, 1, || {
+            read_u16_le(u8, 58).and_then(|value| normalize_speed_kt(f64::from(value)))
+        });
+        let tas_kt = when_valid(validity74, 2, || {
+            read_u16_le(u8, 56).and_then(|value| normalize_speed_kt(f64::from(value)))
+        });
+        let nic = Some(u8[65]);
+        let nac_p = when_valid(validity75, 32, || Some(u8[71] & 15));
+
         let seen_seconds = if version >= BINCRAFT_S32_SEEN_VERSION {
             read_i32_le(u8, 4).map(|value| value as f64 / 10.0)
         } else {
@@ -374,6 +574,19 @@ fn decode_bincraft_aircraft(payload: &[u8]) -> Result<Vec<TrafficAircraft>, Stri
             ground_speed_kt,
             track_deg,
             last_seen_seconds,
+            squawk,
+            baro_rate_fpm,
+            geom_rate_fpm,
+            category,
+            selected_altitude_feet,
+            nav_altitude_feet,
+            selected_heading_deg,
+            mag_heading_deg,
+            true_heading_deg,
+            ias_kt,
+            tas_kt,
+            nic,
+            nac_p,
         };
 
         match by_hex.get(&hex) {
@@ -395,6 +608,15 @@ fn decode_bincraft_aircraft(payload: &[u8]) -> Result<Vec<TrafficAircraft>, Stri
     Ok(by_hex.into_values().collect())
 }
 
+/// `value()` when `bit` is set in a binCraft validity byte.
+fn when_valid<T>(validity: u8, bit: u8, value: impl FnOnce() -> Option<T>) -> Option<T> {
+    if (validity & bit) != 0 {
+        value()
+    } else {
+        None
+    }
+}
+
 fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
     data.get(offset..offset + 2)
         .map(|slice| u16::from_le_bytes([slice[0], slice[1]]))
@@ -444,13 +666,35 @@ async fn fetch_recent_trace_history(
     let mut history_by_hex: HashMap<String, Vec<TrafficHistoryPoint>> = HashMap::new();
 
     for batch in limited_aircraft.chunks(TRACE_HISTORY_BATCH_SIZE) {
-        let futures = batch.iter().map(|entry| {
-            fetch_trace_history_for_hex(state, base_url, &entry.hex, history_cutoff_ms)
+        let futures = batch.iter().map(|entry| async {
+            let key = (base_url.to_string(), entry.hex.clone());
+            let fetch_state = state.clone();
+            let fetch_base_url = base_url.to_string();
+            let fetch_hex = entry.hex.clone();
+            let (result, outcome) = state
+                .traffic_cache
+                .traces
+                .get_or_fetch(key, async move {
+                    fetch_trace_history_for_hex(&fetch_state, &fetch_base_url, &fetch_hex)
+                        .await
+                        .map(Arc::new)
+                })
+                .await;
+            METRICS.record_traffic_cache("trace", outcome);
+            (entry.hex.clone(), result.unwrap_or_default())
         });
         let results = join_all(futures).await;
-        for result in results {
-            if !result.points.is_empty() {
-                history_by_hex.insert(result.hex, result.points);
+        for (hex, points) in results {
+            let mut points = points
+                .iter()
+                .filter(|point| point.timestamp_ms >= history_cutoff_ms)
+                .cloned()
+                .collect::<Vec<_>>();
+            if points.len() > TRACE_HISTORY_MAX_POINTS_PER_AIRCRAFT {
+                points.drain(..points.len() - TRACE_HISTORY_MAX_POINTS_PER_AIRCRAFT);
+            }
+            if !points.is_empty() {
+                history_by_hex.insert(hex, points);
             }
         }
     }
@@ -462,13 +706,9 @@ async fn fetch_trace_history_for_hex(
     state: &AppState,
     base_url: &str,
     aircraft_hex: &str,
-    history_cutoff_ms: i64,
-) -> TraceFetchResult {
+) -> Result<Vec<TrafficHistoryPoint>, String> {
     let Some(trace_hex) = normalize_trace_hex(aircraft_hex) else {
-        return TraceFetchResult {
-            hex: aircraft_hex.to_string(),
-            points: Vec::new(),
-        };
+        return Ok(Vec::new());
     };
 
     let trace_url = format!(
@@ -483,33 +723,22 @@ async fn fetch_trace_history_for_hex(
         .headers(build_fetch_headers(base_url))
         .send()
         .await;
-    let Ok(response) = response else {
-        return TraceFetchResult {
-            hex: aircraft_hex.to_string(),
-            points: Vec::new(),
-        };
-    };
+    let response = response.map_err(|error| {
+        METRICS.record_traffic_upstream_failure(base_url, "trace");
+        error.to_string()
+    })?;
     if !response.status().is_success() {
-        return TraceFetchResult {
-            hex: aircraft_hex.to_string(),
-            points: Vec::new(),
-        };
+        return Ok(Vec::new());
     }
 
     let payload = response.json::<Value>().await;
     let Ok(payload) = payload else {
-        return TraceFetchResult {
-            hex: aircraft_hex.to_string(),
-            points: Vec::new(),
-        };
+        return Ok(Vec::new());
     };
 
     let base_timestamp_seconds = value_to_finite(payload.get("timestamp"));
     let Some(base_timestamp_seconds) = base_timestamp_seconds else {
-        return TraceFetchResult {
-            hex: aircraft_hex.to_string(),
-            points: Vec::new(),
-        };
+        return Ok(Vec::new());
     };
 
     let mut points = Vec::new();
@@ -541,10 +770,6 @@ async fn fetch_trace_history_for_hex(
             let Some(timestamp_ms) = timestamp_ms else {
                 continue;
             };
-            if timestamp_ms < history_cutoff_ms {
-                continue;
-            }
-
             points.push(TrafficHistoryPoint {
                 lat,
                 lon,
@@ -555,14 +780,7 @@ async fn fetch_trace_history_for_hex(
     }
 
     points.sort_by_key(|point| point.timestamp_ms);
-    if points.len() > TRACE_HISTORY_MAX_POINTS_PER_AIRCRAFT {
-        points = points[points.len() - TRACE_HISTORY_MAX_POINTS_PER_AIRCRAFT..].to_vec();
-    }
-
-    TraceFetchResult {
-        hex: aircraft_hex.to_string(),
-        points,
-    }
+    Ok(points)
 }
 
 fn normalize_trace_hex(hex: &str) -> Option<String> {
@@ -663,7 +881,7 @@ fn normalize_lon(raw: Option<&str>) -> Option<f64> {
     normalize_lon_value(parsed)
 }
 
-fn normalize_lat_value(parsed: f64) -> Option<f64> {
+pub(crate) fn normalize_lat_value(parsed: f64) -> Option<f64> {
     if (-90.0..=90.0).contains(&parsed) {
         Some(parsed)
     } else {
@@ -671,7 +889,7 @@ fn normalize_lat_value(parsed: f64) -> Option<f64> {
     }
 }
 
-fn normalize_lon_value(parsed: f64) -> Option<f64> {
+pub(crate) fn normalize_lon_value(parsed: f64) -> Option<f64> {
     if (-180.0..=180.0).contains(&parsed) {
         Some(parsed)
     } else {
@@ -744,7 +962,7 @@ fn normalize_timestamp_ms(value: f64) -> Option<i64> {
     Some(timestamp_ms)
 }
 
-fn now_ms() -> i64 {
+pub(crate) fn now_ms() -> i64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     let Ok(duration) = SystemTime::now().duration_since(UNIX_EPOCH) else {
         return 0;
@@ -756,7 +974,7 @@ fn to_radians(deg: f64) -> f64 {
     deg * std::f64::consts::PI / 180.0
 }
 
-fn distance_nm(lat_a: f64, lon_a: f64, lat_b: f64, lon_b: f64) -> f64 {
+pub(crate) fn distance_nm(lat_a: f64, lon_a: f64, lat_b: f64, lon_b: f64) -> f64 {
     let lat_a_rad = to_radians(lat_a);
     let lat_b_rad = to_radians(lat_b);
     let d_lat = lat_b_rad - lat_a_rad;
@@ -768,7 +986,7 @@ fn distance_nm(lat_a: f64, lon_a: f64, lat_b: f64, lon_b: f64) -> f64 {
     EARTH_RADIUS_NM * c
 }
 
-fn build_bounding_box(lat: f64, lon: f64, radius_nm: f64) -> BoundingBox {
+pub(crate) fn build_bounding_box(lat: f64, lon: f64, radius_nm: f64) -> BoundingBox {
     let lat_delta = radius_nm / 60.0;
     let lon_scale = lat.to_radians().cos().max(0.01);
     let lon_delta = radius_nm / (60.0 * lon_scale);
@@ -804,9 +1022,76 @@ fn build_bounding_box(lat: f64, lon: f64, radius_nm: f64) -> BoundingBox {
     }
 }
 
+/// Widens `bounds` outward to the `TRAFFIC_BOX_ROUNDING_DEG` grid and
+/// returns it with its cache key.
+fn round_bounding_box(bounds: BoundingBox) -> (BoxKey, BoundingBox) {
+    let steps = |value: f64, round: fn(f64) -> f64| round(value / TRAFFIC_BOX_ROUNDING_DEG) as i32;
+    let key = (
+        steps(bounds.south, f64::floor),
+        steps(bounds.north, f64::ceil),
+        steps(bounds.west, f64::floor),
+        steps(bounds.east, f64::ceil),
+    );
+    let degrees = |steps: i32| f64::from(steps) * TRAFFIC_BOX_ROUNDING_DEG;
+    let rounded = BoundingBox {
+        south: degrees(key.0).max(-90.0),
+        north: degrees(key.1).min(90.0),
+        west: degrees(key.2).max(-180.0),
+        east: degrees(key.3).min(180.0),
+    };
+    (key, rounded)
+}
+
 fn box_param(bounds: BoundingBox) -> String {
     format!(
         "{:.6},{:.6},{:.6},{:.6}",
         bounds.south, bounds.north, bounds.west, bounds.east
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_extended_bincraft_fields() {
+        let stride = BINCRAFT_MIN_STRIDE_BYTES;
+        let mut raw = vec![0_u8; stride * 2];
+        raw[8..12].copy_from_slice(&(stride as u32).to_le_bytes());
+        raw[40..44].copy_from_slice(&BINCRAFT_S32_SEEN_VERSION.to_le_bytes());
+
+        let record = &mut raw[stride..];
+        record[0..4].copy_from_slice(&0x00a1_b2c3_i32.to_le_bytes());
+        record[8..12].copy_from_slice(&(-122_375_000_i32).to_le_bytes());
+        record[12..16].copy_from_slice(&37_619_000_i32.to_le_bytes());
+        record[16..18].copy_from_slice(&(-100_i16).to_le_bytes());
+        record[20..22].copy_from_slice(&140_i16.to_le_bytes());
+        record[24..26].copy_from_slice(&1250_u16.to_le_bytes());
+        record[32..34].copy_from_slice(&0x7700_u16.to_le_bytes());
+        record[58..60].copy_from_slice(&250_u16.to_le_bytes());
+        record[64] = 0xa3;
+        record[65] = 8;
+        record[71] = 0xa9;
+        record[73] = 64 | 16;
+        record[74] = 1;
+        record[75] = 1 | 32;
+        record[76] = 4 | 64;
+
+        let payload = zstd::stream::encode_all(Cursor::new(raw), 3).expect("zstd encode");
+        let aircraft = decode_bincraft_aircraft(&payload).expect("valid binCraft");
+        assert_eq!(aircraft.len(), 1);
+        let aircraft = &aircraft[0];
+        assert_eq!(aircraft.hex, "a1b2c3");
+        assert_eq!(aircraft.altitude_feet, Some(3500.0));
+        assert_eq!(aircraft.squawk.as_deref(), Some("7700"));
+        assert_eq!(aircraft.baro_rate_fpm, Some(-800.0));
+        assert_eq!(aircraft.geom_rate_fpm, None);
+        assert_eq!(aircraft.category.as_deref(), Some("A3"));
+        assert_eq!(aircraft.selected_altitude_feet, Some(5000.0));
+        assert_eq!(aircraft.nav_altitude_feet, None);
+        assert_eq!(aircraft.ias_kt, Some(250.0));
+        assert_eq!(aircraft.tas_kt, None);
+        assert_eq!(aircraft.nic, Some(8));
+        assert_eq!(aircraft.nac_p, Some(9));
+    }
+}
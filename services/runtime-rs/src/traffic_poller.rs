@@ -0,0 +1,318 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::constants::{
+    TRAFFIC_POLL_FRESH_INTERVALS, TRAFFIC_TRACK_RETENTION, TRAFFIC_TRACK_STALE_AFTER,
+};
+use crate::metrics::METRICS;
+use crate::shutdown::ShutdownSignal;
+use crate::traffic_api::{
+    build_bounding_box, distance_nm, fetch_adsbx_traffic, now_ms, TrafficAircraft,
+    TrafficHistoryPoint, TrafficSnapshot, MAX_RADIUS_NM, MIN_RADIUS_NM,
+};
+use crate::types::AppState;
+
+/// An area polled in the background, written `name:lat:lon:radiusNm`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrafficPollRegion {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub radius_nm: f64,
+}
+
+impl TrafficPollRegion {
+    pub fn parse(entry: &str) -> Result<Self, String> {
+        let parts = entry.split(':').map(str::trim).collect::<Vec<_>>();
+        let [name, lat, lon, radius_nm] = parts[..] else {
+            return Err(format!(
+                "must list regions as name:lat:lon:radiusNm, got '{entry}'"
+            ));
+        };
+        let number = |value: &str| value.parse::<f64>().ok().filter(|value| value.is_finite());
+        let (Some(lat), Some(lon), Some(radius_nm)) = (number(lat), number(lon), number(radius_nm))
+        else {
+            return Err(format!("has a non-numeric coordinate in '{entry}'"));
+        };
+        if name.is_empty() || !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return Err(format!("has an invalid name or position in '{entry}'"));
+        }
+        if !(MIN_RADIUS_NM..=MAX_RADIUS_NM).contains(&radius_nm) {
+            return Err(format!(
+                "radius must be between {MIN_RADIUS_NM} and {MAX_RADIUS_NM} nm, got '{entry}'"
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            lat,
+            lon,
+            radius_nm,
+        })
+    }
+
+    /// Whether a query circle lies entirely inside this region.
+    fn covers(&self, lat: f64, lon: f64, radius_nm: f64) -> bool {
+        distance_nm(self.lat, self.lon, lat, lon) + radius_nm <= self.radius_nm
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TrafficPollConfig {
+    /// The poller is off when empty.
+    pub regions: Vec<TrafficPollRegion>,
+    pub interval: Duration,
+    /// Positions kept per aircraft for trails.
+    pub history_points: usize,
+}
+
+struct TrackedAircraft {
+    latest: TrafficAircraft,
+    /// Wall-clock time of `latest`'s position.
+    position_ms: i64,
+    history: VecDeque<TrafficHistoryPoint>,
+}
+
+struct RegionPoll {
+    source: String,
+    polled_at_ms: i64,
+}
+
+#[derive(Default)]
+struct TrafficStoreInner {
+    aircraft: HashMap<String, TrackedAircraft>,
+    regions: HashMap<String, RegionPoll>,
+}
+
+/// Aircraft seen by the background poller, keyed by hex, with their recent
+/// positions. Queries inside a freshly polled region are answered from here.
+#[derive(Default)]
+pub struct TrafficStore {
+    inner: Mutex<TrafficStoreInner>,
+}
+
+impl TrafficStore {
+    fn record_poll(
+        &self,
+        region: &TrafficPollRegion,
+        snapshot: &TrafficSnapshot,
+        polled_at_ms: i64,
+        history_points: usize,
+    ) {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        for aircraft in &snapshot.aircraft {
+            let position_ms = polled_at_ms
+                - (aircraft.last_seen_seconds.unwrap_or_default() * 1000.0).round() as i64;
+            let tracked = inner
+                .aircraft
+                .entry(aircraft.hex.clone())
+                .or_insert_with(|| TrackedAircraft {
+                    latest: aircraft.clone(),
+                    position_ms,
+                    history: VecDeque::new(),
+                });
+            if position_ms < tracked.position_ms {
+                continue;
+            }
+            tracked.latest = aircraft.clone();
+            tracked.position_ms = position_ms;
+
+            // Trails follow tar1090 traces, which skip ground positions.
+            let Some(altitude_feet) = aircraft.altitude_feet.filter(|_| !aircraft.is_on_ground)
+            else {
+                continue;
+            };
+            if tracked
+                .history
+                .back()
+                .is_some_and(|point| point.timestamp_ms >= position_ms)
+            {
+                continue;
+            }
+            tracked.history.push_back(TrafficHistoryPoint {
+                lat: aircraft.lat,
+                lon: aircraft.lon,
+                altitude_feet,
+                timestamp_ms: position_ms,
+            });
+            while tracked.history.len() > history_points {
+                tracked.history.pop_front();
+            }
+        }
+        inner.regions.insert(
+            region.name.clone(),
+            RegionPoll {
+                source: snapshot.source.clone(),
+                polled_at_ms,
+            },
+        );
+    }
+
+    /// Drops tracks whose last position is older than the longest trail.
+    fn prune(&self, now_ms: i64) -> usize {
+        let cutoff_ms = now_ms - TRAFFIC_TRACK_RETENTION.as_millis() as i64;
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner
+            .aircraft
+            .retain(|_, tracked| tracked.position_ms >= cutoff_ms);
+        inner.aircraft.len()
+    }
+
+    /// Source and current aircraft when a freshly polled region covers the
+    /// query circle; `None` means the caller should fetch upstream.
+    pub fn covering_snapshot(
+        &self,
+        cfg: &TrafficPollConfig,
+        lat: f64,
+        lon: f64,
+        radius_nm: f64,
+        now_ms: i64,
+    ) -> Option<(String, Vec<TrafficAircraft>)> {
+        let fresh_after_ms =
+            now_ms - (cfg.interval * TRAFFIC_POLL_FRESH_INTERVALS).as_millis() as i64;
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let (region, poll) = cfg
+            .regions
+            .iter()
+            .filter(|region| region.covers(lat, lon, radius_nm))
+            .find_map(|region| {
+                let poll = inner.regions.get(&region.name)?;
+                (poll.polled_at_ms >= fresh_after_ms).then_some((region, poll))
+            })?;
+
+        let stale_before_ms = now_ms - TRAFFIC_TRACK_STALE_AFTER.as_millis() as i64;
+        let aircraft = inner
+            .aircraft
+            .values()
+            .filter(|tracked| tracked.position_ms >= stale_before_ms)
+            .map(|tracked| TrafficAircraft {
+                last_seen_seconds: Some((now_ms - tracked.position_ms).max(0) as f64 / 1000.0),
+                ..tracked.latest.clone()
+            })
+            .collect();
+        Some((
+            format!("{} via poller region {}", poll.source, region.name),
+            aircraft,
+        ))
+    }
+
+    /// Trail points since `cutoff_ms` for each listed aircraft that has any.
+    pub fn history(
+        &self,
+        aircraft: &[TrafficAircraft],
+        cutoff_ms: i64,
+    ) -> HashMap<String, Vec<TrafficHistoryPoint>> {
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        aircraft
+            .iter()
+            .filter_map(|entry| {
+                let tracked = inner.aircraft.get(&entry.hex)?;
+                let points = tracked
+                    .history
+                    .iter()
+                    .filter(|point| point.timestamp_ms >= cutoff_ms)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                (!points.is_empty()).then(|| (entry.hex.clone(), points))
+            })
+            .collect()
+    }
+}
+
+/// Starts the region poller when `RUNTIME_TRAFFIC_POLL_REGIONS` is set. It
+/// returns once `shutdown` fires.
+pub fn spawn_traffic_poller(state: AppState, shutdown: ShutdownSignal) -> Option<JoinHandle<()>> {
+    if state.cfg.traffic_poll.regions.is_empty() {
+        return None;
+    }
+    info!(
+        "Polling traffic for {} region(s) every {}s",
+        state.cfg.traffic_poll.regions.len(),
+        state.cfg.traffic_poll.interval.as_secs()
+    );
+    Some(tokio::spawn(traffic_poll_loop(state, shutdown)))
+}
+
+async fn traffic_poll_loop(state: AppState, mut shutdown: ShutdownSignal) {
+    let cfg = &state.cfg.traffic_poll;
+    while !shutdown.is_triggered() {
+        for region in &cfg.regions {
+            let bounds = build_bounding_box(region.lat, region.lon, region.radius_nm);
+            match fetch_adsbx_traffic(&state, bounds).await {
+                Ok(snapshot) => {
+                    state.traffic_store.record_poll(
+                        region,
+                        &snapshot,
+                        now_ms(),
+                        cfg.history_points,
+                    );
+                }
+                Err(error) => warn!("Traffic poll for region {} failed: {error}", region.name),
+            }
+        }
+        let tracked = state.traffic_store.prune(now_ms());
+        METRICS.traffic_tracked_aircraft.set(tracked as i64);
+
+        tokio::select! {
+            () = sleep(cfg.interval) => {}
+            () = shutdown.triggered() => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic_api::test_aircraft as aircraft;
+
+    #[test]
+    fn polls_build_trails_and_answer_covered_queries() {
+        let region = TrafficPollRegion::parse("KSFO:37.6:-122.4:40").expect("valid region");
+        let cfg = TrafficPollConfig {
+            regions: vec![region.clone()],
+            interval: Duration::from_secs(5),
+            history_points: 2,
+        };
+        let store = TrafficStore::default();
+        let t0 = 1_700_000_000_000;
+        for (step, lat) in [37.50, 37.55, 37.60].into_iter().enumerate() {
+            let snapshot = TrafficSnapshot {
+                source: "upstream".to_string(),
+                base_url: "https://example.com".to_string(),
+                aircraft: vec![aircraft("a1b2c3", lat, 1.0)],
+            };
+            store.record_poll(&region, &snapshot, t0 + step as i64 * 5_000, 2);
+        }
+
+        let now = t0 + 12_000;
+        let (source, current) = store
+            .covering_snapshot(&cfg, 37.6, -122.4, 20.0, now)
+            .expect("region covers the query");
+        assert_eq!(source, "upstream via poller region KSFO");
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].lat, 37.60);
+        assert_eq!(current[0].last_seen_seconds, Some(3.0));
+
+        let trails = store.history(&current, t0);
+        let lats = trails["a1b2c3"]
+            .iter()
+            .map(|point| point.lat)
+            .collect::<Vec<_>>();
+        assert_eq!(lats, vec![37.55, 37.60]);
+
+        // Circles reaching outside the region, or a stale poll, go upstream.
+        assert!(store
+            .covering_snapshot(&cfg, 37.6, -122.4, 60.0, now)
+            .is_none());
+        assert!(store
+            .covering_snapshot(&cfg, 37.6, -122.4, 20.0, now + 60_000)
+            .is_none());
+
+        assert_eq!(store.prune(now + 31 * 60_000), 0);
+        assert!(TrafficPollRegion::parse("KSFO:37.6:-122.4").is_err());
+    }
+}
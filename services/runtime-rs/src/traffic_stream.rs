@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use axum::Extension;
+use serde::{Deserialize, Serialize};
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::access::TrafficStreamClient;
+use crate::constants::TRAFFIC_STREAM_UPDATE_INTERVAL;
+use crate::metrics::METRICS;
+use crate::shutdown::ShutdownSignal;
+use crate::traffic_api::{
+    nearby_traffic, normalize_lat_value, normalize_lon_value, now_ms, TrafficAircraft, TrafficArea,
+};
+use crate::types::AppState;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ClientMessage {
+    /// Starts or replaces the subscription.
+    Subscribe(Subscription),
+    Unsubscribe,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Subscription {
+    lat: f64,
+    lon: f64,
+    radius_nm: Option<f64>,
+    limit: Option<f64>,
+    #[serde(default)]
+    hide_ground: bool,
+}
+
+impl Subscription {
+    fn area(&self) -> Result<TrafficArea, String> {
+        match (normalize_lat_value(self.lat), normalize_lon_value(self.lon)) {
+            (Some(lat), Some(lon)) => Ok(TrafficArea::new(
+                lat,
+                lon,
+                self.radius_nm,
+                self.limit,
+                self.hide_ground,
+            )),
+            _ => Err("Valid lat/lon are required.".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ServerMessage {
+    #[serde(rename_all = "camelCase")]
+    Delta {
+        /// `None` for the removal sent on unsubscribe.
+        source: Option<String>,
+        fetched_at_ms: i64,
+        #[serde(flatten)]
+        delta: AircraftDelta,
+    },
+    Error {
+        error: String,
+    },
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct AircraftDelta {
+    added: Vec<TrafficAircraft>,
+    /// Aircraft whose state changed; unchanged ones are not resent.
+    moved: Vec<TrafficAircraft>,
+    /// Hex ids that left the area or were no longer reported.
+    removed: Vec<String>,
+}
+
+impl AircraftDelta {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.moved.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Updates `known` to `current` and returns what changed. `lastSeenSeconds`
+/// alone ticking up does not count as a move.
+fn diff_aircraft(
+    known: &mut HashMap<String, TrafficAircraft>,
+    current: Vec<TrafficAircraft>,
+) -> AircraftDelta {
+    let mut delta = AircraftDelta::default();
+    let mut next = HashMap::with_capacity(current.len());
+    for aircraft in current {
+        match known.remove(&aircraft.hex) {
+            None => delta.added.push(aircraft.clone()),
+            Some(previous) => {
+                let unchanged = TrafficAircraft {
+                    last_seen_seconds: previous.last_seen_seconds,
+                    ..aircraft.clone()
+                } == previous;
+                if !unchanged {
+                    delta.moved.push(aircraft.clone());
+                }
+            }
+        }
+        next.insert(aircraft.hex.clone(), aircraft);
+    }
+    delta.removed = known.drain().map(|(hex, _)| hex).collect();
+    delta.removed.sort();
+    *known = next;
+    delta
+}
+
+pub async fn traffic_stream(
+    State(state): State<AppState>,
+    Extension(shutdown): Extension<ShutdownSignal>,
+    client: Option<Extension<Arc<TrafficStreamClient>>>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let client = client.map(|Extension(client)| client);
+    upgrade.on_upgrade(move |socket| stream_traffic(state, socket, shutdown, client))
+}
+
+/// `client` holds the stream's slot and is charged for each upstream fetch;
+/// `None` when the request had no identifiable client.
+async fn stream_traffic(
+    state: AppState,
+    mut socket: WebSocket,
+    mut shutdown: ShutdownSignal,
+    client: Option<Arc<TrafficStreamClient>>,
+) {
+    METRICS.traffic_stream_connections.inc();
+    let mut area: Option<TrafficArea> = None;
+    let mut known = HashMap::new();
+    let mut paused_until: Option<Instant> = None;
+    let mut ticker = interval(TRAFFIC_STREAM_UPDATE_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        let outgoing = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(ClientMessage::Subscribe(subscription)) => match subscription.area() {
+                        Ok(next) => {
+                            area = Some(next);
+                            ticker.reset_immediately();
+                            None
+                        }
+                        Err(error) => Some(ServerMessage::Error { error }),
+                    },
+                    Ok(ClientMessage::Unsubscribe) => {
+                        area = None;
+                        let delta = diff_aircraft(&mut known, Vec::new());
+                        (!delta.is_empty()).then(|| ServerMessage::Delta {
+                            source: None,
+                            fetched_at_ms: now_ms(),
+                            delta,
+                        })
+                    }
+                    Err(error) => Some(ServerMessage::Error {
+                        error: format!("Invalid message: {error}"),
+                    }),
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => None,
+            },
+            _ = ticker.tick(), if area.is_some() => {
+                let Some(area) = area else { continue };
+                if paused_until.is_some_and(|until| Instant::now() < until) {
+                    continue;
+                }
+                match nearby_traffic(&state, area).await {
+                    Ok(nearby) => {
+                        let charged = match &client {
+                            Some(client) if nearby.upstream_fetched => {
+                                client.charge_upstream_fetch()
+                            }
+                            _ => Ok(()),
+                        };
+                        match charged {
+                            Ok(()) => {
+                                let delta = diff_aircraft(&mut known, nearby.aircraft);
+                                (!delta.is_empty()).then(|| ServerMessage::Delta {
+                                    source: Some(nearby.source),
+                                    fetched_at_ms: now_ms(),
+                                    delta,
+                                })
+                            }
+                            // Over budget: drop this fetch and skip ticks until a
+                            // token is available again.
+                            Err(retry_after) => {
+                                paused_until = Some(Instant::now() + retry_after);
+                                let seconds = retry_after.as_secs_f64().ceil().max(1.0);
+                                Some(ServerMessage::Error {
+                                    error: format!(
+                                        "Rate limit exceeded; updates resume in {seconds}s."
+                                    ),
+                                })
+                            }
+                        }
+                    }
+                    Err(error) => Some(ServerMessage::Error { error }),
+                }
+            }
+            () = shutdown.triggered() => {
+                let _ = socket
+                    .send(Message::Close(Some(CloseFrame {
+                        code: close_code::AWAY,
+                        reason: "Server shutting down".into(),
+                    })))
+                    .await;
+                break;
+            }
+        };
+
+        let Some(outgoing) = outgoing else {
+            continue;
+        };
+        let Ok(text) = serde_json::to_string(&outgoing) else {
+            continue;
+        };
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            break;
+        }
+    }
+    METRICS.traffic_stream_connections.dec();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic_api::test_aircraft as aircraft;
+
+    #[test]
+    fn deltas_report_added_moved_and_removed_aircraft() {
+        let mut known = HashMap::new();
+        let first = diff_aircraft(
+            &mut known,
+            vec![aircraft("aaa111", 37.5, 1.0), aircraft("bbb222", 37.6, 1.0)],
+        );
+        assert_eq!(first.added.len(), 2);
+        assert!(first.moved.is_empty() && first.removed.is_empty());
+
+        let second = diff_aircraft(
+            &mut known,
+            vec![aircraft("aaa111", 37.5, 3.0), aircraft("bbb222", 37.7, 0.5)],
+        );
+        assert_eq!(second.moved, vec![aircraft("bbb222", 37.7, 0.5)]);
+        assert!(second.added.is_empty() && second.removed.is_empty());
+
+        let third = diff_aircraft(&mut known, vec![aircraft("ccc333", 37.8, 0.0)]);
+        assert_eq!(third.added, vec![aircraft("ccc333", 37.8, 0.0)]);
+        assert_eq!(third.removed, vec!["aaa111", "bbb222"]);
+        assert_eq!(known.len(), 1);
+
+        let message = serde_json::from_str::<ClientMessage>(
+            r#"{"type":"subscribe","lat":37.6,"lon":-122.4,"radiusNm":30,"hideGround":true}"#,
+        )
+        .expect("valid subscribe");
+        let ClientMessage::Subscribe(subscription) = message else {
+            panic!("expected subscribe");
+        };
+        let area = subscription.area().expect("valid area");
+        assert_eq!(area.radius_nm, 30.0);
+        assert!(area.hide_ground);
+    }
+}
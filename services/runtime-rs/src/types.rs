@@ -1,20 +1,118 @@
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, PoisonError};
 use std::time::Instant;
 
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tokio::sync::{Mutex, RwLock};
 
+use crate::availability::AvailabilityIndex;
 use crate::config::Config;
+use crate::dead_letter::{AttemptError, DeadLetterLog};
+use crate::domain::{route_domain, MrmsDomain};
+use crate::readiness::RuntimeHealth;
+use crate::source::IngestSource;
+use crate::traffic_api::TrafficCache;
+use crate::traffic_poller::TrafficStore;
 
 #[derive(Clone)]
 pub struct AppState {
     pub cfg: Arc<Config>,
     pub http: Client,
-    pub latest: Arc<RwLock<Option<Arc<ScanSnapshot>>>>,
-    pub pending: Arc<Mutex<HashMap<String, PendingIngest>>>,
-    pub recent_timestamps: Arc<Mutex<HashSet<String>>>,
+    pub source: Arc<dyn IngestSource>,
+    pub domains: Arc<BTreeMap<MrmsDomain, Arc<DomainState>>>,
+    pub health: Arc<RuntimeHealth>,
+    pub traffic_cache: Arc<TrafficCache>,
+    pub traffic_store: Arc<TrafficStore>,
+}
+
+/// Ingest and query state for one MRMS domain.
+pub struct DomainState {
+    pub domain: MrmsDomain,
+    pub latest: RwLock<Option<Arc<ScanSnapshot>>>,
+    pub pending: Mutex<HashMap<String, PendingIngest>>,
+    pub recent_timestamps: Mutex<HashSet<String>>,
+    pub availability: AvailabilityIndex,
+    pub dead_letters: DeadLetterLog,
+    /// Scheduler ingest failures since the last success; reported by `/readyz`.
+    consecutive_ingest_failures: AtomicU32,
+    level_tags: std::sync::RwLock<Arc<[String]>>,
+    level_tags_discovered: AtomicBool,
+}
+
+impl DomainState {
+    pub fn new(domain: MrmsDomain, latest: Option<Arc<ScanSnapshot>>) -> Self {
+        let builtin_tags: Vec<String> = domain
+            .builtin_level_tags()
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect();
+        Self {
+            domain,
+            level_tags: std::sync::RwLock::new(builtin_tags.into()),
+            level_tags_discovered: AtomicBool::new(false),
+            latest: RwLock::new(latest),
+            pending: Mutex::new(HashMap::new()),
+            recent_timestamps: Mutex::new(HashSet::new()),
+            availability: AvailabilityIndex::default(),
+            dead_letters: DeadLetterLog::default(),
+            consecutive_ingest_failures: AtomicU32::new(0),
+        }
+    }
+
+    pub fn record_ingest_result(&self, ok: bool) {
+        if ok {
+            self.consecutive_ingest_failures.store(0, Ordering::Relaxed);
+        } else {
+            self.consecutive_ingest_failures
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn consecutive_ingest_failures(&self) -> u32 {
+        self.consecutive_ingest_failures.load(Ordering::Relaxed)
+    }
+
+    /// Reflectivity heights published for this domain, lowest first: the
+    /// listed set once discovery succeeded, else the built-in list.
+    pub fn level_tags(&self) -> Arc<[String]> {
+        self.level_tags
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn set_discovered_level_tags(&self, tags: Vec<String>) {
+        *self
+            .level_tags
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = tags.into();
+        self.level_tags_discovered.store(true, Ordering::Relaxed);
+    }
+
+    /// False until a bucket listing replaced the built-in level tags; the
+    /// bootstrap loop keeps retrying discovery until then.
+    pub fn level_tags_discovered(&self) -> bool {
+        self.level_tags_discovered.load(Ordering::Relaxed)
+    }
+
+    /// Reflectivity and dual-pol level products not yet seen for `timestamp`.
+    pub fn missing_scan_products(&self, timestamp: &str) -> Vec<String> {
+        self.availability
+            .missing_scan_products(&self.level_tags(), timestamp)
+    }
+}
+
+impl AppState {
+    pub fn domain_state(&self, domain: MrmsDomain) -> Option<&Arc<DomainState>> {
+        self.domains.get(&domain)
+    }
+
+    /// Domain state serving queries whose origin is at `lat`/`lon`.
+    pub fn domain_state_for_position(&self, lat: f64, lon: f64) -> Option<&Arc<DomainState>> {
+        route_domain(&self.cfg.mrms_domains, lat, lon).and_then(|domain| self.domain_state(domain))
+    }
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
@@ -74,12 +172,88 @@ pub struct ScanSnapshot {
     pub echo_top_debug: EchoTopDebugMetadata,
     #[serde(default)]
     pub phase_debug: PhaseDebugMetadata,
+    /// Level indices whose reflectivity file was unavailable at ingest.
+    #[serde(default)]
+    pub missing_levels: Vec<u8>,
+}
+
+impl ScanSnapshot {
+    /// Bit `i` is set when level `i` is present (levels beyond 64 are not representable).
+    pub fn level_present_mask(&self) -> u64 {
+        let mut mask = 0_u64;
+        for level_idx in 0..self.level_bounds.len().min(64) {
+            if !self.missing_levels.contains(&(level_idx as u8)) {
+                mask |= 1 << level_idx;
+            }
+        }
+        mask
+    }
+}
+
+/// Single-tile fixture of `nx` x `ny` cells starting at 40N 105W on a 0.01
+/// degree grid, with `level_count` stacked 2000 ft levels.
+#[cfg(test)]
+pub(crate) fn test_scan(
+    nx: u32,
+    ny: u32,
+    level_count: u16,
+    voxels: Vec<StoredVoxel>,
+) -> ScanSnapshot {
+    ScanSnapshot {
+        timestamp: "20260212-123456".to_string(),
+        generated_at_ms: 0,
+        scan_time_ms: 0,
+        grid: GridDef {
+            nx,
+            ny,
+            la1_deg: 40.0,
+            lo1_deg360: 255.0,
+            di_deg: 0.01,
+            dj_deg: 0.01,
+            scanning_mode: 0,
+            lat_step_deg: -0.01,
+            lon_step_deg: 0.01,
+        },
+        tile_size: 256,
+        tile_cols: 1,
+        tile_rows: 1,
+        level_bounds: (0..level_count)
+            .map(|level_idx| LevelBounds {
+                bottom_feet: level_idx * 2000,
+                top_feet: (level_idx + 1) * 2000,
+            })
+            .collect(),
+        tile_offsets: vec![0, voxels.len() as u32],
+        voxels,
+        echo_tops: Vec::new(),
+        echo_top_debug: Default::default(),
+        phase_debug: Default::default(),
+        missing_levels: Vec::new(),
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct PendingIngest {
     pub attempts: u32,
     pub next_attempt_at: Instant,
+    /// Start of the completeness deadline (or upgrade window).
+    pub first_seen_at: Instant,
+    /// Re-ingest of a deadline scan once its missing levels arrive.
+    pub upgrade: bool,
+    /// One entry per failed attempt, carried into the dead letter on give-up.
+    pub errors: Vec<AttemptError>,
+}
+
+impl PendingIngest {
+    pub fn new(upgrade: bool, next_attempt_at: Instant) -> Self {
+        Self {
+            attempts: 0,
+            next_attempt_at,
+            first_seen_at: Instant::now(),
+            upgrade,
+            errors: Vec::new(),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -104,6 +278,10 @@ pub struct PhaseDebugMetadata {
     pub freezing_level_timestamp: Option<String>,
     pub zdr_age_seconds: Option<i64>,
     pub rhohv_age_seconds: Option<i64>,
+    /// `IngestPolicy` that triggered this ingest (`complete`, `deadline`, ...).
+    pub ingest_policy: String,
+    /// Reflectivity and dual-pol level products missing at ingest time.
+    pub missing_scan_products: u32,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -5,16 +5,6 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 
 use crate::constants::{DEG_TO_RAD, METERS_TO_NM, WGS84_E2, WGS84_SEMI_MAJOR_METERS};
 
-pub fn init_tracing() {
-    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
-    tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
-        .with_target(false)
-        .without_time()
-        .init();
-}
-
 pub fn clamp(value: f64, min_value: f64, max_value: f64) -> f64 {
     value.max(min_value).min(max_value)
 }
@@ -79,3 +69,26 @@ pub fn parse_timestamp_utc(timestamp: &str) -> Option<DateTime<Utc>> {
 pub fn iso_from_ms(timestamp_ms: i64) -> Option<String> {
     DateTime::<Utc>::from_timestamp_millis(timestamp_ms).map(|ts| ts.to_rfc3339())
 }
+
+/// Compares without short-circuiting on the first differing byte.
+pub fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0_u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_match_requires_exact_token() {
+        assert!(tokens_match("s3cret", "s3cret"));
+        assert!(!tokens_match("s3cres", "s3cret"));
+        assert!(!tokens_match("s3cret-longer", "s3cret"));
+        assert!(!tokens_match("", "s3cret"));
+    }
+}